@@ -0,0 +1,413 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::exporter::SharedSnapshot;
+use crate::json::{Document, FORMAT_VERSION};
+use crate::snapshot::*;
+use crate::source::{host_name, DataSource};
+
+// See "Agent protocol" in the README.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+pub const DEFAULT_AGENT_ADDRESS: &str = "127.0.0.1:9185";
+
+// An unreachable host must not hold up the interface for long.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Agents drop clients that send nothing for this long, or a request
+// longer than this many bytes.
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(120);
+pub const MAX_REQUEST_LEN: u64 = 4096;
+
+// Where an agent listens: "host:port", or "unix:/path/to/socket".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+impl FromStr for Endpoint {
+    type Err = String;
+
+    fn from_str(input: &str) -> std::result::Result<Self, String> {
+        if let Some(path) = input.strip_prefix("unix:") {
+            if !cfg!(unix) {
+                return Err("unix sockets are not supported on this platform".to_string());
+            }
+            if path.is_empty() {
+                return Err("expected a socket path after 'unix:'".to_string());
+            }
+            return Ok(Endpoint::Unix(PathBuf::from(path)));
+        }
+        match input.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(Endpoint::Tcp(input.to_string())),
+            _ => Err(format!("invalid address '{}' (expected host:port or unix:/path)", input)),
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Tcp(address) => write!(f, "{}", address),
+            Endpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+// First line an agent sends on every connection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hello {
+    pub protocol: u32,
+    // "systemmor 0.1.0"
+    pub agent: String,
+    pub hostname: String,
+}
+
+impl Hello {
+    fn local() -> Self {
+        Hello {
+            protocol: PROTOCOL_VERSION,
+            agent: format!("systemmor {}", env!("CARGO_PKG_VERSION")),
+            hostname: host_name().unwrap_or_else(|| "unknown".to_string()),
+        }
+    }
+}
+
+// `{"request": "snapshot"}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "lowercase")]
+pub enum Request {
+    Snapshot,
+}
+
+// `{"snapshot": {...}}` or `{"error": "..."}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Response<'a> {
+    Snapshot(Box<Document<'a>>),
+    Error(String),
+}
+
+// Serves the latest snapshot to any number of clients, each on its own
+// thread, until the process exits.
+pub struct Agent {
+    endpoint: Endpoint,
+}
+
+impl Agent {
+    // Binds right away so an address in use is reported at startup.
+    pub fn start(endpoint: &Endpoint, snapshot: SharedSnapshot) -> io::Result<Self> {
+        let hello = Hello::local();
+        match endpoint {
+            Endpoint::Tcp(address) => {
+                let listener = TcpListener::bind(address)?;
+                let bound = Endpoint::Tcp(listener.local_addr()?.to_string());
+                thread::spawn(move || {
+                    for stream in listener.incoming().flatten() {
+                        spawn_client(Stream::Tcp(stream), snapshot.clone(), hello.clone());
+                    }
+                });
+                Ok(Agent { endpoint: bound })
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                if path.exists() {
+                    if UnixStream::connect(path).is_ok() {
+                        return Err(io::Error::new(io::ErrorKind::AddrInUse, "another agent is listening on this socket"));
+                    }
+                    std::fs::remove_file(path)?;
+                }
+                let listener = UnixListener::bind(path)?;
+                thread::spawn(move || {
+                    for stream in listener.incoming().flatten() {
+                        spawn_client(Stream::Unix(stream), snapshot.clone(), hello.clone());
+                    }
+                });
+                Ok(Agent { endpoint: endpoint.clone() })
+            }
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => Err(io::Error::new(io::ErrorKind::Unsupported, "unix sockets are not supported on this platform")),
+        }
+    }
+
+    // The bound address, with the actual port when port 0 was asked for.
+    pub fn endpoint(&self) -> &Endpoint {
+        &self.endpoint
+    }
+}
+
+fn spawn_client(stream: Stream, snapshot: SharedSnapshot, hello: Hello) {
+    thread::spawn(move || {
+        // Errors only concern the one client.
+        let _ = serve_client(stream, &snapshot, &hello);
+    });
+}
+
+fn serve_client(stream: Stream, snapshot: &SharedSnapshot, hello: &Hello) -> io::Result<()> {
+    stream.set_timeouts(Some(CLIENT_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    writeln!(writer, "{}", serde_json::to_string(hello)?)?;
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = String::new();
+        // One byte past the limit tells a long request from one that fits.
+        let read = (&mut reader).take(MAX_REQUEST_LEN + 1).read_line(&mut line)?;
+        if read == 0 {
+            return Ok(());
+        }
+        if read as u64 > MAX_REQUEST_LEN {
+            let response = serde_json::to_string(&Response::Error(format!("request longer than {} bytes", MAX_REQUEST_LEN)))?;
+            writer.write_all(format!("{}\n", response).as_bytes())?;
+            return writer.flush();
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Snapshot) => {
+                let snapshot = snapshot.lock().map(|snapshot| snapshot.clone()).unwrap_or_default();
+                serde_json::to_string(&Response::Snapshot(Box::new(Document::new(&snapshot, &Subsystem::ALL))))?
+            }
+            Err(err) => serde_json::to_string(&Response::Error(format!("invalid request: {}", err)))?,
+        };
+        writer.write_all(format!("{}\n", response).as_bytes())?;
+        writer.flush()?;
+    }
+}
+
+// One connection to an agent.
+pub struct Client {
+    reader: BufReader<Stream>,
+    writer: Stream,
+    hello: Hello,
+}
+
+impl Client {
+    pub fn connect(endpoint: &Endpoint) -> Result<Self> {
+        let fail = |err: io::Error| Error::Remote(format!("cannot reach {}: {}", endpoint, err));
+        let stream = Stream::connect(endpoint).map_err(fail)?;
+        stream.set_timeouts(Some(REQUEST_TIMEOUT)).map_err(fail)?;
+        let writer = stream.try_clone().map_err(fail)?;
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(fail)?;
+        let hello: Hello = serde_json::from_str(&line)
+            .map_err(|err| Error::Remote(format!("{} is not a systemmor agent: {}", endpoint, err)))?;
+        if hello.protocol != PROTOCOL_VERSION {
+            return Err(Error::Remote(format!("{} speaks protocol {}, this build speaks {}", endpoint, hello.protocol, PROTOCOL_VERSION)));
+        }
+        Ok(Client { reader, writer, hello })
+    }
+
+    pub fn hello(&self) -> &Hello {
+        &self.hello
+    }
+
+    // The agent's latest snapshot. An error leaves the connection unusable.
+    pub fn snapshot(&mut self) -> Result<SystemSnapshot> {
+        let fail = |err: io::Error| Error::Remote(format!("{}: {}", self.hello.hostname, err));
+        let request = format!("{}\n", serde_json::to_string(&Request::Snapshot).unwrap_or_default());
+        self.writer.write_all(request.as_bytes()).and_then(|_| self.writer.flush()).map_err(fail)?;
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).map_err(fail)? == 0 {
+            return Err(Error::Remote(format!("{} closed the connection", self.hello.hostname)));
+        }
+        let invalid = |message: String| Error::Remote(format!("{} sent an invalid response: {}", self.hello.hostname, message));
+        match serde_json::from_str::<Response>(&line).map_err(|err| invalid(err.to_string()))? {
+            Response::Snapshot(document) if document.version > FORMAT_VERSION => {
+                Err(invalid(format!("format version {} is newer than this build supports ({})", document.version, FORMAT_VERSION)))
+            }
+            Response::Snapshot(document) => {
+                let mut snapshot = SystemSnapshot::default();
+                document.apply(&mut snapshot);
+                Ok(snapshot)
+            }
+            Response::Error(message) => Err(Error::Remote(message)),
+        }
+    }
+}
+
+// A remote machine as a `DataSource`. Snapshots are fetched on a
+// background thread, so a slow agent does not hold up the interface.
+pub struct RemoteSource {
+    hello: Hello,
+    latest: Arc<Mutex<Result<SystemSnapshot>>>,
+    requests: Sender<()>,
+    // What this sample reads.
+    current: Result<SystemSnapshot>,
+}
+
+impl RemoteSource {
+    // Connects right away, so a wrong address is reported at startup.
+    pub fn connect(endpoint: &Endpoint) -> Result<Self> {
+        let mut client = Client::connect(endpoint)?;
+        let hello = client.hello().clone();
+        let first = client.snapshot();
+        let client = first.is_ok().then_some(client);
+        let latest = Arc::new(Mutex::new(first));
+        let (requests, received) = mpsc::channel();
+        let (endpoint, shared) = (endpoint.clone(), latest.clone());
+        thread::spawn(move || fetch(endpoint, client, shared, received));
+        Ok(RemoteSource {
+            hello,
+            latest,
+            requests,
+            current: Ok(SystemSnapshot::default()),
+        })
+    }
+
+    pub fn hello(&self) -> &Hello {
+        &self.hello
+    }
+
+    fn read<T: Clone>(&self, subsystem: Subsystem, field: impl Fn(&SystemSnapshot) -> &T) -> Result<T> {
+        let snapshot = self.current.as_ref().map_err(Clone::clone)?;
+        match snapshot.error(subsystem) {
+            Some(err) => Err(err.clone()),
+            None => Ok(field(snapshot).clone()),
+        }
+    }
+}
+
+// Answers the requests of a `RemoteSource` until it is dropped.
+fn fetch(endpoint: Endpoint, mut client: Option<Client>, latest: Arc<Mutex<Result<SystemSnapshot>>>, requests: Receiver<()>) {
+    while requests.recv().is_ok() {
+        // Requests that piled up during a slow fetch share the next one.
+        while requests.try_recv().is_ok() {}
+        let result = match &mut client {
+            Some(client) => client.snapshot(),
+            None => Client::connect(&endpoint).and_then(|connected| client.insert(connected).snapshot()),
+        };
+        if result.is_err() {
+            client = None;
+        }
+        if let Ok(mut latest) = latest.lock() {
+            *latest = result;
+        }
+    }
+}
+
+impl DataSource for RemoteSource {
+    fn begin_sample(&mut self) {
+        if let Ok(latest) = self.latest.lock() {
+            self.current = latest.clone();
+        }
+        let _ = self.requests.send(());
+    }
+
+    fn cpus(&mut self) -> Result<Vec<CpuSample>> {
+        self.read(Subsystem::Cpu, |snapshot| &snapshot.cpus)
+    }
+
+    fn load(&mut self) -> Option<LoadSample> {
+        self.read(Subsystem::Cpu, |snapshot| &snapshot.load).ok().flatten()
+    }
+
+    fn memory(&mut self) -> Result<MemorySample> {
+        self.read(Subsystem::Memory, |snapshot| &snapshot.memory)
+    }
+
+    fn networks(&mut self) -> Result<Vec<NetworkSample>> {
+        self.read(Subsystem::Networks, |snapshot| &snapshot.networks)
+    }
+
+    fn processes(&mut self) -> Result<Vec<ProcessSample>> {
+        self.read(Subsystem::Processes, |snapshot| &snapshot.processes)
+    }
+
+    fn disks(&mut self) -> Result<Vec<DiskSample>> {
+        self.read(Subsystem::Disks, |snapshot| &snapshot.disks)
+    }
+
+    fn components(&mut self) -> Result<Vec<ComponentSample>> {
+        self.read(Subsystem::Components, |snapshot| &snapshot.components)
+    }
+
+    fn batteries(&mut self) -> Result<Vec<BatterySample>> {
+        self.read(Subsystem::Batteries, |snapshot| &snapshot.batteries)
+    }
+}
+
+// A TCP or Unix socket connection.
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn connect(endpoint: &Endpoint) -> io::Result<Self> {
+        match endpoint {
+            Endpoint::Tcp(address) => {
+                let mut last = io::Error::new(io::ErrorKind::NotFound, "address did not resolve");
+                for address in address.to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+                        Ok(stream) => return Ok(Stream::Tcp(stream)),
+                        Err(err) => last = err,
+                    }
+                }
+                Err(last)
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => UnixStream::connect(path).map(Stream::Unix),
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => Err(io::Error::new(io::ErrorKind::Unsupported, "unix sockets are not supported on this platform")),
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Stream::Tcp(stream) => stream.try_clone().map(Stream::Tcp),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.try_clone().map(Stream::Unix),
+        }
+    }
+
+    fn set_timeouts(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_read_timeout(timeout).and_then(|_| stream.set_write_timeout(timeout)),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.set_read_timeout(timeout).and_then(|_| stream.set_write_timeout(timeout)),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.flush(),
+        }
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use serde::Deserialize;
+use crate::config::deserialize_cadence;
+use crate::error::{Error, Result};
+use crate::interval::format_duration;
+use crate::snapshot::{BatteryState, Subsystem, SystemSnapshot};
+
+// Number of started/ended alerts kept for the alerts panel.
+pub const EVENT_CAPACITY: usize = 100;
+
+// One `[[alerts]]` entry in the configuration.
+//
+//     [[alerts]]
+//     name = "cpu busy"
+//     when = "cpu > 90%"
+//     for = "30s"
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertConfig {
+    pub name: String,
+    pub when: String,
+    // How long the condition must hold before the alert fires; "0"
+    // fires on the first matching sample.
+    #[serde(rename = "for", default, deserialize_with = "deserialize_cadence")]
+    pub hold: Duration,
+}
+
+// Values a condition can test. Metrics over several devices (cores, disks,
+// sensors, batteries) match when any one of them does, and comparisons of
+// the same devices joined with "and" must match the same one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    // Average usage over all cores, in percent.
+    Cpu,
+    CpuCore,
+    // Used memory and swap, in percent.
+    Memory,
+    Swap,
+    // Used space per disk, in percent.
+    Disk,
+    // Per sensor, in °C.
+    Temperature,
+    // Charge per battery, in percent.
+    Battery,
+    BatteryState,
+}
+
+const METRICS: [(&str, Metric); 8] = [
+    ("cpu", Metric::Cpu),
+    ("cpu.core", Metric::CpuCore),
+    ("memory", Metric::Memory),
+    ("swap", Metric::Swap),
+    ("disk", Metric::Disk),
+    ("temperature", Metric::Temperature),
+    ("battery", Metric::Battery),
+    ("battery.state", Metric::BatteryState),
+];
+
+const BATTERY_STATES: [(&str, BatteryState); 5] = [
+    ("unknown", BatteryState::Unknown),
+    ("charging", BatteryState::Charging),
+    ("discharging", BatteryState::Discharging),
+    ("empty", BatteryState::Empty),
+    ("full", BatteryState::Full),
+];
+
+impl Metric {
+    pub fn subsystem(&self) -> Subsystem {
+        match self {
+            Metric::Cpu | Metric::CpuCore => Subsystem::Cpu,
+            Metric::Memory | Metric::Swap => Subsystem::Memory,
+            Metric::Disk => Subsystem::Disks,
+            Metric::Temperature => Subsystem::Components,
+            Metric::Battery | Metric::BatteryState => Subsystem::Batteries,
+        }
+    }
+
+    // The number of devices the metric is read from, or `None` for values
+    // of the whole machine.
+    fn devices(&self, snapshot: &SystemSnapshot) -> Option<usize> {
+        match self {
+            Metric::CpuCore => Some(snapshot.cpus.len()),
+            Metric::Disk => Some(snapshot.disks.len()),
+            Metric::Temperature => Some(snapshot.components.len()),
+            Metric::Battery | Metric::BatteryState => Some(snapshot.batteries.len()),
+            Metric::Cpu | Metric::Memory | Metric::Swap => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+}
+
+const OPERATORS: [(&str, Operator); 6] = [
+    (">=", Operator::GreaterOrEqual),
+    ("<=", Operator::LessOrEqual),
+    ("==", Operator::Equal),
+    ("!=", Operator::NotEqual),
+    (">", Operator::Greater),
+    ("<", Operator::Less),
+];
+
+impl Operator {
+    fn compare(&self, left: f64, right: f64) -> bool {
+        match self {
+            Operator::Greater => left > right,
+            Operator::GreaterOrEqual => left >= right,
+            Operator::Less => left < right,
+            Operator::LessOrEqual => left <= right,
+            Operator::Equal => left == right,
+            Operator::NotEqual => left != right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Threshold {
+    Number(f64),
+    // The sensor's own critical temperature.
+    Critical,
+    State(BatteryState),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    pub metric: Metric,
+    pub operator: Operator,
+    pub threshold: Threshold,
+}
+
+impl Comparison {
+    fn readable(&self, snapshot: &SystemSnapshot) -> bool {
+        snapshot.refreshed_at(self.metric.subsystem()).is_some() && snapshot.error(self.metric.subsystem()).is_none()
+    }
+
+    // Whether the comparison holds for the device at `index`, which is
+    // ignored for values of the whole machine.
+    fn holds_on(&self, snapshot: &SystemSnapshot, index: usize) -> bool {
+        let number = |value: f64| match self.threshold {
+            Threshold::Number(threshold) => self.operator.compare(value, threshold),
+            _ => false,
+        };
+        match self.metric {
+            Metric::Cpu => !snapshot.cpus.is_empty() && number(snapshot.cpu_average() as f64),
+            Metric::CpuCore => snapshot.cpus.get(index).is_some_and(|cpu| number(cpu.usage as f64)),
+            Metric::Memory => snapshot.memory.total > 0 && number(snapshot.memory.used_ratio() * 100.0),
+            Metric::Swap => snapshot.memory.total_swap > 0
+                && number(snapshot.memory.used_swap as f64 / snapshot.memory.total_swap as f64 * 100.0),
+            Metric::Disk => snapshot.disks.get(index).is_some_and(|disk| number(disk.used_ratio() * 100.0)),
+            Metric::Temperature => snapshot.components.get(index).is_some_and(|component| match self.threshold {
+                Threshold::Critical => component.critical
+                    .is_some_and(|critical| self.operator.compare(component.temperature as f64, critical as f64)),
+                _ => number(component.temperature as f64),
+            }),
+            Metric::Battery => snapshot.batteries.get(index).is_some_and(|battery| number(battery.charge as f64 * 100.0)),
+            Metric::BatteryState => snapshot.batteries.get(index).is_some_and(|battery| match self.threshold {
+                Threshold::State(state) => (battery.state == state) == (self.operator == Operator::Equal),
+                _ => false,
+            }),
+        }
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = String;
+
+    // Parses "cpu > 90", "memory >= 95%", "temperature > critical" or
+    // "battery.state == discharging".
+    fn from_str(input: &str) -> std::result::Result<Self, String> {
+        let (index, symbol, operator) = OPERATORS.iter()
+            .filter_map(|(symbol, operator)| input.find(symbol).map(|index| (index, *symbol, *operator)))
+            .min_by_key(|(index, symbol, _)| (*index, std::cmp::Reverse(symbol.len())))
+            .ok_or_else(|| format!("expected a comparison such as 'cpu > 90' in '{}'", input.trim()))?;
+        let name = input[..index].trim();
+        let value = input[index + symbol.len()..].trim();
+
+        let metric = METRICS.iter().find(|(known, _)| *known == name).map(|(_, metric)| *metric).ok_or_else(|| {
+            let known: Vec<&str> = METRICS.iter().map(|(name, _)| *name).collect();
+            format!("unknown metric '{}' (available: {})", name, known.join(", "))
+        })?;
+        let threshold = match (metric, value) {
+            (Metric::BatteryState, state) => {
+                let state = BATTERY_STATES.iter().find(|(name, _)| *name == state).map(|(_, state)| *state).ok_or_else(|| {
+                    let known: Vec<&str> = BATTERY_STATES.iter().map(|(name, _)| *name).collect();
+                    format!("unknown battery state '{}' (available: {})", state, known.join(", "))
+                })?;
+                if !matches!(operator, Operator::Equal | Operator::NotEqual) {
+                    return Err("battery.state can only be compared with == or !=".to_string());
+                }
+                Threshold::State(state)
+            }
+            (Metric::Temperature, "critical") => Threshold::Critical,
+            (_, value) => value.trim_end_matches('%').trim().parse().map(Threshold::Number)
+                .map_err(|_| format!("expected a number after '{}' in '{}'", symbol, input.trim()))?,
+        };
+        Ok(Comparison { metric, operator, threshold })
+    }
+}
+
+// Comparisons joined with "and", all of which must hold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub comparisons: Vec<Comparison>,
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(input: &str) -> std::result::Result<Self, String> {
+        let comparisons = input.split(" and ").map(str::parse).collect::<std::result::Result<Vec<Comparison>, String>>()?;
+        Ok(Condition { comparisons })
+    }
+}
+
+impl Condition {
+    pub fn holds(&self, snapshot: &SystemSnapshot) -> bool {
+        if !self.comparisons.iter().all(|comparison| comparison.readable(snapshot)) {
+            return false;
+        }
+        self.comparisons.iter().all(|comparison| match comparison.metric.devices(snapshot) {
+            // Every comparison of these devices must hold on one of them.
+            Some(devices) => {
+                let same: Vec<&Comparison> = self.comparisons.iter()
+                    .filter(|other| other.metric.subsystem() == comparison.metric.subsystem() && other.metric.devices(snapshot).is_some())
+                    .collect();
+                (0..devices).any(|index| same.iter().all(|other| other.holds_on(snapshot, index)))
+            }
+            None => comparison.holds_on(snapshot, 0),
+        })
+    }
+
+    pub fn needs(&self) -> Vec<Subsystem> {
+        let mut needs: Vec<Subsystem> = self.comparisons.iter().map(|comparison| comparison.metric.subsystem()).collect();
+        needs.sort();
+        needs.dedup();
+        needs
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    pub name: String,
+    // The condition as written in the configuration.
+    pub when: String,
+    pub condition: Condition,
+    pub hold: Duration,
+}
+
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.when.trim())?;
+        if !self.hold.is_zero() {
+            write!(f, " for {}", format_duration(self.hold))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertState {
+    Ok,
+    // The condition holds but not yet for long enough.
+    Pending(SystemTime),
+    Firing(SystemTime),
+}
+
+// An alert that fired, and when it stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertEvent {
+    pub name: String,
+    pub started: SystemTime,
+    pub ended: Option<SystemTime>,
+}
+
+// Returned by `Alerts::evaluate` for every alert that started or ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertChange {
+    Started(String),
+    Ended(String),
+}
+
+// The configured rules with their state, evaluated on every sample using
+// the snapshot's own timestamps so replays behave like live data.
+#[derive(Debug, Clone, Default)]
+pub struct Alerts {
+    rules: Vec<(AlertRule, AlertState)>,
+    events: VecDeque<AlertEvent>,
+    last: Option<SystemTime>,
+}
+
+impl Alerts {
+    pub fn new(configs: &[AlertConfig]) -> Result<Self> {
+        let mut alerts = Alerts::default();
+        for config in configs {
+            if alerts.rules.iter().any(|(rule, _)| rule.name == config.name) {
+                return Err(Error::Config(format!("alert '{}' is defined more than once", config.name)));
+            }
+            let condition = config.when.parse()
+                .map_err(|message| Error::Config(format!("alert '{}': {}", config.name, message)))?;
+            let rule = AlertRule {
+                name: config.name.clone(),
+                when: config.when.clone(),
+                condition,
+                hold: config.hold,
+            };
+            alerts.rules.push((rule, AlertState::Ok));
+        }
+        Ok(alerts)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    // Subsystems the rules read, which must be sampled even when no panel
+    // shows them.
+    pub fn needs(&self) -> Vec<Subsystem> {
+        let mut needs: Vec<Subsystem> = self.rules.iter().flat_map(|(rule, _)| rule.condition.needs()).collect();
+        needs.sort();
+        needs.dedup();
+        needs
+    }
+
+    pub fn evaluate(&mut self, snapshot: &SystemSnapshot) -> Vec<AlertChange> {
+        let now = snapshot.timestamp;
+        // Time went backwards: a replay was rewound.
+        if self.last.is_some_and(|last| now < last) {
+            for (_, state) in &mut self.rules {
+                *state = AlertState::Ok;
+            }
+            self.events.clear();
+        }
+        self.last = Some(now);
+
+        let mut changes = vec![];
+        for (rule, state) in &mut self.rules {
+            let next = match (*state, rule.condition.holds(snapshot)) {
+                (AlertState::Firing(since), true) => AlertState::Firing(since),
+                (pending, true) => {
+                    let since = match pending {
+                        AlertState::Pending(since) => since,
+                        _ => now,
+                    };
+                    if now.duration_since(since).unwrap_or_default() >= rule.hold {
+                        AlertState::Firing(since)
+                    } else {
+                        AlertState::Pending(since)
+                    }
+                }
+                (_, false) => AlertState::Ok,
+            };
+            match (*state, next) {
+                (AlertState::Firing(_), AlertState::Firing(_)) => {}
+                (_, AlertState::Firing(since)) => {
+                    if self.events.len() == EVENT_CAPACITY {
+                        self.events.pop_front();
+                    }
+                    self.events.push_back(AlertEvent { name: rule.name.clone(), started: since, ended: None });
+                    changes.push(AlertChange::Started(rule.name.clone()));
+                }
+                (AlertState::Firing(_), _) => {
+                    if let Some(event) = self.events.iter_mut().rev().find(|event| event.name == rule.name && event.ended.is_none()) {
+                        event.ended = Some(now);
+                    }
+                    changes.push(AlertChange::Ended(rule.name.clone()));
+                }
+                _ => {}
+            }
+            *state = next;
+        }
+        changes
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &(AlertRule, AlertState)> {
+        self.rules.iter()
+    }
+
+    pub fn rule(&self, name: &str) -> Option<&AlertRule> {
+        self.rules.iter().find(|(rule, _)| rule.name == name).map(|(rule, _)| rule)
+    }
+
+    pub fn firing(&self) -> impl Iterator<Item = (&AlertRule, SystemTime)> {
+        self.rules.iter().filter_map(|(rule, state)| match state {
+            AlertState::Firing(since) => Some((rule, *since)),
+            _ => None,
+        })
+    }
+
+    // Started and ended alerts, oldest first.
+    pub fn events(&self) -> impl DoubleEndedIterator<Item = &AlertEvent> {
+        self.events.iter()
+    }
+}
@@ -0,0 +1,102 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap::error::ErrorKind;
+use crate::interval::parse_interval;
+
+#[derive(Debug, Parser)]
+#[command(name = "systemmor", version, about = "Text-based system monitor", args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Panel to start on, e.g. cpu or memory; when printing samples, the only panel reported [default: from the config file]
+    #[arg(short, long)]
+    pub panel: Option<String>,
+
+    /// Time between samples, e.g. 250ms, 2s or 1m (a bare number is milliseconds) [default: 250ms]
+    #[arg(short, long, global = true, value_parser = parse_interval)]
+    pub interval: Option<Duration>,
+
+    /// Configuration file [default: $XDG_CONFIG_HOME/systemmor/config.toml]
+    #[arg(short, long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Print a single sample to stdout and exit instead of starting the interface
+    #[arg(long, conflicts_with = "count")]
+    pub once: bool,
+
+    /// Print this many samples to stdout, one interval apart, and exit
+    #[arg(short = 'n', long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub count: Option<u64>,
+
+    /// Print samples in this format instead of starting the interface; ndjson streams until interrupted unless --count is given [default: text]
+    #[arg(short, long)]
+    pub format: Option<Format>,
+
+    /// Serve OpenMetrics on this address at /metrics, e.g. 127.0.0.1:9184 [default: from the config file]
+    #[arg(short, long, conflicts_with_all = ["once", "count", "format"])]
+    pub listen: Option<String>,
+
+    /// Run only the metrics endpoint and recorder, without the interface, until interrupted
+    #[arg(long, conflicts_with_all = ["once", "count", "format"])]
+    pub headless: bool,
+
+    /// Append every sample to this file while running
+    #[arg(short, long, conflicts_with_all = ["once", "count", "format"])]
+    pub record: Option<PathBuf>,
+
+    /// Play back a file written by --record instead of sampling this machine
+    #[arg(long, conflicts_with_all = ["once", "count", "format", "headless", "record"])]
+    pub replay: Option<PathBuf>,
+
+    /// Show the machine a `systemmor agent` runs on instead of this one, e.g. lab1:9185 or unix:/run/systemmor.sock
+    #[arg(long, conflicts_with = "replay")]
+    pub connect: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Sample continuously and serve snapshots to `systemmor --connect`
+    Agent {
+        /// Address to listen on, host:port or unix:/path/to/socket [default: from the config file, or 127.0.0.1:9185]
+        address: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Text,
+    // A single JSON document.
+    Json,
+    // One JSON document per line.
+    Ndjson,
+}
+
+pub enum Mode {
+    Interface,
+    Agent,
+    // Sample in the background for the exporter and recorder only.
+    Headless,
+    // Print samples without touching the terminal; `None` runs until
+    // interrupted.
+    Print { format: Format, count: Option<u64> },
+}
+
+impl Cli {
+    pub fn mode(&self) -> Mode {
+        if let Some(Command::Agent { .. }) = self.command {
+            return Mode::Agent;
+        }
+        let count = if self.once { Some(1) } else { self.count };
+        match (self.format, count) {
+            (None, None) if self.headless => Mode::Headless,
+            (None, None) => Mode::Interface,
+            (Some(Format::Json), Some(count)) if count > 1 => Cli::command()
+                .error(ErrorKind::ArgumentConflict, "--format json prints a single document; use --format ndjson with --count")
+                .exit(),
+            (Some(Format::Json), _) => Mode::Print { format: Format::Json, count: Some(1) },
+            (format, count) => Mode::Print { format: format.unwrap_or(Format::Text), count },
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use serde::Deserialize;
+use crate::config::deserialize_duration;
+use crate::error::{Error, Result};
+use crate::history::{Series, DEFAULT_CAPACITY};
+use crate::interval::format_duration;
+
+pub const DEFAULT_COMMAND_INTERVAL: Duration = Duration::from_secs(5);
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+
+// How often a running command is checked for having exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// A panel fed by a local command, from `[[commands]]` in the configuration.
+//
+//     [[commands]]
+//     name = "Queue"
+//     command = "redis-cli llen jobs"
+//     interval = "10s"
+//     widget = "line"
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandConfig {
+    // Panel title; its lower-case form names the panel in `panels`.
+    pub name: String,
+    // Run with `sh -c` (`cmd /C` on Windows).
+    pub command: String,
+    // Tab hotkey. Command panels without one get 1, 2, 3... in order.
+    #[serde(default)]
+    pub key: Option<char>,
+    #[serde(default = "default_interval", deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+    #[serde(default = "default_timeout", deserialize_with = "deserialize_duration")]
+    pub timeout: Duration,
+    #[serde(default)]
+    pub format: OutputFormat,
+    #[serde(default)]
+    pub widget: CommandWidget,
+    // Full scale of the gauges and bars. Required for gauges.
+    #[serde(default)]
+    pub max: Option<f64>,
+    // Shown after every value, e.g. "jobs" or "%".
+    #[serde(default)]
+    pub unit: Option<String>,
+}
+
+fn default_interval() -> Duration {
+    DEFAULT_COMMAND_INTERVAL
+}
+
+fn default_timeout() -> Duration {
+    DEFAULT_COMMAND_TIMEOUT
+}
+
+impl CommandConfig {
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: &str| Err(Error::Config(format!("command panel '{}': {}", self.name, message)));
+        if self.name.trim().is_empty() {
+            return Err(Error::Config("command panels need a name".to_string()));
+        }
+        if self.command.trim().is_empty() {
+            return invalid("command is empty");
+        }
+        match self.max {
+            Some(max) if max <= 0.0 || !max.is_finite() => invalid("max must be a positive number"),
+            None if self.widget == CommandWidget::Gauge => invalid("gauges need a max"),
+            _ => Ok(()),
+        }
+    }
+}
+
+// How the command's standard output is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    // A single number.
+    #[default]
+    Number,
+    // One `key=value` per line; blank lines and lines starting with `#`
+    // are skipped.
+    KeyValue,
+    // A number, or an object whose numbers (at any depth, keys joined with
+    // ".") are the values. Other fields are ignored.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandWidget {
+    Gauge,
+    #[default]
+    Bar,
+    Line,
+}
+
+// Values read from one run, in output order (JSON fields by key). A plain
+// number is named after the panel.
+pub fn parse_output(output: &str, format: OutputFormat, name: &str) -> Result<Vec<(String, f64)>> {
+    let invalid = |message: String| Error::Command(format!("printed unreadable output: {}", message));
+    let values = match format {
+        OutputFormat::Number => {
+            let value = output.trim();
+            vec![(name.to_string(), parse_number(value).ok_or_else(|| invalid(format!("'{}' is not a number", value)))?)]
+        }
+        OutputFormat::KeyValue => {
+            let mut values = vec![];
+            for (number, line) in output.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (key, value) = line.split_once('=')
+                    .ok_or_else(|| invalid(format!("line {}: expected key=value, got '{}'", number + 1, line)))?;
+                let value = parse_number(value.trim())
+                    .ok_or_else(|| invalid(format!("line {}: '{}' is not a number", number + 1, value.trim())))?;
+                values.push((key.trim().to_string(), value));
+            }
+            values
+        }
+        OutputFormat::Json => {
+            let json: serde_json::Value = serde_json::from_str(output).map_err(|err| invalid(err.to_string()))?;
+            let mut values = vec![];
+            match json {
+                serde_json::Value::Number(_) => flatten(&json, name, &mut values),
+                _ => flatten(&json, "", &mut values),
+            }
+            values
+        }
+    };
+    if values.is_empty() {
+        return Err(invalid("no values found".to_string()));
+    }
+    Ok(values)
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    value.parse::<f64>().ok().filter(|value| value.is_finite())
+}
+
+fn flatten(json: &serde_json::Value, key: &str, values: &mut Vec<(String, f64)>) {
+    let nested = |field: &str| if key.is_empty() { field.to_string() } else { format!("{}.{}", key, field) };
+    match json {
+        serde_json::Value::Number(number) => values.extend(number.as_f64().map(|value| (key.to_string(), value))),
+        serde_json::Value::Object(fields) => {
+            for (field, value) in fields {
+                flatten(value, &nested(field), values);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                flatten(item, &nested(&index.to_string()), values);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Runs `command` in a shell and returns its standard output, killing it
+// once `timeout` has passed.
+pub fn run(command: &str, timeout: Duration) -> Result<String> {
+    let mut child = shell(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| Error::Command(format!("could not be started: {}", err)))?;
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let timed_out = || Error::Command(format!("timed out after {}", format_duration(timeout)));
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                kill(&mut child);
+                let _ = child.wait();
+                return Err(timed_out());
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(err) => return Err(Error::Command(format!("failed: {}", err))),
+        }
+    };
+    // A background process the command started can keep the pipe open
+    // after the command itself exits.
+    let output = stdout.recv_timeout(deadline.saturating_duration_since(Instant::now())).map_err(|_| timed_out())?;
+    if !status.success() {
+        let stderr = stderr.recv_timeout(POLL_INTERVAL).unwrap_or_default();
+        let reason = match status.code() {
+            Some(code) => format!("exited with status {}", code),
+            None => "was killed by a signal".to_string(),
+        };
+        return Err(Error::Command(match stderr.lines().map(str::trim).find(|line| !line.is_empty()) {
+            Some(line) => format!("{}: {}", reason, line),
+            None => reason,
+        }));
+    }
+    Ok(output)
+}
+
+// Its own process group, so a timeout also kills what the shell started.
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command).process_group(0);
+    shell
+}
+
+#[cfg(unix)]
+fn kill(child: &mut Child) {
+    // SAFETY: kill(2) only takes integers; a negative pid names the group.
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+fn read_in_background<R: Read + Send + 'static>(pipe: Option<R>) -> Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut bytes = vec![];
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        let _ = tx.send(String::from_utf8_lossy(&bytes).into_owned());
+    });
+    rx
+}
+
+// The last successful run of a command panel, and the last error.
+#[derive(Debug, Clone, Default)]
+pub struct CommandState {
+    pub values: Vec<(String, f64)>,
+    pub history: BTreeMap<String, Series>,
+    // When `values` were read.
+    pub updated: Option<SystemTime>,
+    pub error: Option<Error>,
+}
+
+impl CommandState {
+    pub fn update(&mut self, result: Result<Vec<(String, f64)>>, now: SystemTime) {
+        match result {
+            Ok(values) => {
+                for (key, value) in &values {
+                    self.history.entry(key.clone()).or_insert_with(|| Series::new(DEFAULT_CAPACITY)).push(now, *value);
+                }
+                self.values = values;
+                self.updated = Some(now);
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+}
+
+// Runs a command every `interval` on its own thread until dropped.
+pub struct CommandRunner {
+    state: Arc<Mutex<CommandState>>,
+}
+
+impl CommandRunner {
+    pub fn start(config: &CommandConfig) -> Self {
+        let state = Arc::new(Mutex::new(CommandState::default()));
+        let weak = Arc::downgrade(&state);
+        let config = config.clone();
+        thread::spawn(move || poll(weak, config));
+        CommandRunner { state }
+    }
+
+    pub fn state(&self) -> CommandState {
+        self.state.lock().map(|state| state.clone()).unwrap_or_default()
+    }
+}
+
+fn poll(state: Weak<Mutex<CommandState>>, config: CommandConfig) {
+    loop {
+        let started = Instant::now();
+        let result = run(&config.command, config.timeout)
+            .and_then(|output| parse_output(&output, config.format, &config.name));
+        let Some(state) = state.upgrade() else { return };
+        if let Ok(mut state) = state.lock() {
+            state.update(result, SystemTime::now());
+        }
+        drop(state);
+        thread::sleep(config.interval.saturating_sub(started.elapsed()));
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer};
+use crate::agent::DEFAULT_AGENT_ADDRESS;
+use crate::alerts::AlertConfig;
+use crate::command::CommandConfig;
+use crate::fleet::{DEFAULT_FLEET_REFRESH, DEFAULT_STALE_AFTER};
+use crate::error::{Error, Result};
+use crate::exporter::DEFAULT_EXPORTED_PROCESSES;
+use crate::history::{DEFAULT_CAPACITY, DEFAULT_RESOLUTION, MAX_CAPACITY, MAX_RESOLUTION};
+use crate::interval::{parse_duration, parse_interval, DEFAULT_INTERVAL};
+use crate::keymap::KeysConfig;
+use crate::sampler::{default_cadence, DEFAULT_BACKGROUND_INTERVAL};
+use crate::snapshot::Subsystem;
+use crate::theme::ThemeConfig;
+use crate::units::Units;
+
+// Contents of `config.toml`. Every key is optional; unknown keys are
+// rejected so typos do not silently fall back to the defaults.
+//
+//     panels = ["home", "cpu", "memory"]
+//     default_panel = "cpu"
+//     theme = "mine"
+//
+//     [refresh]
+//     interval = "500ms"
+//     background = "off"
+//
+//     [refresh.cadence]
+//     processes = "2s"
+//
+//     [units]
+//     memory = "decimal"
+//
+//     [themes.mine]
+//     base = "dark"
+//     gauge = "#ff8800"
+//
+//     [keys]
+//     quit = ["q", "ctrl+c"]
+//
+//     [exporter]
+//     listen = "127.0.0.1:9184"
+//
+//     [agent]
+//     listen = "0.0.0.0:9185"
+//
+//     [fleet]
+//     hosts = ["lab1:9185", "lab2:9185"]
+//
+//     [[alerts]]
+//     name = "cpu busy"
+//     when = "cpu > 90%"
+//     for = "30s"
+//
+//     [[commands]]
+//     name = "Queue"
+//     command = "redis-cli llen jobs"
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // Panels to show, in tab order, by lower-case title. All built-in
+    // panels are shown when unset.
+    pub panels: Option<Vec<String>>,
+    pub default_panel: Option<String>,
+    pub refresh: RefreshConfig,
+    pub units: Units,
+    // Theme to start with, by name. Defaults to "dark".
+    pub theme: Option<String>,
+    pub themes: BTreeMap<String, ThemeConfig>,
+    pub keys: KeysConfig,
+    pub exporter: ExporterConfig,
+    pub agent: AgentConfig,
+    pub fleet: FleetConfig,
+    pub alerts: Vec<AlertConfig>,
+    pub commands: Vec<CommandConfig>,
+}
+
+// The `/metrics` endpoint, off unless `listen` is set.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExporterConfig {
+    // Address to serve on, e.g. "127.0.0.1:9184".
+    pub listen: Option<String>,
+    // Number of processes exported, busiest first.
+    pub processes: usize,
+}
+
+impl Default for ExporterConfig {
+    fn default() -> Self {
+        ExporterConfig {
+            listen: None,
+            processes: DEFAULT_EXPORTED_PROCESSES,
+        }
+    }
+}
+
+// `systemmor agent`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentConfig {
+    // "host:port" or "unix:/path/to/socket".
+    pub listen: String,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        AgentConfig {
+            listen: DEFAULT_AGENT_ADDRESS.to_string(),
+        }
+    }
+}
+
+// Agents shown in the fleet panel.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FleetConfig {
+    // "host:port" or "unix:/path/to/socket" of each agent.
+    pub hosts: Vec<String>,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub refresh: Duration,
+    // A host whose data has not changed for this long is marked stale.
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub stale: Duration,
+}
+
+impl Default for FleetConfig {
+    fn default() -> Self {
+        FleetConfig {
+            hosts: vec![],
+            refresh: DEFAULT_FLEET_REFRESH,
+            stale: DEFAULT_STALE_AFTER,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RefreshConfig {
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+    // Refresh rate of subsystems the active panel does not need; "off"
+    // disables background refreshes.
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    pub background: Option<Duration>,
+    pub history_length: usize,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub history_resolution: Duration,
+    pub cadence: CadenceConfig,
+}
+
+impl Default for RefreshConfig {
+    fn default() -> Self {
+        RefreshConfig {
+            interval: DEFAULT_INTERVAL,
+            background: Some(DEFAULT_BACKGROUND_INTERVAL),
+            history_length: DEFAULT_CAPACITY,
+            history_resolution: DEFAULT_RESOLUTION,
+            cadence: CadenceConfig::default(),
+        }
+    }
+}
+
+impl RefreshConfig {
+    // Bounds serde cannot express; the messages name the offending key.
+    fn validate(&self) -> Result<()> {
+        if !(1..=MAX_CAPACITY).contains(&self.history_length) {
+            return Err(Error::Config(format!("refresh.history_length must be between 1 and {}, got {}", MAX_CAPACITY, self.history_length)));
+        }
+        if self.history_resolution > MAX_RESOLUTION {
+            return Err(Error::Config(format!("refresh.history_resolution must be at most {}s, got {:?}", MAX_RESOLUTION.as_secs(), self.history_resolution)));
+        }
+        Ok(())
+    }
+}
+
+// Minimum time between refreshes of each subsystem while it is needed.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CadenceConfig {
+    #[serde(deserialize_with = "deserialize_cadence")]
+    pub cpu: Duration,
+    #[serde(deserialize_with = "deserialize_cadence")]
+    pub memory: Duration,
+    #[serde(deserialize_with = "deserialize_cadence")]
+    pub networks: Duration,
+    #[serde(deserialize_with = "deserialize_cadence")]
+    pub processes: Duration,
+    #[serde(deserialize_with = "deserialize_cadence")]
+    pub disks: Duration,
+    #[serde(deserialize_with = "deserialize_cadence")]
+    pub components: Duration,
+    #[serde(deserialize_with = "deserialize_cadence")]
+    pub batteries: Duration,
+}
+
+impl Default for CadenceConfig {
+    fn default() -> Self {
+        CadenceConfig {
+            cpu: default_cadence(Subsystem::Cpu),
+            memory: default_cadence(Subsystem::Memory),
+            networks: default_cadence(Subsystem::Networks),
+            processes: default_cadence(Subsystem::Processes),
+            disks: default_cadence(Subsystem::Disks),
+            components: default_cadence(Subsystem::Components),
+            batteries: default_cadence(Subsystem::Batteries),
+        }
+    }
+}
+
+impl CadenceConfig {
+    pub fn get(&self, subsystem: Subsystem) -> Duration {
+        match subsystem {
+            Subsystem::Cpu => self.cpu,
+            Subsystem::Memory => self.memory,
+            Subsystem::Networks => self.networks,
+            Subsystem::Processes => self.processes,
+            Subsystem::Disks => self.disks,
+            Subsystem::Components => self.components,
+            Subsystem::Batteries => self.batteries,
+        }
+    }
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    fn from_str(contents: &str) -> Result<Self> {
+        let config: Config = toml::from_str(contents).map_err(|err| Error::Config(err.to_string()))?;
+        config.refresh.validate()?;
+        Ok(config)
+    }
+}
+
+impl Config {
+    // `$XDG_CONFIG_HOME/systemmor/config.toml`, falling back to
+    // `$HOME/.config/systemmor/config.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join("systemmor").join("config.toml"))
+    }
+
+    // Loads `path`, or the default location when `path` is `None`. A
+    // missing file at the default location yields the default config; a
+    // missing file that was asked for explicitly is an error.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, explicit) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match Config::default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => contents.parse().map_err(|err| match err {
+                Error::Config(message) => Error::Config(format!("{}: {}", path.display(), message)),
+                err => err,
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && !explicit => Ok(Config::default()),
+            Err(err) => Err(Error::Config(format!("{}: {}", path.display(), err))),
+        }
+    }
+}
+
+pub(crate) fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Duration, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_interval(&value).map_err(serde::de::Error::custom)
+}
+
+// Like `deserialize_duration` but allows "0" for "every sample".
+pub(crate) fn deserialize_cadence<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Duration, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_duration(&value).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_optional_duration<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<Duration>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    if value.trim() == "off" {
+        return Ok(None);
+    }
+    parse_interval(&value).map(Some).map_err(serde::de::Error::custom)
+}
+
+pub(crate) fn deserialize_optional_color<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<Color>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Color::from_str(&value).map(Some).map_err(|_| serde::de::Error::custom(format!("invalid colour '{}'", value)))
+}
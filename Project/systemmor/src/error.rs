@@ -0,0 +1,46 @@
+use std::fmt;
+use crate::snapshot::Subsystem;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    // The operating system reported no devices for the subsystem,
+    // e.g. a desktop without a battery or a container without disks.
+    NotFound(Subsystem),
+    // The battery backend could not be opened or queried.
+    Battery(String),
+    // The configuration file could not be read or is invalid.
+    Config(String),
+    // A recording could not be read or is invalid.
+    Recording(String),
+    // An error stored in a recording or reported by a remote agent, shown
+    // as it was reported.
+    Replayed(String),
+    // A remote agent could not be reached or answered nonsense.
+    Remote(String),
+    // A command panel's command failed or printed something unreadable.
+    Command(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound(subsystem) => write!(f, "no {} found", subsystem.label()),
+            Error::Battery(message) => write!(f, "battery backend error: {}", message),
+            Error::Config(message) => write!(f, "invalid configuration: {}", message),
+            Error::Recording(message) => write!(f, "invalid recording: {}", message),
+            Error::Replayed(message) => write!(f, "{}", message),
+            Error::Remote(message) => write!(f, "remote agent: {}", message),
+            Error::Command(message) => write!(f, "command {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<battery::Error> for Error {
+    fn from(err: battery::Error) -> Self {
+        Error::Battery(err.to_string())
+    }
+}
@@ -0,0 +1,99 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use crate::metrics::{self, CONTENT_TYPE};
+use crate::snapshot::{Subsystem, SystemSnapshot};
+
+pub const DEFAULT_EXPORTED_PROCESSES: usize = 10;
+
+// Drops clients that stop sending or reading.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Connections served at once; more are closed right away.
+pub const MAX_CONNECTIONS: usize = 16;
+
+// The latest snapshot, published by the sampling loop and read on every
+// scrape.
+pub type SharedSnapshot = Arc<Mutex<SystemSnapshot>>;
+
+// Serves `/metrics` over HTTP from a background thread.
+pub struct Exporter {
+    address: SocketAddr,
+}
+
+impl Exporter {
+    // Binds `address` right away, so a port that is in use is reported
+    // before the interface starts, then answers requests on its own thread.
+    pub fn start(address: &str, snapshot: SharedSnapshot, top_processes: usize) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        thread::spawn(move || {
+            // Each connection gets its own thread, so an idle client only
+            // holds up itself.
+            let open = Arc::new(AtomicUsize::new(0));
+            for stream in listener.incoming().flatten() {
+                if open.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    open.fetch_sub(1, Ordering::SeqCst);
+                    continue;
+                }
+                let (snapshot, open) = (snapshot.clone(), open.clone());
+                thread::spawn(move || {
+                    // Errors only concern the one connection.
+                    let _ = handle(stream, &snapshot, top_processes);
+                    open.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        Ok(Exporter { address })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    // Every subsystem is exported, so all of them must be sampled even
+    // when no panel shows them.
+    pub fn needs(&self) -> &'static [Subsystem] {
+        &Subsystem::ALL
+    }
+}
+
+fn handle(mut stream: TcpStream, snapshot: &SharedSnapshot, top_processes: usize) -> io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers are not needed, but must be read before answering.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && header.trim_end() != "" {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    let path = target.split('?').next().unwrap_or_default();
+    let (status, content_type, body) = match (method, path) {
+        ("GET" | "HEAD", "/metrics") => {
+            let snapshot = snapshot.lock().map(|snapshot| snapshot.clone()).unwrap_or_default();
+            ("200 OK", CONTENT_TYPE, metrics::render(&snapshot, top_processes))
+        }
+        ("GET" | "HEAD", "/") => ("200 OK", "text/plain; charset=utf-8", "systemmor exporter, metrics are at /metrics\n".to_string()),
+        ("GET" | "HEAD", _) => ("404 Not Found", "text/plain; charset=utf-8", "not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain; charset=utf-8", "method not allowed\n".to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status, content_type, body.len(),
+    )?;
+    if method != "HEAD" {
+        stream.write_all(body.as_bytes())?;
+    }
+    stream.flush()
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use crate::error::Result;
+use crate::snapshot::*;
+use crate::source::DataSource;
+
+const MIB: u64 = 1024 * 1024;
+const GIB: u64 = 1024 * MIB;
+
+// Values returned by a `FakeSource`. A field set to an error makes that
+// subsystem fail, and an empty list makes it "not found".
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeData {
+    pub cpus: Result<Vec<CpuSample>>,
+    pub load: Option<LoadSample>,
+    pub memory: Result<MemorySample>,
+    pub networks: Result<Vec<NetworkSample>>,
+    pub processes: Result<Vec<ProcessSample>>,
+    pub disks: Result<Vec<DiskSample>>,
+    pub components: Result<Vec<ComponentSample>>,
+    pub batteries: Result<Vec<BatterySample>>,
+}
+
+// A small laptop: 4 cores, 8 GiB of memory, one of everything else.
+impl Default for FakeData {
+    fn default() -> Self {
+        FakeData {
+            cpus: Ok(cpus(4, 25.0)),
+            load: Some(LoadSample { one: 1.5, five: 1.0, fifteen: 0.5, running: Some(2), blocked: Some(1), tasks: Some(300) }),
+            memory: Ok(MemorySample {
+                total: 8 * GIB,
+                used: 4 * GIB,
+                total_swap: 2 * GIB,
+                used_swap: 512 * MIB,
+            }),
+            networks: Ok(networks(1)),
+            processes: Ok(processes(20)),
+            disks: Ok(disks(1)),
+            components: Ok(components(2)),
+            batteries: Ok(vec![BatterySample { state: BatteryState::Discharging, charge: 0.8 }]),
+        }
+    }
+}
+
+impl FakeData {
+    pub fn with_cpus(mut self, count: usize, usage: f32) -> Self {
+        self.cpus = Ok(cpus(count, usage));
+        self
+    }
+
+    pub fn with_load(mut self, load: Option<LoadSample>) -> Self {
+        self.load = load;
+        self
+    }
+
+    // Every core's time breakdown.
+    pub fn with_times(mut self, times: Option<CpuTimes>) -> Self {
+        if let Ok(cpus) = &mut self.cpus {
+            for cpu in cpus {
+                cpu.times = times;
+            }
+        }
+        self
+    }
+
+    // Every core's current clock in MHz.
+    pub fn with_frequency(mut self, frequency: u64) -> Self {
+        if let Ok(cpus) = &mut self.cpus {
+            for cpu in cpus {
+                cpu.frequency = frequency;
+            }
+        }
+        self
+    }
+
+    // Used memory as a fraction of the total.
+    pub fn with_memory_used(mut self, ratio: f64) -> Self {
+        if let Ok(memory) = &mut self.memory {
+            memory.used = (memory.total as f64 * ratio) as u64;
+        }
+        self
+    }
+
+    pub fn with_networks(mut self, count: usize) -> Self {
+        self.networks = Ok(networks(count));
+        self
+    }
+
+    pub fn with_processes(mut self, count: usize) -> Self {
+        self.processes = Ok(processes(count));
+        self
+    }
+
+    pub fn with_disks(mut self, count: usize) -> Self {
+        self.disks = Ok(disks(count));
+        self
+    }
+
+    pub fn with_components(mut self, count: usize) -> Self {
+        self.components = Ok(components(count));
+        self
+    }
+
+    pub fn with_battery(mut self, state: BatteryState, charge: f32) -> Self {
+        self.batteries = Ok(vec![BatterySample { state, charge }]);
+        self
+    }
+
+    pub fn without_batteries(mut self) -> Self {
+        self.batteries = Ok(vec![]);
+        self
+    }
+}
+
+// A scripted `DataSource`. With a single step it returns the same values
+// forever; with several, the n-th refresh of a subsystem reads step n and
+// the last step repeats once the scenario runs out.
+pub struct FakeSource {
+    steps: Vec<FakeData>,
+    refreshes: HashMap<Subsystem, usize>,
+}
+
+impl Default for FakeSource {
+    fn default() -> Self {
+        FakeSource::new(FakeData::default())
+    }
+}
+
+impl FakeSource {
+    pub fn new(data: FakeData) -> Self {
+        FakeSource::scenario(vec![data])
+    }
+
+    pub fn scenario(mut steps: Vec<FakeData>) -> Self {
+        if steps.is_empty() {
+            steps.push(FakeData::default());
+        }
+        FakeSource { steps, refreshes: HashMap::new() }
+    }
+
+    fn step(&mut self, subsystem: Subsystem) -> &FakeData {
+        let refreshes = self.refreshes.entry(subsystem).or_default();
+        let index = (*refreshes).min(self.steps.len() - 1);
+        *refreshes += 1;
+        &self.steps[index]
+    }
+}
+
+impl DataSource for FakeSource {
+    fn cpus(&mut self) -> Result<Vec<CpuSample>> {
+        self.step(Subsystem::Cpu).cpus.clone()
+    }
+
+    // From the step `cpus` just returned.
+    fn load(&mut self) -> Option<LoadSample> {
+        let refreshes = self.refreshes.get(&Subsystem::Cpu).copied().unwrap_or(1);
+        self.steps[refreshes.saturating_sub(1).min(self.steps.len() - 1)].load
+    }
+
+    fn memory(&mut self) -> Result<MemorySample> {
+        self.step(Subsystem::Memory).memory.clone()
+    }
+
+    fn networks(&mut self) -> Result<Vec<NetworkSample>> {
+        self.step(Subsystem::Networks).networks.clone()
+    }
+
+    fn processes(&mut self) -> Result<Vec<ProcessSample>> {
+        self.step(Subsystem::Processes).processes.clone()
+    }
+
+    fn disks(&mut self) -> Result<Vec<DiskSample>> {
+        self.step(Subsystem::Disks).disks.clone()
+    }
+
+    fn components(&mut self) -> Result<Vec<ComponentSample>> {
+        self.step(Subsystem::Components).components.clone()
+    }
+
+    fn batteries(&mut self) -> Result<Vec<BatterySample>> {
+        self.step(Subsystem::Batteries).batteries.clone()
+    }
+}
+
+// 2.4 GHz cores that can run from 800 MHz to 4.2 GHz, busy mostly with
+// user code.
+fn cpus(count: usize, usage: f32) -> Vec<CpuSample> {
+    (0..count).map(|i| CpuSample {
+        name: format!("cpu{}", i),
+        usage,
+        frequency: 2400,
+        min_frequency: Some(800),
+        max_frequency: Some(4200),
+        brand: "Fake CPU @ 2.40GHz".to_string(),
+        vendor: "FakeVendor".to_string(),
+        times: Some(CpuTimes {
+            user: usage * 0.6,
+            system: usage * 0.2,
+            iowait: usage * 0.08,
+            irq: usage * 0.04,
+            steal: usage * 0.04,
+            guest: usage * 0.04,
+        }),
+    }).collect()
+}
+
+fn networks(count: usize) -> Vec<NetworkSample> {
+    (0..count).map(|i| NetworkSample {
+        interface: format!("eth{}", i),
+        received: 1_000 * (i as u64 + 1),
+        transmitted: 500 * (i as u64 + 1),
+        total_received: 1_000_000 * (i as u64 + 1),
+        total_transmitted: 500_000 * (i as u64 + 1),
+    }).collect()
+}
+
+// Pids from 1, each using a little more memory and CPU than the last.
+fn processes(count: usize) -> Vec<ProcessSample> {
+    (0..count).map(|i| ProcessSample {
+        pid: i as u32 + 1,
+        name: format!("process-{}", i + 1),
+        memory: (i as u64 + 1) * MIB,
+        cpu_usage: (i % 100) as f32,
+        disk_read: 0,
+        disk_written: 0,
+    }).collect()
+}
+
+fn disks(count: usize) -> Vec<DiskSample> {
+    (0..count).map(|i| DiskSample {
+        name: format!("/dev/sda{}", i + 1),
+        kind: "SSD".to_string(),
+        mount_point: if i == 0 { "/".to_string() } else { format!("/mnt/disk{}", i) },
+        total_space: 256 * GIB,
+        available_space: 64 * GIB,
+    }).collect()
+}
+
+fn components(count: usize) -> Vec<ComponentSample> {
+    (0..count).map(|i| ComponentSample {
+        label: format!("sensor {}", i),
+        temperature: 40.0 + i as f32,
+        max: 60.0,
+        critical: Some(100.0),
+    }).collect()
+}
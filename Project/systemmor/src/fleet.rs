@@ -0,0 +1,239 @@
+use std::cmp::Ordering;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::agent::{Client, Endpoint};
+use crate::snapshot::{Subsystem, SystemSnapshot};
+
+pub const DEFAULT_FLEET_REFRESH: Duration = Duration::from_secs(2);
+pub const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(10);
+
+// What is known about one host, written by its polling thread.
+struct HostState {
+    endpoint: Endpoint,
+    hostname: Option<String>,
+    snapshot: Option<SystemSnapshot>,
+    // When `snapshot` was fetched.
+    updated: Option<Instant>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostStatus {
+    // Not polled yet.
+    Connecting,
+    Up,
+    // The agent answers, but its data has not changed for this long.
+    Stale(Duration),
+    Unreachable(String),
+}
+
+// One line of the fleet table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostRow {
+    // The agent's host name, or its address until it answers.
+    pub name: String,
+    pub endpoint: Endpoint,
+    pub status: HostStatus,
+    // Average CPU usage in percent.
+    pub cpu: Option<f32>,
+    // Used memory and fullest disk, between 0 and 1.
+    pub memory: Option<f64>,
+    pub disk: Option<f64>,
+    // Hottest sensor in °C.
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FleetSort {
+    #[default]
+    Name,
+    Status,
+    Cpu,
+    Memory,
+    Disk,
+    Temperature,
+}
+
+const SORTS: [FleetSort; 6] = [FleetSort::Name, FleetSort::Status, FleetSort::Cpu, FleetSort::Memory, FleetSort::Disk, FleetSort::Temperature];
+
+impl FleetSort {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FleetSort::Name => "host",
+            FleetSort::Status => "status",
+            FleetSort::Cpu => "cpu",
+            FleetSort::Memory => "memory",
+            FleetSort::Disk => "disk",
+            FleetSort::Temperature => "temperature",
+        }
+    }
+}
+
+// Agents polled for the fleet panel, each on its own thread.
+#[derive(Default)]
+pub struct Fleet {
+    hosts: Vec<Arc<Mutex<HostState>>>,
+    stale_after: Duration,
+    sort: FleetSort,
+    descending: bool,
+    // Index into the sorted rows.
+    selected: usize,
+    opened: Option<HostRow>,
+}
+
+impl Fleet {
+    pub fn start(endpoints: &[Endpoint], refresh: Duration, stale_after: Duration) -> Self {
+        let hosts: Vec<Arc<Mutex<HostState>>> = endpoints.iter().map(|endpoint| Arc::new(Mutex::new(HostState {
+            endpoint: endpoint.clone(),
+            hostname: None,
+            snapshot: None,
+            updated: None,
+            error: None,
+        }))).collect();
+        for (host, endpoint) in hosts.iter().zip(endpoints) {
+            let host = Arc::downgrade(host);
+            let endpoint = endpoint.clone();
+            thread::spawn(move || poll(host, endpoint, refresh));
+        }
+        Fleet { hosts, stale_after, ..Fleet::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.hosts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+
+    // Every host, in the current sort order.
+    pub fn rows(&self) -> Vec<HostRow> {
+        let now = Instant::now();
+        let mut rows: Vec<HostRow> = self.hosts.iter()
+            .filter_map(|host| host.lock().ok().map(|host| row(&host, now, self.stale_after)))
+            .collect();
+        rows.sort_by(|a, b| {
+            let ordering = match self.sort {
+                FleetSort::Name => a.name.cmp(&b.name),
+                FleetSort::Status => status_rank(&a.status).cmp(&status_rank(&b.status)),
+                FleetSort::Cpu => compare(a.cpu, b.cpu),
+                FleetSort::Memory => compare(a.memory, b.memory),
+                FleetSort::Disk => compare(a.disk, b.disk),
+                FleetSort::Temperature => compare(a.temperature, b.temperature),
+            };
+            let ordering = if self.descending { ordering.reverse() } else { ordering };
+            // Agents on one machine share a host name.
+            ordering.then_with(|| a.name.cmp(&b.name)).then_with(|| a.endpoint.to_string().cmp(&b.endpoint.to_string()))
+        });
+        rows
+    }
+
+    pub fn sort(&self) -> (FleetSort, bool) {
+        (self.sort, self.descending)
+    }
+
+    // Sorts by the next column; numbers sort busiest first.
+    pub fn next_sort(&mut self) {
+        let index = SORTS.iter().position(|sort| *sort == self.sort).unwrap_or(0);
+        self.sort = SORTS[(index + 1) % SORTS.len()];
+        self.descending = !matches!(self.sort, FleetSort::Name | FleetSort::Status);
+    }
+
+    pub fn reverse(&mut self) {
+        self.descending = !self.descending;
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected.min(self.len().saturating_sub(1))
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected() + 1).min(self.len().saturating_sub(1));
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected().saturating_sub(1);
+    }
+
+    // Asks for the selected host to be opened, see `take_opened`.
+    pub fn open_selected(&mut self) {
+        self.opened = self.rows().into_iter().nth(self.selected());
+    }
+
+    pub fn take_opened(&mut self) -> Option<HostRow> {
+        self.opened.take()
+    }
+}
+
+fn poll(host: Weak<Mutex<HostState>>, endpoint: Endpoint, refresh: Duration) {
+    let mut client: Option<Client> = None;
+    loop {
+        let Some(host) = host.upgrade() else { return };
+        let result = match &mut client {
+            Some(client) => client.snapshot(),
+            None => Client::connect(&endpoint).and_then(|connected| client.insert(connected).snapshot()),
+        };
+        if let Ok(mut state) = host.lock() {
+            if let Some(client) = &client {
+                state.hostname = Some(client.hello().hostname.clone());
+            }
+            match result {
+                Ok(snapshot) => {
+                    // A stuck agent keeps sending the same snapshot.
+                    if state.snapshot.as_ref().map(|old| old.timestamp) != Some(snapshot.timestamp) {
+                        state.updated = Some(Instant::now());
+                    }
+                    state.snapshot = Some(snapshot);
+                    state.error = None;
+                }
+                Err(err) => {
+                    client = None;
+                    state.error = Some(err.to_string());
+                }
+            }
+        }
+        drop(host);
+        thread::sleep(refresh);
+    }
+}
+
+fn row(host: &HostState, now: Instant, stale_after: Duration) -> HostRow {
+    let status = match (&host.error, host.updated) {
+        (Some(err), _) => HostStatus::Unreachable(err.clone()),
+        (None, None) => HostStatus::Connecting,
+        (None, Some(updated)) if now.duration_since(updated) > stale_after => HostStatus::Stale(now.duration_since(updated)),
+        (None, Some(_)) => HostStatus::Up,
+    };
+    let snapshot = host.snapshot.as_ref();
+    let available = |subsystem: Subsystem| snapshot.filter(|snapshot| snapshot.error(subsystem).is_none());
+    HostRow {
+        name: host.hostname.clone().unwrap_or_else(|| host.endpoint.to_string()),
+        endpoint: host.endpoint.clone(),
+        status,
+        cpu: available(Subsystem::Cpu).map(SystemSnapshot::cpu_average),
+        memory: available(Subsystem::Memory).map(|snapshot| snapshot.memory.used_ratio()),
+        disk: available(Subsystem::Disks)
+            .and_then(|snapshot| snapshot.disks.iter().map(|disk| disk.used_ratio()).max_by(f64::total_cmp)),
+        temperature: available(Subsystem::Components)
+            .and_then(|snapshot| snapshot.components.iter().map(|component| component.temperature).max_by(f32::total_cmp)),
+    }
+}
+
+// Problems first when sorting by status.
+fn status_rank(status: &HostStatus) -> u8 {
+    match status {
+        HostStatus::Unreachable(_) => 0,
+        HostStatus::Stale(_) => 1,
+        HostStatus::Connecting => 2,
+        HostStatus::Up => 3,
+    }
+}
+
+// Missing values sort below every number.
+fn compare<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, SystemTime};
+use crate::snapshot::{Subsystem, SystemSnapshot};
+
+pub const DEFAULT_CAPACITY: usize = 300;
+pub const DEFAULT_RESOLUTION: Duration = Duration::from_secs(1);
+// A day at the default resolution; larger capacities are capped.
+pub const MAX_CAPACITY: usize = 86_400;
+// Longest resolution the config accepts.
+pub const MAX_RESOLUTION: Duration = Duration::from_secs(3600);
+
+// Fixed-size ring buffer of timestamped values for a single metric. It
+// grows as points arrive, up to `capacity`.
+#[derive(Debug, Clone)]
+pub struct Series {
+    capacity: usize,
+    points: VecDeque<(SystemTime, f64)>,
+}
+
+impl Series {
+    pub fn new(capacity: usize) -> Self {
+        Series {
+            capacity: capacity.clamp(1, MAX_CAPACITY),
+            points: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, timestamp: SystemTime, value: f64) {
+        if self.points.len() == self.capacity {
+            self.points.pop_front();
+        }
+        self.points.push_back((timestamp, value));
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn latest(&self) -> Option<f64> {
+        self.points.back().map(|(_, value)| *value)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.points.iter().map(|(_, value)| *value).reduce(f64::max)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(SystemTime, f64)> {
+        self.points.iter()
+    }
+
+    // Points as (seconds relative to `now`, value), oldest first. The
+    // x values are negative, so `now` sits at 0 on the right of a chart.
+    pub fn chart_points(&self, now: SystemTime) -> Vec<(f64, f64)> {
+        self.points.iter().map(|(timestamp, value)| {
+            let age = now.duration_since(*timestamp).unwrap_or_default();
+            (-age.as_secs_f64(), *value)
+        }).collect()
+    }
+}
+
+// Rolling history of every metric the panels can chart.
+//
+// Percentages are stored as 0-100, rates as bytes per second,
+// temperatures in °C and load per core (1.0 keeps every core busy). Each
+// subsystem is recorded only when the snapshot carries a newer refresh of
+// it, stamped with that refresh time, and no closer together than
+// `resolution`; `capacity * resolution` is the time span covered by a full
+// history.
+#[derive(Debug, Clone)]
+pub struct History {
+    capacity: usize,
+    resolution: Duration,
+    last_recorded: HashMap<Subsystem, SystemTime>,
+    last_seen: HashMap<Subsystem, SystemTime>,
+    last_totals: HashMap<String, (u64, u64)>,
+    pub cpu_total: Series,
+    pub cpu_cores: Vec<Series>,
+    // Average and fastest core clock in MHz.
+    pub cpu_frequency: Series,
+    pub cpu_frequency_peak: Series,
+    // Time breakdown of all cores, in `CpuTimes::NAMES` order.
+    pub cpu_times: [Series; 6],
+    // Load averages and running and blocked tasks, per core.
+    pub load_one: Series,
+    pub load_five: Series,
+    pub load_fifteen: Series,
+    pub tasks_running: Series,
+    pub tasks_blocked: Series,
+    pub memory: Series,
+    pub swap: Series,
+    pub network_rx: BTreeMap<String, Series>,
+    pub network_tx: BTreeMap<String, Series>,
+    pub disk_read: Series,
+    pub disk_write: Series,
+    // By label and position, as sensors can share a label.
+    pub temperatures: BTreeMap<(String, usize), Series>,
+    pub battery_charge: Vec<Series>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new(DEFAULT_CAPACITY, DEFAULT_RESOLUTION)
+    }
+}
+
+impl History {
+    pub fn new(capacity: usize, resolution: Duration) -> Self {
+        let capacity = capacity.clamp(1, MAX_CAPACITY);
+        History {
+            capacity,
+            resolution,
+            last_recorded: HashMap::new(),
+            last_seen: HashMap::new(),
+            last_totals: HashMap::new(),
+            cpu_total: Series::new(capacity),
+            cpu_cores: vec![],
+            cpu_frequency: Series::new(capacity),
+            cpu_frequency_peak: Series::new(capacity),
+            cpu_times: std::array::from_fn(|_| Series::new(capacity)),
+            load_one: Series::new(capacity),
+            load_five: Series::new(capacity),
+            load_fifteen: Series::new(capacity),
+            tasks_running: Series::new(capacity),
+            tasks_blocked: Series::new(capacity),
+            memory: Series::new(capacity),
+            swap: Series::new(capacity),
+            network_rx: BTreeMap::new(),
+            network_tx: BTreeMap::new(),
+            disk_read: Series::new(capacity),
+            disk_write: Series::new(capacity),
+            temperatures: BTreeMap::new(),
+            battery_charge: vec![],
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn resolution(&self) -> Duration {
+        self.resolution
+    }
+
+    // The time span a full history covers.
+    pub fn span(&self) -> Duration {
+        self.resolution.checked_mul(self.capacity as u32).unwrap_or(Duration::MAX)
+    }
+
+    pub fn record(&mut self, snapshot: &SystemSnapshot) {
+        for subsystem in Subsystem::ALL {
+            let Some(refreshed) = snapshot.refreshed_at(subsystem) else {
+                continue;
+            };
+            // Time since the previous refresh of this subsystem, used to
+            // turn "bytes since last refresh" counters into rates.
+            let elapsed = match self.last_seen.insert(subsystem, refreshed) {
+                Some(previous) if previous >= refreshed => continue,
+                Some(previous) => refreshed.duration_since(previous).ok(),
+                None => None,
+            };
+            if let Some(last) = self.last_recorded.get(&subsystem) {
+                if refreshed.duration_since(*last).unwrap_or_default() < self.resolution {
+                    continue;
+                }
+            }
+            let since_recorded = self.last_recorded.insert(subsystem, refreshed)
+                .and_then(|last| refreshed.duration_since(last).ok());
+            self.record_subsystem(subsystem, snapshot, refreshed, elapsed, since_recorded);
+        }
+    }
+
+    fn record_subsystem(&mut self, subsystem: Subsystem, snapshot: &SystemSnapshot, now: SystemTime, elapsed: Option<Duration>, since_recorded: Option<Duration>) {
+        let capacity = self.capacity;
+        match subsystem {
+            Subsystem::Cpu => {
+                self.cpu_total.push(now, snapshot.cpu_average() as f64);
+                self.cpu_cores.resize_with(snapshot.cpus.len(), || Series::new(capacity));
+                for (series, cpu) in self.cpu_cores.iter_mut().zip(&snapshot.cpus) {
+                    series.push(now, cpu.usage as f64);
+                }
+                if let Some((average, fastest)) = snapshot.cpu_frequency() {
+                    self.cpu_frequency.push(now, average);
+                    self.cpu_frequency_peak.push(now, fastest as f64);
+                }
+                if let Some(times) = snapshot.cpu_times() {
+                    for (series, value) in self.cpu_times.iter_mut().zip(times.values()) {
+                        series.push(now, value as f64);
+                    }
+                }
+                if let (Some(load), false) = (snapshot.load, snapshot.cpus.is_empty()) {
+                    let cores = snapshot.cpus.len() as f64;
+                    self.load_one.push(now, load.one / cores);
+                    self.load_five.push(now, load.five / cores);
+                    self.load_fifteen.push(now, load.fifteen / cores);
+                    if let Some(running) = load.running {
+                        self.tasks_running.push(now, running as f64 / cores);
+                    }
+                    if let Some(blocked) = load.blocked {
+                        self.tasks_blocked.push(now, blocked as f64 / cores);
+                    }
+                }
+            }
+            Subsystem::Memory => {
+                let memory = &snapshot.memory;
+                self.memory.push(now, memory.used_ratio() * 100.0);
+                let swap = if memory.total_swap == 0 { 0.0 } else { memory.used_swap as f64 / memory.total_swap as f64 * 100.0 };
+                self.swap.push(now, swap);
+            }
+            Subsystem::Networks => {
+                // Rates come from the running totals so refreshes skipped
+                // by the resolution are still accounted for.
+                for network in &snapshot.networks {
+                    let totals = (network.total_received, network.total_transmitted);
+                    let previous = self.last_totals.insert(network.interface.clone(), totals);
+                    let (Some((rx, tx)), Some(since)) = (previous, since_recorded) else {
+                        continue;
+                    };
+                    let seconds = since.as_secs_f64();
+                    if seconds <= 0.0 {
+                        continue;
+                    }
+                    self.network_rx.entry(network.interface.clone())
+                        .or_insert_with(|| Series::new(capacity))
+                        .push(now, totals.0.saturating_sub(rx) as f64 / seconds);
+                    self.network_tx.entry(network.interface.clone())
+                        .or_insert_with(|| Series::new(capacity))
+                        .push(now, totals.1.saturating_sub(tx) as f64 / seconds);
+                }
+            }
+            Subsystem::Processes => {
+                let Some(elapsed) = elapsed.filter(|elapsed| !elapsed.is_zero()) else {
+                    return;
+                };
+                let seconds = elapsed.as_secs_f64();
+                let read: u64 = snapshot.processes.iter().map(|process| process.disk_read).sum();
+                let written: u64 = snapshot.processes.iter().map(|process| process.disk_written).sum();
+                self.disk_read.push(now, read as f64 / seconds);
+                self.disk_write.push(now, written as f64 / seconds);
+            }
+            Subsystem::Disks => {}
+            Subsystem::Components => {
+                for (i, component) in snapshot.components.iter().enumerate() {
+                    self.temperatures.entry((component.label.clone(), i))
+                        .or_insert_with(|| Series::new(capacity))
+                        .push(now, component.temperature as f64);
+                }
+            }
+            Subsystem::Batteries => {
+                self.battery_charge.resize_with(snapshot.batteries.len(), || Series::new(capacity));
+                for (series, battery) in self.battery_charge.iter_mut().zip(&snapshot.batteries) {
+                    series.push(now, battery.charge as f64 * 100.0);
+                }
+            }
+        }
+    }
+}
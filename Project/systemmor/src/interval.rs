@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(250);
+
+// The sampling intervals `faster`/`slower` step through.
+pub const INTERVAL_STEPS: [Duration; 9] = [
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+    Duration::from_secs(30),
+    Duration::from_secs(60),
+];
+
+pub fn faster(interval: Duration) -> Duration {
+    INTERVAL_STEPS.iter().rev().copied().find(|step| *step < interval).unwrap_or(INTERVAL_STEPS[0])
+}
+
+pub fn slower(interval: Duration) -> Duration {
+    INTERVAL_STEPS.iter().copied().find(|step| *step > interval).unwrap_or(INTERVAL_STEPS[INTERVAL_STEPS.len() - 1])
+}
+
+// Parses "250ms", "2s", "1.5s", "1m" or a bare number of milliseconds.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let value: f64 = number.parse().map_err(|_| format!("invalid duration '{}'", input))?;
+    let seconds = match unit.trim() {
+        "" | "ms" => value / 1000.0,
+        "s" => value,
+        "m" => value * 60.0,
+        other => return Err(format!("unknown duration unit '{}' in '{}' (use ms, s or m)", other, input)),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|err| format!("invalid duration '{}': {}", input, err))
+}
+
+// `parse_duration` for periods that must not be zero.
+pub fn parse_interval(input: &str) -> Result<Duration, String> {
+    let duration = parse_duration(input)?;
+    // Also catches durations that round down to nothing.
+    if duration.is_zero() {
+        return Err(format!("duration '{}' must be greater than zero", input.trim()));
+    }
+    Ok(duration)
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{} ms", duration.as_millis())
+    } else if duration.subsec_millis() == 0 {
+        format!("{} s", duration.as_secs())
+    } else {
+        format!("{:.1} s", duration.as_secs_f64())
+    }
+}
@@ -0,0 +1,169 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::alerts::Alerts;
+use crate::error::Error;
+use crate::snapshot::{BatterySample, ComponentSample, CpuSample, DiskSample, LoadSample, MemorySample, NetworkSample, ProcessSample, Subsystem, SystemSnapshot};
+
+// Bumped whenever a field is renamed, removed or changes meaning. Adding
+// fields does not change the version.
+pub const FORMAT_VERSION: u32 = 1;
+
+// The JSON form of a snapshot, see "JSON output" in the README. Only the
+// subsystems that were asked for are present; one that could not be read
+// is left out and has its reason under `errors` instead.
+//
+// Recordings are streams of these documents, so they can be read back
+// too; fields are borrowed when writing and owned after reading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Document<'a> {
+    pub version: u32,
+    // Seconds since the Unix epoch.
+    pub timestamp: f64,
+    // When each included subsystem was last refreshed, in seconds since
+    // the Unix epoch.
+    #[serde(default)]
+    pub refreshed: BTreeMap<Subsystem, f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<Cow<'a, [CpuSample]>>,
+    // Comes with `cpus`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load: Option<LoadSample>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Cow<'a, MemorySample>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub networks: Option<Cow<'a, [NetworkSample]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub processes: Option<Cow<'a, [ProcessSample]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disks: Option<Cow<'a, [DiskSample]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub components: Option<Cow<'a, [ComponentSample]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batteries: Option<Cow<'a, [BatterySample]>>,
+    #[serde(default)]
+    pub errors: BTreeMap<Subsystem, String>,
+    // Firing alerts, when printing samples with alert rules configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alerts: Option<Vec<AlertDocument>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertDocument {
+    pub name: String,
+    pub when: String,
+    // Seconds since the Unix epoch.
+    pub since: f64,
+}
+
+impl<'a> Document<'a> {
+    pub fn new(snapshot: &'a SystemSnapshot, subsystems: &[Subsystem]) -> Self {
+        let included = |subsystem: Subsystem| subsystems.contains(&subsystem) && snapshot.error(subsystem).is_none();
+        Document {
+            version: FORMAT_VERSION,
+            timestamp: seconds(snapshot.timestamp),
+            refreshed: snapshot.refreshed.iter()
+                .filter(|(subsystem, _)| subsystems.contains(subsystem))
+                .map(|(subsystem, refreshed)| (*subsystem, seconds(*refreshed)))
+                .collect(),
+            cpus: included(Subsystem::Cpu).then_some(Cow::Borrowed(snapshot.cpus.as_slice())),
+            load: snapshot.load.filter(|_| included(Subsystem::Cpu)),
+            memory: included(Subsystem::Memory).then_some(Cow::Borrowed(&snapshot.memory)),
+            networks: included(Subsystem::Networks).then_some(Cow::Borrowed(snapshot.networks.as_slice())),
+            processes: included(Subsystem::Processes).then_some(Cow::Borrowed(snapshot.processes.as_slice())),
+            disks: included(Subsystem::Disks).then_some(Cow::Borrowed(snapshot.disks.as_slice())),
+            components: included(Subsystem::Components).then_some(Cow::Borrowed(snapshot.components.as_slice())),
+            batteries: included(Subsystem::Batteries).then_some(Cow::Borrowed(snapshot.batteries.as_slice())),
+            errors: snapshot.errors.iter()
+                .filter(|(subsystem, _)| subsystems.contains(subsystem))
+                .map(|(subsystem, err)| (*subsystem, err.to_string()))
+                .collect(),
+            alerts: None,
+        }
+    }
+
+    pub fn with_alerts(mut self, alerts: &Alerts) -> Self {
+        self.alerts = Some(alerts.firing()
+            .map(|(rule, since)| AlertDocument { name: rule.name.clone(), when: rule.to_string(), since: seconds(since) })
+            .collect());
+        self
+    }
+
+    // The subsystems this document has data or an error for.
+    pub fn subsystems(&self) -> Vec<Subsystem> {
+        let present = [
+            (Subsystem::Cpu, self.cpus.is_some()),
+            (Subsystem::Memory, self.memory.is_some()),
+            (Subsystem::Networks, self.networks.is_some()),
+            (Subsystem::Processes, self.processes.is_some()),
+            (Subsystem::Disks, self.disks.is_some()),
+            (Subsystem::Components, self.components.is_some()),
+            (Subsystem::Batteries, self.batteries.is_some()),
+        ];
+        present.into_iter()
+            .filter(|(subsystem, present)| *present || self.errors.contains_key(subsystem))
+            .map(|(subsystem, _)| subsystem)
+            .collect()
+    }
+
+    // Updates `snapshot` with the subsystems in this document, leaving the
+    // others as they were.
+    pub fn apply(&self, snapshot: &mut SystemSnapshot) {
+        snapshot.timestamp = time(self.timestamp);
+        for subsystem in self.subsystems() {
+            let refreshed = self.refreshed.get(&subsystem).copied().unwrap_or(self.timestamp);
+            snapshot.refreshed.insert(subsystem, time(refreshed));
+            match self.errors.get(&subsystem) {
+                Some(message) => {
+                    snapshot.errors.insert(subsystem, Error::Replayed(message.clone()));
+                }
+                None => {
+                    snapshot.errors.remove(&subsystem);
+                }
+            }
+        }
+        snapshot.cpus = owned(&self.cpus, Subsystem::Cpu, self, std::mem::take(&mut snapshot.cpus));
+        if self.subsystems().contains(&Subsystem::Cpu) {
+            snapshot.load = self.load;
+        }
+        snapshot.networks = owned(&self.networks, Subsystem::Networks, self, std::mem::take(&mut snapshot.networks));
+        snapshot.processes = owned(&self.processes, Subsystem::Processes, self, std::mem::take(&mut snapshot.processes));
+        snapshot.disks = owned(&self.disks, Subsystem::Disks, self, std::mem::take(&mut snapshot.disks));
+        snapshot.components = owned(&self.components, Subsystem::Components, self, std::mem::take(&mut snapshot.components));
+        snapshot.batteries = owned(&self.batteries, Subsystem::Batteries, self, std::mem::take(&mut snapshot.batteries));
+        if let Some(memory) = &self.memory {
+            snapshot.memory = memory.clone().into_owned();
+        } else if self.errors.contains_key(&Subsystem::Memory) {
+            snapshot.memory = MemorySample::default();
+        }
+    }
+}
+
+// A single, indented document.
+pub fn to_json(document: &Document<'_>) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(document)
+}
+
+// One document on a single line, for newline-delimited streams.
+pub fn to_json_line(document: &Document<'_>) -> serde_json::Result<String> {
+    serde_json::to_string(document)
+}
+
+// The new value of one subsystem's list: the document's if it has one,
+// empty if it has an error instead, otherwise unchanged.
+fn owned<T: Clone>(field: &Option<Cow<'_, [T]>>, subsystem: Subsystem, document: &Document<'_>, current: Vec<T>) -> Vec<T> {
+    match field {
+        Some(values) => values.to_vec(),
+        None if document.errors.contains_key(&subsystem) => vec![],
+        None => current,
+    }
+}
+
+fn seconds(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs_f64()).unwrap_or_default()
+}
+
+fn time(seconds: f64) -> SystemTime {
+    UNIX_EPOCH + Duration::try_from_secs_f64(seconds).unwrap_or_default()
+}
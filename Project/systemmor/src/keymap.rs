@@ -0,0 +1,400 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use crate::error::{Error, Result};
+
+// Everything a key binding can trigger.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    ScrollUp,
+    ScrollDown,
+    Faster,
+    Slower,
+    NextTheme,
+    Pause,
+    SeekForward,
+    SeekBackward,
+    // Leave a host opened from the fleet panel.
+    Back,
+    // CPU panel: cycle the chart between the average, every core and one
+    // core, and pick that core.
+    CpuView,
+    CpuNextCore,
+    CpuPrevCore,
+    // CPU panel: switch between the bar graph and the heatmap.
+    CpuHeatmap,
+    // CPU panel: switch between the bar graph and where the time goes.
+    CpuBreakdown,
+    // Fleet panel: sort by the next column, reverse the order and open the
+    // selected host.
+    FleetSort,
+    FleetReverse,
+    FleetOpen,
+    // Switch to the panel with this name, see `panel::panel_name`.
+    ShowPanel(String),
+}
+
+// Actions configurable by name under `[keys]`, in the order the help
+// text lists them.
+const NAMED_ACTIONS: [(&str, Action, &str); 18] = [
+    ("quit", Action::Quit, "exit"),
+    ("scroll_up", Action::ScrollUp, "scroll up"),
+    ("scroll_down", Action::ScrollDown, "scroll down"),
+    ("faster", Action::Faster, "sample faster (or speed up a replay)"),
+    ("slower", Action::Slower, "sample slower (or slow down a replay)"),
+    ("theme", Action::NextTheme, "switch theme"),
+    ("pause", Action::Pause, "pause or resume"),
+    ("seek_forward", Action::SeekForward, "skip forward in a replay"),
+    ("seek_backward", Action::SeekBackward, "skip back in a replay"),
+    ("back", Action::Back, "return to this machine from a fleet host"),
+    ("cpu_view", Action::CpuView, "switch the CPU chart between the average, every core and one core"),
+    ("cpu_next_core", Action::CpuNextCore, "chart the next core"),
+    ("cpu_prev_core", Action::CpuPrevCore, "chart the previous core"),
+    ("cpu_heatmap", Action::CpuHeatmap, "switch the CPU bars to a heatmap and back"),
+    ("cpu_breakdown", Action::CpuBreakdown, "switch the CPU bars to where the time goes and back"),
+    ("fleet_sort", Action::FleetSort, "sort the fleet by the next column"),
+    ("fleet_reverse", Action::FleetReverse, "reverse the fleet's order"),
+    ("fleet_open", Action::FleetOpen, "open the selected fleet host"),
+];
+
+fn default_keys(action: &Action) -> &'static [&'static str] {
+    match action {
+        Action::Quit => &["q", "ctrl+c"],
+        Action::ScrollUp => &["up"],
+        Action::ScrollDown => &["down"],
+        Action::Faster => &["+", "="],
+        Action::Slower => &["-"],
+        Action::NextTheme => &["T"],
+        Action::Pause => &["space"],
+        Action::SeekForward => &["right"],
+        Action::SeekBackward => &["left"],
+        Action::Back => &["esc"],
+        Action::CpuView => &["v"],
+        Action::CpuNextCore => &["]"],
+        Action::CpuPrevCore => &["["],
+        Action::CpuHeatmap => &["H"],
+        Action::CpuBreakdown => &["B"],
+        Action::FleetSort => &["s"],
+        Action::FleetReverse => &["r"],
+        Action::FleetOpen => &["enter"],
+        Action::ShowPanel(_) => &[],
+    }
+}
+
+impl Action {
+    // The panel, by name, of actions that only do something there.
+    pub fn panel(&self) -> Option<&'static str> {
+        match self {
+            Action::CpuView | Action::CpuNextCore | Action::CpuPrevCore | Action::CpuHeatmap | Action::CpuBreakdown => Some("cpu"),
+            Action::FleetSort | Action::FleetReverse | Action::FleetOpen => Some("fleet"),
+            _ => None,
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Action::ShowPanel(name) => format!("show the {} panel", name),
+            action => NAMED_ACTIONS.iter()
+                .find(|(_, named, _)| named == action)
+                .map(|(_, _, description)| description.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// A single key press with its modifiers, e.g. `ctrl+w`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already part of the character for printable keys, and
+        // terminals disagree on whether they report it.
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        KeyChord { code, modifiers: modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT) }
+    }
+
+    // Parses "q", "G", "ctrl+c", "alt+enter", "shift+tab", "f5", "space".
+    pub fn parse(input: &str) -> Result<Self> {
+        let invalid = |reason: &str| Error::Config(format!("invalid key '{}': {}", input, reason));
+        let (modifiers, key) = match input.rfind('+') {
+            // A trailing '+' is the plus key itself, e.g. "+" or "ctrl++".
+            Some(index) if index + 1 == input.len() => match input[..index].strip_suffix('+') {
+                Some(prefix) => (prefix, "+"),
+                None if index == 0 => ("", "+"),
+                None => return Err(invalid("expected a key after '+'")),
+            },
+            Some(index) => (&input[..index], &input[index + 1..]),
+            None => ("", input),
+        };
+
+        let mut parsed = KeyModifiers::NONE;
+        for modifier in modifiers.split('+').filter(|modifier| !modifier.is_empty()) {
+            parsed |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => return Err(invalid(&format!("unknown modifier '{}'", other))),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').and_then(|number| number.parse::<u8>().ok()) {
+                    Some(number) if (1..=24).contains(&number) => KeyCode::F(number),
+                    _ => return Err(invalid("unknown key name")),
+                },
+            },
+        };
+        // "shift+g" is the key that types 'G'.
+        let code = match code {
+            KeyCode::Char(c) if parsed.contains(KeyModifiers::SHIFT) => KeyCode::Char(c.to_ascii_uppercase()),
+            code => code,
+        };
+        Ok(KeyChord::new(code, parsed))
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(event: KeyEvent) -> Self {
+        KeyChord::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::F(number) => write!(f, "F{}", number),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            KeyCode::BackTab => write!(f, "shift+tab"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+// Keys pressed one after another, e.g. "g g".
+pub type KeySequence = Vec<KeyChord>;
+
+pub fn parse_sequence(input: &str) -> Result<KeySequence> {
+    let sequence = input.split_whitespace().map(KeyChord::parse).collect::<Result<KeySequence>>()?;
+    if sequence.is_empty() {
+        return Err(Error::Config("empty key binding".to_string()));
+    }
+    Ok(sequence)
+}
+
+pub fn format_sequence(sequence: &[KeyChord]) -> String {
+    sequence.iter().map(KeyChord::to_string).collect::<Vec<_>>().join(" ")
+}
+
+// One key or a list of keys in the config file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    pub fn keys(&self) -> Vec<&str> {
+        match self {
+            KeyList::One(key) => vec![key.as_str()],
+            KeyList::Many(keys) => keys.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+// The `[keys]` table. Listing an action replaces its default keys; an
+// empty list unbinds it.
+//
+//     [keys]
+//     quit = ["q", "ctrl+c"]
+//     scroll_down = ["down", "j"]
+//
+//     [keys.panels]
+//     process = ["p", "g p"]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct KeysConfig {
+    #[serde(default)]
+    pub panels: BTreeMap<String, KeyList>,
+    #[serde(flatten)]
+    pub actions: BTreeMap<String, KeyList>,
+}
+
+pub enum KeyResult {
+    Action(Action),
+    // The key starts a longer sequence; wait for the next one.
+    Pending,
+    Unbound,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    bindings: Vec<(KeySequence, Action)>,
+    pending: KeySequence,
+}
+
+impl Keymap {
+    // Builds the active key map from the built-in defaults, the panels'
+    // own hotkeys and the user's `[keys]` table. `panels` is the list of
+    // enabled panels as (name, default hotkey); actions of other panels
+    // are left unbound.
+    pub fn new(panels: &[(String, char)], config: &KeysConfig) -> Result<Self> {
+        let mut keymap = Keymap::default();
+        let enabled = |action: &Action| action.panel().is_none_or(|panel| panels.iter().any(|(name, _)| name == panel));
+
+        for (name, action, _) in NAMED_ACTIONS.iter() {
+            if !config.actions.contains_key(*name) && enabled(action) {
+                for key in default_keys(action) {
+                    keymap.bind(parse_sequence(key)?, action.clone())?;
+                }
+            }
+        }
+        for (name, keys) in &config.actions {
+            let action = named_action(name).ok_or_else(|| {
+                let known: Vec<&str> = NAMED_ACTIONS.iter().map(|(name, _, _)| *name).collect();
+                Error::Config(format!("unknown action '{}' in [keys] (available: {}, panels)", name, known.join(", ")))
+            })?;
+            for key in keys.keys() {
+                let sequence = parse_sequence(key)?;
+                if enabled(&action) {
+                    keymap.bind(sequence, action.clone())?;
+                }
+            }
+        }
+
+        for name in config.panels.keys() {
+            if !panels.iter().any(|(panel, _)| panel == name) {
+                let known: Vec<&str> = panels.iter().map(|(panel, _)| panel.as_str()).collect();
+                return Err(Error::Config(format!("unknown panel '{}' in [keys.panels] (available: {})", name, known.join(", "))));
+            }
+        }
+        for (name, hotkey) in panels {
+            let action = Action::ShowPanel(name.clone());
+            match config.panels.get(name) {
+                Some(keys) => {
+                    for key in keys.keys() {
+                        keymap.bind(parse_sequence(key)?, action.clone())?;
+                    }
+                }
+                None => keymap.bind(vec![KeyChord::new(KeyCode::Char(*hotkey), KeyModifiers::NONE)], action)?,
+            }
+        }
+        Ok(keymap)
+    }
+
+    // Adds a binding, rejecting sequences that are already bound or that
+    // would shadow (or be shadowed by) a longer sequence.
+    pub fn bind(&mut self, sequence: KeySequence, action: Action) -> Result<()> {
+        for (bound, bound_action) in &self.bindings {
+            let shared = bound.len().min(sequence.len());
+            if bound[..shared] == sequence[..shared] {
+                return Err(Error::Config(format!(
+                    "key '{}' for {} conflicts with '{}' for {}",
+                    format_sequence(&sequence), action.description(),
+                    format_sequence(bound), bound_action.description(),
+                )));
+            }
+        }
+        self.bindings.push((sequence, action));
+        Ok(())
+    }
+
+    pub fn feed(&mut self, event: KeyEvent) -> KeyResult {
+        self.pending.push(KeyChord::from(event));
+        if let Some((_, action)) = self.bindings.iter().find(|(sequence, _)| *sequence == self.pending) {
+            self.pending.clear();
+            return KeyResult::Action(action.clone());
+        }
+        if self.bindings.iter().any(|(sequence, _)| sequence.starts_with(&self.pending)) {
+            return KeyResult::Pending;
+        }
+        // A broken sequence should not swallow the key that broke it.
+        let restart = self.pending.len() > 1;
+        self.pending.clear();
+        if restart {
+            return self.feed(event);
+        }
+        KeyResult::Unbound
+    }
+
+    pub fn pending(&self) -> &[KeyChord] {
+        &self.pending
+    }
+
+    pub fn keys_for(&self, action: &Action) -> Vec<&KeySequence> {
+        self.bindings.iter()
+            .filter(|(_, bound)| bound == action)
+            .map(|(sequence, _)| sequence)
+            .collect()
+    }
+
+    // The first single-character binding of a panel, to underline in the
+    // tab bar.
+    pub fn panel_hotkey(&self, name: &str) -> Option<char> {
+        self.keys_for(&Action::ShowPanel(name.to_string())).into_iter().find_map(|sequence| match sequence.as_slice() {
+            [KeyChord { code: KeyCode::Char(c), modifiers }] if modifiers.is_empty() => Some(*c),
+            _ => None,
+        })
+    }
+
+    // Every bound action with its keys, for the help screen.
+    pub fn help(&self) -> Vec<(String, Action)> {
+        let mut actions: Vec<&Action> = vec![];
+        for (_, action) in &self.bindings {
+            if !actions.contains(&action) {
+                actions.push(action);
+            }
+        }
+        actions.into_iter().map(|action| {
+            let keys: Vec<String> = self.keys_for(action).into_iter().map(|sequence| format_sequence(sequence)).collect();
+            (keys.join(", "), action.clone())
+        }).collect()
+    }
+}
+
+fn named_action(name: &str) -> Option<Action> {
+    NAMED_ACTIONS.iter().find(|(named, _, _)| *named == name).map(|(_, action, _)| action.clone())
+}
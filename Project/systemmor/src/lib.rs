@@ -1,6 +1,5 @@
-use std::io::Stdout;
 use ratatui::{
-    backend::CrosstermBackend,
+    backend::{Backend, TestBackend},
     widgets::*,
     layout::{Layout, Constraint, Direction},
     text::{Span, Line},
@@ -8,71 +7,234 @@ use ratatui::{
     prelude::*,
 };
 use ratatui::symbols::scrollbar;
-use sysinfo::{System, SystemExt, CpuExt, NetworkExt, DiskExt, ComponentExt, ProcessExt};
+use std::time::SystemTime;
+use alerts::AlertState;
+use command::{CommandConfig, CommandState, CommandWidget};
+use fleet::{FleetSort, HostStatus};
+use panel::{CpuGraph, CpuView};
+use snapshot::{CpuSample, CpuTimes};
+use interval::format_duration;
+use units::format_frequency;
+use replay::format_timestamp;
+use keymap::{format_sequence, KeysConfig};
+
+pub mod error;
+pub mod snapshot;
+pub mod sampler;
+pub mod source;
+pub mod fake;
+pub mod history;
+pub mod panel;
+pub mod interval;
+pub mod units;
+pub mod config;
+pub mod keymap;
+pub mod theme;
+pub mod report;
+pub mod json;
+pub mod metrics;
+pub mod exporter;
+pub mod replay;
+pub mod agent;
+pub mod fleet;
+pub mod alerts;
+pub mod command;
+pub mod procfs;
+pub mod terminal;
+pub mod cli;
+
+pub use error::{Error, Result};
+pub use snapshot::{Subsystem, SystemSnapshot};
+pub use sampler::Sampler;
+pub use history::History;
+pub use config::Config;
+pub use units::Units;
+pub use keymap::{Action, Keymap};
+pub use theme::{Theme, Themes};
+pub use panel::{Panel, PanelRegistry};
+pub use alerts::Alerts;
+pub use fleet::Fleet;
 
-#[derive(Default)]
 pub struct App {
     pub vertical_scroll_state: ScrollbarState,
     pub vertical_scroll: usize,
+    pub history: History,
+    pub units: Units,
+    pub keymap: Keymap,
+    pub alerts: Alerts,
+    pub fleet: Fleet,
+}
+
+// With the built-in panels' key bindings.
+impl Default for App {
+    fn default() -> Self {
+        App {
+            vertical_scroll_state: ScrollbarState::default(),
+            vertical_scroll: 0,
+            history: History::default(),
+            units: Units::default(),
+            keymap: Keymap::new(&PanelRegistry::<TestBackend>::default().hotkeys(), &KeysConfig::default()).unwrap_or_default(),
+            alerts: Alerts::default(),
+            fleet: Fleet::default(),
+        }
+    }
+}
+
+impl App {
+    // "v: view, [ ]: core" for the hints whose actions are all bound, each
+    // named by its first key.
+    pub fn key_hints(&self, hints: &KeyHints) -> String {
+        hints.iter().filter_map(|(actions, hint)| {
+            let keys = actions.iter()
+                .map(|action| self.keymap.keys_for(action).first().map(|keys| format_sequence(keys)))
+                .collect::<Option<Vec<_>>>()?;
+            Some(format!("{}: {}", keys.join(" "), hint))
+        }).collect::<Vec<_>>().join(", ")
+    }
+
+    pub fn scroll_down(&mut self) {
+        self.vertical_scroll = self.vertical_scroll.saturating_add(1);
+        self.vertical_scroll_state = self
+            .vertical_scroll_state
+            .position(self.vertical_scroll as u16);
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.vertical_scroll = self.vertical_scroll.saturating_sub(1);
+        self.vertical_scroll_state = self
+            .vertical_scroll_state
+            .position(self.vertical_scroll as u16);
+    }
 }
 
-pub fn display_home(rect: &mut Frame<'_, CrosstermBackend<Stdout>>, chunks: Rect) {
+// Key hints such as "v: view": the actions whose keys are shown, and what
+// they do.
+pub type KeyHints = [(&'static [Action], &'static str)];
+
+// Hints of the keys that change the CPU chart.
+const CPU_CHART_KEYS: &KeyHints = &[(&[Action::CpuView], "view"), (&[Action::CpuPrevCore, Action::CpuNextCore], "core")];
+const CPU_BARS_KEYS: &KeyHints = &[(&[Action::CpuHeatmap], "heatmap"), (&[Action::CpuBreakdown], "time")];
+const CPU_HEATMAP_KEYS: &KeyHints = &[(&[Action::CpuHeatmap], "bars")];
+const CPU_TIME_KEYS: &KeyHints = &[(&[Action::CpuBreakdown], "bars")];
+const CPU_PANEL_KEYS: &KeyHints = &[
+    (&[Action::CpuView], "view"),
+    (&[Action::CpuPrevCore, Action::CpuNextCore], "core"),
+    (&[Action::CpuHeatmap], "heatmap"),
+    (&[Action::CpuBreakdown], "time"),
+];
+const FLEET_KEYS: &KeyHints = &[(&[Action::FleetOpen], "open"), (&[Action::FleetSort], "sort"), (&[Action::FleetReverse], "reverse")];
+
+// Each panel's own keys, for the home page.
+const PANEL_KEYS: [(&str, &KeyHints); 2] = [
+    ("CPU", CPU_PANEL_KEYS),
+    ("Fleet", FLEET_KEYS),
+];
+
+pub fn display_home<B: Backend>(rect: &mut Frame<'_, B>, chunks: Rect, app: &App, theme: &Theme) {
     let block = Block::default()
         .title("Home")
         .borders(Borders::ALL);
     rect.render_widget(block, chunks);
 
-    let home_text = vec![
-        Line::styled("🖥️  Welcome to System Monitor! 🖥️", Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow)),
-        Line::from("\n"),
-        Line::from(vec![
-            Span::raw("Press "),
-            Span::styled("q", Style::default().add_modifier(Modifier::BOLD).fg(Color::LightYellow)),
-            Span::raw(" to exit."),
-        ]),
-        Line::from(vec![
-            Span::raw("\n")
-        ]),
-        Line::from(vec![
-            Span::raw("Press "),
-            Span::styled("↑, ↓", Style::default().add_modifier(Modifier::BOLD).fg(Color::LightYellow)),
-            Span::raw(" to scroll."),
-        ]),
-        Line::from(vec![
-            Span::raw("\n")
-        ]),
-        Line::from(vec![
-            Span::raw("Press "),
-            Span::styled("c, m, n, p, d, t", Style::default().add_modifier(Modifier::BOLD).fg(Color::LightYellow)),
-            Span::raw(" and"),
-            Span::styled(" b", Style::default().add_modifier(Modifier::BOLD).fg(Color::LightYellow)),
-            Span::raw(" to choose what to display."),
-        ]),
+    let key_style = theme.key.add_modifier(Modifier::BOLD);
+    let mut home_text = vec![
+        Line::styled("🖥️  Welcome to System Monitor! 🖥️", theme.title.add_modifier(Modifier::BOLD)),
     ];
 
+    // The help text follows the active key bindings, with every panel
+    // switch folded into one line and each panel's own keys into another.
+    let mut panel_keys = vec![];
+    for (keys, action) in app.keymap.help() {
+        if let Action::ShowPanel(_) = action {
+            panel_keys.push(keys);
+            continue;
+        }
+        if action.panel().is_some() {
+            continue;
+        }
+        home_text.push(Line::from("\n"));
+        home_text.push(Line::from(vec![
+            Span::raw("Press "),
+            Span::styled(keys, key_style),
+            Span::raw(format!(" to {}.", action.description())),
+        ]));
+    }
+    if let Some(last) = panel_keys.pop() {
+        let mut line = vec![Span::raw("Press ")];
+        if !panel_keys.is_empty() {
+            line.push(Span::styled(panel_keys.join(", "), key_style));
+            line.push(Span::raw(" and "));
+        }
+        line.push(Span::styled(last, key_style));
+        line.push(Span::raw(" to choose what to display."));
+        home_text.push(Line::from("\n"));
+        home_text.push(Line::from(line));
+    }
+    for (panel, hints) in PANEL_KEYS {
+        let hints = app.key_hints(hints);
+        if !hints.is_empty() {
+            home_text.push(Line::from("\n"));
+            home_text.push(Line::from(format!("In the {} panel: {}.", panel, hints)));
+        }
+    }
+
     let home_paragraph = Paragraph::new(home_text)
         .block(Block::default().borders(Borders::ALL).title("Home"))
-        .style(Style::default().fg(Color::White))
+        .style(theme.text)
         .alignment(Alignment::Center);
     rect.render_widget(home_paragraph, chunks);
 }
 
-pub fn display_cpu(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdout>>, chunks: Rect){
+pub fn display_cpu<B: Backend>(snapshot: &SystemSnapshot, rect: &mut Frame<'_, B>, chunks: Rect, app: &App, view: CpuView, graph: CpuGraph, theme: &Theme){
+    if let Some(err) = snapshot.error(Subsystem::Cpu) {
+        display_unavailable(rect, chunks, "CPU Usage", err, theme);
+        return;
+    }
+    let selected = match view {
+        CpuView::Core(core) => Some(core.min(snapshot.cpus.len().saturating_sub(1))),
+        _ => None,
+    };
     let mut cpu_usage = vec![];
-    let mut cpu_all: f32 = 0.0;
+    // The clock range goes on each core only where they differ, as on
+    // CPUs with performance and efficiency cores.
+    let range = |cpu: &CpuSample| cpu.min_frequency.zip(cpu.max_frequency);
+    let shared_range = snapshot.cpus.first().and_then(range)
+        .filter(|first| snapshot.cpus.iter().all(|cpu| range(cpu) == Some(*first)));
 
-    for (i, cpu) in sys.cpus().iter().enumerate() {
-        let cpu_stat = format!("CPU {} {:.2}%", i, cpu.cpu_usage());
-        cpu_all += cpu.cpu_usage();
+    for (i, cpu) in snapshot.cpus.iter().enumerate() {
+        let mut cpu_stat = format!("CPU {} {:.2}%", i, cpu.usage);
+        if cpu.frequency > 0 {
+            cpu_stat += &format!(" {}", format_frequency(cpu.frequency));
+        }
+        if let (None, Some((min, max))) = (shared_range, range(cpu)) {
+            cpu_stat += &format!(" ({}-{})", format_frequency(min), format_frequency(max));
+        }
+        let style = if selected == Some(i) { theme.highlight } else { theme.text };
         cpu_usage.push(Line::from(vec![
-            Span::raw(cpu_stat),
+            Span::styled(cpu_stat, style),
         ]));
     }
 
-    let cpu_avg = format!("Average CPU Usage: {:.2}%", cpu_all / sys.cpus().len() as f32);
-    cpu_usage.insert(0, Line::from(vec![
+    let cpu_avg = format!("Average CPU Usage: {:.2}%", snapshot.cpu_average());
+    let mut summary = vec![Line::from(vec![
         Span::raw(cpu_avg),
-    ]));
+    ])];
+    let frequency = snapshot.cpu_frequency();
+    if let Some((average, _)) = frequency {
+        summary.push(Line::from(format!("Average clock: {}", format_frequency(average.round() as u64))));
+    }
+    if let Some((min, max)) = shared_range {
+        summary.push(Line::from(format!("Clock range: {}-{}", format_frequency(min), format_frequency(max))));
+    }
+    if let Some(load) = snapshot.load {
+        let per_core = load.one / snapshot.cpus.len().max(1) as f64;
+        summary.push(Line::from(format!("Load: {:.2} {:.2} {:.2} ({:.2}/core)", load.one, load.five, load.fifteen, per_core)));
+        if let (Some(running), Some(blocked)) = (load.running, load.blocked) {
+            let tasks = load.tasks.map(|tasks| format!(" of {}", tasks)).unwrap_or_default();
+            summary.push(Line::from(format!("Tasks: {} running, {} blocked{}", running, blocked, tasks)));
+        }
+    }
+    cpu_usage.splice(0..0, summary);
     let chunk_cpu = Layout::default()
         .direction(Direction::Horizontal)
         .constraints(
@@ -83,88 +245,391 @@ pub fn display_cpu(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdout>>,
         )
         .split(chunks);
 
+    let title = match snapshot.cpu_brand() {
+        Some(brand) => format!("CPU Usage ({})", brand),
+        None => "CPU Usage".to_string(),
+    };
     let cpu_paragraph = Paragraph::new(cpu_usage)
-        .block(Block::default().borders(Borders::ALL).title("CPU Usage"))
-        .style(Style::default().fg(Color::White))
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .style(theme.text)
         .alignment(ratatui::layout::Alignment::Left);
 
+    // Usage chart, then the clock and load charts side by side where the
+    // system reports them, then the bars.
+    let clocked = frequency.is_some() || !app.history.cpu_frequency.is_empty();
+    let loaded = snapshot.load.is_some() || !app.history.load_one.is_empty();
+    let constraints = if clocked || loaded {
+        vec![Constraint::Percentage(40), Constraint::Percentage(30), Constraint::Percentage(30)]
+    } else {
+        vec![Constraint::Percentage(50), Constraint::Percentage(50)]
+    };
     let chunk2_cpu = Layout::default()
         .direction(Direction::Vertical)
-        .constraints(
-            [
-                Constraint::Percentage(50),
-                Constraint::Percentage(50),
-            ].as_ref()
-        )
+        .constraints(constraints)
         .split(chunk_cpu[1]);
+    let bars_area = chunk2_cpu[chunk2_cpu.len() - 1];
+    let charts = clocked as u32 + loaded as u32;
+    let middle = if charts > 0 {
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, charts); charts as usize])
+            .split(chunk2_cpu[1])
+            .to_vec()
+    } else {
+        vec![]
+    };
 
-    let mut cpu_chart_data: Vec<(f64, f64)> = vec![];
-    let avg_cpu_chart = cpu_all / sys.cpus().len() as f32;
-    for (i, cpu) in sys.cpus().iter().enumerate() {
-        let cpu_stat = i as f64; // X-axis value
-        let cpu_usage = cpu.cpu_usage() as f64; // Y-axis value
-        cpu_chart_data.push((cpu_stat, cpu_usage));
+    // Seconds before the latest sample, so the newest point sits at 0 on
+    // the right; a replay charts the time being replayed.
+    let span = app.history.span().as_secs_f64().max(0.1);
+    let seconds = |seconds: f64| if span < 10.0 { format!("{:.1}", seconds) } else { format!("{:.0}", seconds) };
+    let mut series = vec![];
+    if let CpuView::Average | CpuView::AllCores = view {
+        series.push(("Average".to_string(), &app.history.cpu_total, theme.chart));
     }
-
-    let cpu_avg: Vec<(f64, f64)> = vec![(0.0, cpu_all as f64 / sys.cpus().len() as f64), (sys.cpus().len() as f64 , avg_cpu_chart as f64)];
-
-    let datasets = vec![
-        // Dataset::default()
-        //     .name("All CPU Usage")
-        //     .marker(symbols::Marker::Braille)
-        //     .graph_type(GraphType::Line)
-        //     .style(Style::default().fg(Color::Magenta))
-        //     .data(&cpu_chart_data),
+    let core_styles = [theme.gauge, theme.bar, theme.battery, theme.highlight, theme.key, theme.axis];
+    for (i, core) in app.history.cpu_cores.iter().enumerate() {
+        let shown = match view {
+            CpuView::Average => false,
+            CpuView::AllCores => true,
+            CpuView::Core(_) => selected == Some(i),
+        };
+        if shown {
+            series.push((format!("CPU {}", i), core, core_styles[i % core_styles.len()]));
+        }
+    }
+    let points: Vec<Vec<(f64, f64)>> = series.iter().map(|(_, core, _)| core.chart_points(snapshot.timestamp)).collect();
+    let datasets = series.iter().zip(&points).map(|((name, _, style), points)| {
         Dataset::default()
-            .name("Average CPU Usage")
+            .name(name.clone())
             .marker(symbols::Marker::Braille)
             .graph_type(GraphType::Line)
-            .style(Style::default().fg(Color::Yellow))
-            .data(&cpu_avg)
-    ];
+            .style(*style)
+            .data(points)
+    }).collect();
 
+    let view_name = match (view, selected) {
+        (CpuView::Average, _) => "average".to_string(),
+        (CpuView::AllCores, _) => "all cores".to_string(),
+        (CpuView::Core(_), Some(core)) => format!("CPU {}", core),
+        (CpuView::Core(_), None) => "no cores".to_string(),
+    };
+    let hints = app.key_hints(CPU_CHART_KEYS);
     let chart = Chart::new(datasets)
-        .block(Block::default().title("CPU Chart").borders(Borders::ALL))
+        .block(Block::default().title(titled("CPU Chart", &[&view_name, &hints])).borders(Borders::ALL))
+        // Too many cores for a legend hide it.
+        .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)))
         .x_axis(Axis::default()
-        .title(Span::styled("X Axis", Style::default().fg(Color::Red)))
-        .style(Style::default().fg(Color::White))
-        .bounds([0.0, 20.0])
-        .labels(["0.0", "10.0", "15.0"].iter().cloned().map(Span::from).collect()))
+        .title(Span::styled("Seconds ago", theme.axis))
+        .style(theme.text)
+        .bounds([-span, 0.0])
+        .labels([seconds(span), seconds(span / 2.0), "0".to_string()].into_iter().map(Span::from).collect()))
         .y_axis(Axis::default()
-        .title(Span::styled("Y Axis", Style::default().fg(Color::Red)))
-        .style(Style::default().fg(Color::White))
-        .bounds([0.0, 30.0])
-        .labels(["0.0", "10.0", "25.0"].iter().cloned().map(Span::from).collect()));
+        .title(Span::styled("Usage", theme.axis))
+        .style(theme.text)
+        .bounds([0.0, 100.0])
+        .labels(["0%", "50%", "100%"].iter().cloned().map(Span::from).collect()));
 
     let mut cpu_bar_data: Vec<(String, u64)> = vec![];
-    for (i, cpu) in sys.cpus().iter().enumerate() {
+    for (i, cpu) in snapshot.cpus.iter().enumerate() {
         let cpu_stat = format!("C{}", i);
-        cpu_bar_data.push((cpu_stat, cpu.cpu_usage() as u64));
+        cpu_bar_data.push((cpu_stat, cpu.usage as u64));
     }
 
     let cpu_bar_data_map: Vec<(&str, u64)> = cpu_bar_data.iter().map(|(s, u)| (s.as_str(), *u)).collect();
 
+    rect.render_widget(cpu_paragraph, chunk_cpu[0]);
+    rect.render_widget(chart, chunk2_cpu[0]);
+    if clocked {
+        // Scaled to the highest clock the cores can reach, so turbo boost
+        // climbs towards the top and throttling drops away from it.
+        let average = app.history.cpu_frequency.chart_points(snapshot.timestamp);
+        let fastest = app.history.cpu_frequency_peak.chart_points(snapshot.timestamp);
+        let top = snapshot.cpus.iter().filter_map(|cpu| cpu.max_frequency)
+            .chain(fastest.iter().map(|(_, mhz)| *mhz as u64))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let datasets = vec![
+            Dataset::default().name("Average").marker(symbols::Marker::Braille).graph_type(GraphType::Line).style(theme.chart).data(&average),
+            Dataset::default().name("Fastest core").marker(symbols::Marker::Braille).graph_type(GraphType::Line).style(theme.gauge).data(&fastest),
+        ];
+        let chart = Chart::new(datasets)
+            .block(Block::default().title("CPU Clock").borders(Borders::ALL))
+            // The chart is short; its two-line legend still fits.
+            .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(3, 4)))
+            .x_axis(Axis::default()
+            .title(Span::styled("Seconds ago", theme.axis))
+            .style(theme.text)
+            .bounds([-span, 0.0])
+            .labels([seconds(span), seconds(span / 2.0), "0".to_string()].into_iter().map(Span::from).collect()))
+            .y_axis(Axis::default()
+            .title(Span::styled("Clock", theme.axis))
+            .style(theme.text)
+            .bounds([0.0, top as f64])
+            .labels(["0".to_string(), format_frequency(top / 2), format_frequency(top)].into_iter().map(Span::from).collect()));
+        rect.render_widget(chart, middle[0]);
+    }
+    if loaded {
+        // Per core, so 1.0 means every core is busy whatever the count.
+        let series = [
+            ("1 min", &app.history.load_one, theme.chart),
+            ("5 min", &app.history.load_five, theme.gauge),
+            ("15 min", &app.history.load_fifteen, theme.bar),
+            ("Running", &app.history.tasks_running, theme.battery),
+            ("Blocked", &app.history.tasks_blocked, theme.error),
+        ];
+        let points: Vec<Vec<(f64, f64)>> = series.iter().map(|(_, series, _)| series.chart_points(snapshot.timestamp)).collect();
+        let top = points.iter().flatten().map(|(_, load)| *load).fold(1.0, f64::max).ceil();
+        let datasets = series.iter().zip(&points)
+            .filter(|(_, points)| !points.is_empty())
+            .map(|((name, _, style), points)| {
+                Dataset::default()
+                    .name(*name)
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(*style)
+                    .data(points)
+            }).collect();
+        let chart = Chart::new(datasets)
+            .block(Block::default().title("Load per Core").borders(Borders::ALL))
+            .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 1)))
+            .x_axis(Axis::default()
+            .title(Span::styled("Seconds ago", theme.axis))
+            .style(theme.text)
+            .bounds([-span, 0.0])
+            .labels([seconds(span), seconds(span / 2.0), "0".to_string()].into_iter().map(Span::from).collect()))
+            .y_axis(Axis::default()
+            .title(Span::styled("Load", theme.axis))
+            .style(theme.text)
+            .bounds([0.0, top])
+            .labels(["0".to_string(), format!("{:.1}", top / 2.0), format!("{:.1}", top)].into_iter().map(Span::from).collect()));
+        rect.render_widget(chart, middle[middle.len() - 1]);
+    }
+    match graph {
+        CpuGraph::Heatmap => {
+            rect.render_widget(cpu_heatmap(app, bars_area, theme), bars_area);
+            return;
+        }
+        CpuGraph::Breakdown => {
+            display_cpu_times(snapshot, rect, bars_area, app, theme);
+            return;
+        }
+        CpuGraph::Bars => {}
+    }
+
+    // Each bar takes its width plus a gap; the rest are cut off.
+    let fitting = (bars_area.width.saturating_sub(2) as usize + 1) / 4;
+    let cores = if snapshot.cpus.len() > fitting {
+        format!("{} of {} cores", fitting, snapshot.cpus.len())
+    } else {
+        String::new()
+    };
+    let bar_title = titled("CPU Bar Graph", &[&cores, &app.key_hints(CPU_BARS_KEYS)]);
     let barchart = BarChart::default()
-        .block(Block::default().title("CPU Bar Graph").borders(Borders::ALL))
+        .block(Block::default().title(bar_title).borders(Borders::ALL))
         .data(&cpu_bar_data_map)
         .bar_width(3)
         .group_gap(3)
         .bar_gap(1)
-        .value_style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD))
-        .label_style(Style::default().fg(Color::White))
-        .bar_style(Style::default().fg(Color::LightBlue))
+        .value_style(theme.text.add_modifier(Modifier::BOLD))
+        .label_style(theme.text)
+        .bar_style(theme.bar)
         .direction(Direction::Vertical);
 
-    rect.render_widget(cpu_paragraph, chunk_cpu[0]);
-    rect.render_widget(chart, chunk2_cpu[0]);
-    rect.render_widget(barchart, chunk2_cpu[1]);
+    rect.render_widget(barchart, bars_area);
 }
 
-pub fn display_memory(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdout>>, chunks: Rect){
-    let mem_total = sys.total_memory();
-    let mem_used = sys.used_memory();
-    let mem_usage = format!("Memory: {:.2} / {:.2} GB", mem_used as f64 / 1_073_741_824.0 , mem_total as f64 / 1_073_741_824.0); // 1 GB = 1_073_741_824.0 bytes
-    let available = format!("Available memory: {:.2} GB", mem_total as f64 / 1_073_741_824.0 - mem_used as f64 / 1_073_741_824.0);
+// "Name (first; second)", leaving out empty parts.
+fn titled(name: &str, parts: &[&str]) -> String {
+    let parts: Vec<&str> = parts.iter().copied().filter(|part| !part.is_empty()).collect();
+    if parts.is_empty() {
+        name.to_string()
+    } else {
+        format!("{} ({})", name, parts.join("; "))
+    }
+}
+
+// One row per core and one column per history sample, newest on the right.
+// When there are more cores than rows, each row shows the busiest core of a
+// group so a single pinned core still stands out.
+fn cpu_heatmap<'a>(app: &'a App, area: Rect, theme: &Theme) -> Paragraph<'a> {
+    let cores = &app.history.cpu_cores;
+    // Borders and the legend row.
+    let rows = (area.height.saturating_sub(3) as usize).max(1);
+    let per_row = cores.len().div_ceil(rows).max(1);
+    let groups: Vec<&[history::Series]> = cores.chunks(per_row).collect();
+    let label = |first: usize, count: usize| if count == 1 { format!("C{}", first) } else { format!("C{}-{}", first, first + count - 1) };
+    let label_width = groups.iter().enumerate().map(|(i, group)| label(i * per_row, group.len()).len()).max().unwrap_or(0) + 1;
+    let columns = (area.width.saturating_sub(2) as usize).saturating_sub(label_width);
+
+    let heat = |usage: f64| {
+        let style = if usage >= 80.0 { theme.error } else if usage >= 60.0 { theme.chart } else { theme.battery };
+        let cell = match usage {
+            usage if usage < 20.0 => " ",
+            usage if usage < 40.0 => "░",
+            usage if usage < 60.0 => "▒",
+            usage if usage < 80.0 => "▓",
+            _ => "█",
+        };
+        Span::styled(cell, style)
+    };
+
+    let mut lines = vec![];
+    for (i, group) in groups.iter().enumerate() {
+        let mut spans = vec![Span::styled(format!("{:<width$}", label(i * per_row, group.len()), width = label_width), theme.text)];
+        // The busiest core of the group at each of the last `columns`
+        // samples, lined up on the newest one.
+        let mut busiest = vec![None; columns];
+        for series in group.iter() {
+            let skipped = series.len().saturating_sub(columns);
+            let offset = columns - (series.len() - skipped);
+            for (column, (_, usage)) in series.iter().skip(skipped).enumerate() {
+                let cell = &mut busiest[offset + column];
+                *cell = Some(cell.map_or(*usage, |busiest: f64| busiest.max(*usage)));
+            }
+        }
+        spans.extend(busiest.into_iter().map(|usage| match usage {
+            Some(usage) => heat(usage),
+            None => Span::raw(" "),
+        }));
+        lines.push(Line::from(spans));
+    }
+    lines.push(Line::from(vec![
+        Span::styled(format!("{:<width$}", "", width = label_width), theme.text),
+        heat(0.0), Span::raw(" <20% "),
+        heat(20.0), Span::raw(" 20% "),
+        heat(40.0), Span::raw(" 40% "),
+        heat(60.0), Span::raw(" 60% "),
+        heat(80.0), Span::raw(" 80%+"),
+    ]));
+
+    let rows = if per_row > 1 { format!("busiest of {} cores per row", per_row) } else { String::new() };
+    let title = titled("CPU Heatmap", &[&rows, &app.key_hints(CPU_HEATMAP_KEYS)]);
+    Paragraph::new(lines)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .style(theme.text)
+}
+
+// Glyph and style of each `CpuTimes::NAMES` entry; the glyphs tell them
+// apart in themes with few colours.
+fn cpu_time_parts(theme: &Theme) -> [(&'static str, Style); 6] {
+    [("█", theme.bar), ("▓", theme.gauge), ("▒", theme.chart), ("░", theme.battery), ("▚", theme.error), ("▞", theme.text)]
+}
+
+// Stacked bars of where each core's time goes, next to a stacked chart of
+// all cores over time. Big machines get one bar per group of cores,
+// averaged.
+fn display_cpu_times<B: Backend>(snapshot: &SystemSnapshot, rect: &mut Frame<'_, B>, area: Rect, app: &App, theme: &Theme) {
+    if snapshot.cpu_times().is_none() && app.history.cpu_times[0].is_empty() {
+        let paragraph = Paragraph::new("Not available: the breakdown is read from /proc/stat on Linux, from the second sample on.")
+            .block(Block::default().title(titled("CPU Time", &[&app.key_hints(CPU_TIME_KEYS)])).borders(Borders::ALL))
+            .style(theme.text)
+            .wrap(Wrap { trim: true });
+        rect.render_widget(paragraph, area);
+        return;
+    }
+    let halves = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(area);
+    let parts = cpu_time_parts(theme);
+
+    // The legend wraps onto as many rows as it needs.
+    let inner = halves[0].width.saturating_sub(2) as usize;
+    let mut legend: Vec<Vec<Span>> = vec![vec![]];
+    let mut used = 0;
+    for (name, (glyph, style)) in CpuTimes::NAMES.iter().zip(parts) {
+        let item = name.len() + 3;
+        if used > 0 && used + item > inner {
+            legend.push(vec![]);
+            used = 0;
+        }
+        let row = legend.last_mut().unwrap();
+        row.push(Span::styled(glyph, style));
+        row.push(Span::raw(format!(" {} ", name)));
+        used += item;
+    }
+
+    // Borders, the "All" row and the legend rows.
+    let rows = (halves[0].height.saturating_sub(3 + legend.len() as u16) as usize).max(1);
+    let per_row = snapshot.cpus.len().div_ceil(rows).max(1);
+    let label = |first: usize, count: usize| if count == 1 { format!("C{}", first) } else { format!("C{}-{}", first, first + count - 1) };
+    let mut bars = vec![("All".to_string(), snapshot.cpu_times())];
+    for (i, group) in snapshot.cpus.chunks(per_row).enumerate() {
+        bars.push((label(i * per_row, group.len()), CpuTimes::average(group.iter().filter_map(|cpu| cpu.times.as_ref()))));
+    }
+    let label_width = bars.iter().map(|(label, _)| label.len()).max().unwrap_or(0) + 1;
+    let width = inner.saturating_sub(label_width);
+    let mut lines: Vec<Line> = bars.into_iter().map(|(label, times)| {
+        let mut spans = vec![Span::styled(format!("{:<width$}", label, width = label_width), theme.text)];
+        // Each part ends where the running total does, so rounding never
+        // adds up to more than the bar.
+        let (mut total, mut drawn) = (0.0, 0);
+        for (value, (glyph, style)) in times.map(|times| times.values()).unwrap_or_default().into_iter().zip(parts) {
+            total += value as f64;
+            let end = ((total / 100.0 * width as f64).round() as usize).min(width);
+            spans.push(Span::styled(glyph.repeat(end.saturating_sub(drawn)), style));
+            drawn = drawn.max(end);
+        }
+        Line::from(spans)
+    }).collect();
+    lines.extend(legend.into_iter().map(Line::from));
+    let rows = if per_row > 1 { format!("average of {} cores per row", per_row) } else { String::new() };
+    let title = titled("CPU Time", &[&rows, &app.key_hints(CPU_TIME_KEYS)]);
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .style(theme.text);
+    rect.render_widget(paragraph, halves[0]);
+
+    // One column per sample, newest on the right, filled from the bottom;
+    // each cell shows the part its middle falls in.
+    let rows = (halves[1].height.saturating_sub(2) as usize).max(1);
+    let label_width = 5;
+    let columns = (halves[1].width.saturating_sub(2) as usize).saturating_sub(label_width);
+    let history = &app.history.cpu_times;
+    let samples = history[0].len().min(columns);
+    let values: Vec<Vec<f64>> = history.iter()
+        .map(|series| series.iter().skip(series.len().saturating_sub(samples)).map(|(_, value)| *value).collect())
+        .collect();
+    let mut lines = vec![];
+    for row in 0..rows {
+        let label = match row {
+            0 => "100%",
+            row if row == rows - 1 => "0%",
+            _ => "",
+        };
+        let mut spans = vec![Span::styled(format!("{:<width$}", label, width = label_width), theme.text)];
+        spans.push(Span::raw(" ".repeat(columns - samples)));
+        let level = (rows - row) as f64 - 0.5;
+        for sample in 0..samples {
+            let mut total = 0.0;
+            let part = values.iter().zip(parts).find(|(values, _)| {
+                total += values.get(sample).copied().unwrap_or(0.0) / 100.0 * rows as f64;
+                total > level
+            });
+            spans.push(match part {
+                Some((_, (glyph, style))) => Span::styled(glyph, style),
+                None => Span::raw(" "),
+            });
+        }
+        lines.push(Line::from(spans));
+    }
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().title("CPU Time History (all cores)").borders(Borders::ALL))
+        .style(theme.text);
+    rect.render_widget(paragraph, halves[1]);
+}
+
+pub fn display_memory<B: Backend>(snapshot: &SystemSnapshot, rect: &mut Frame<'_, B>, chunks: Rect, app: &App, theme: &Theme){
+    if let Some(err) = snapshot.error(Subsystem::Memory) {
+        display_unavailable(rect, chunks, "Memory", err, theme);
+        return;
+    }
+    let mem_total = snapshot.memory.total;
+    let mem_used = snapshot.memory.used;
+    let mem_usage = format!("Memory: {} / {}", app.units.memory(mem_used), app.units.memory(mem_total));
+    let available = format!("Available memory: {}", app.units.memory(snapshot.memory.available()));
     let mem_paragraph = Paragraph::new(vec![
         Line::from(vec![
             Span::raw(mem_usage),
@@ -174,7 +639,7 @@ pub fn display_memory(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdout
         ]),
     ])
     .block(Block::default().borders(Borders::ALL).title("Memory"))
-    .style(Style::default().fg(Color::White))
+    .style(theme.text)
     .alignment(ratatui::layout::Alignment::Left);
 
     let chunk_mem = Layout::default()
@@ -201,30 +666,31 @@ pub fn display_memory(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdout
 
     let mem_gauge = Gauge::default()
         .block(Block::default().title("Memory Gauge").borders(Borders::ALL))
-        .gauge_style(Style::default().fg(Color::Magenta))
+        .gauge_style(theme.gauge)
         .use_unicode(true)
-        .ratio(((mem_used as f64 / 1_073_741_824.0) / (mem_total as f64 / 1_073_741_824.0)).into());
+        .ratio(snapshot.memory.used_ratio());
     
     rect.render_widget(mem_gauge, chunk_mem2[1]);
     rect.render_widget(mem_paragraph, chunk_mem[0]);
 }
 
-pub fn display_network(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdout>>, chunks: Rect, app: &mut App) {
+pub fn display_network<B: Backend>(snapshot: &SystemSnapshot, rect: &mut Frame<'_, B>, chunks: Rect, app: &mut App, theme: &Theme) {
+    if let Some(err) = snapshot.error(Subsystem::Networks) {
+        display_unavailable(rect, chunks, "Network", err, theme);
+        return;
+    }
     let mut network_usage = vec![];
-    for (i, network) in sys.networks(){
-        let transmitted_kb = (network.transmitted() as f64 * 8.0) / 1000.0;
-        let received_kb = (network.received() as f64 * 8.0) / 1000.0;
-        let network_stat1 = format!("Network [{}]", i);
+    for network in &snapshot.networks {
+        let network_stat1 = format!("Network [{}]", network.interface);
         network_usage.push(Line::from(vec![
             Span::raw(network_stat1),
         ]));
-        let network_stat2 = format!("Received: {:.2} KB Transmitted: {:.2} KB", received_kb, transmitted_kb);
+        let network_stat2 = format!("Received: {} Transmitted: {}", app.units.network(network.received), app.units.network(network.transmitted));
         network_usage.push(Line::from(vec![
             Span::raw(network_stat2),
             ]));
-        let space = format!(" ");
         network_usage.push(Line::from(vec![
-            Span::raw(space),
+            Span::raw(" "),
         ]));
     }
     
@@ -239,9 +705,9 @@ pub fn display_network(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdou
         .split(chunks);
     
     let mut network_bar_data: Vec<(String, u64)> = vec![];
-    for (i, network) in sys.networks(){
-        let network_stat = format!("{}", i);
-        network_bar_data.push((network_stat, network.received() as u64));
+    for network in &snapshot.networks {
+        let network_stat = network.interface.clone();
+        network_bar_data.push((network_stat, network.received));
     }
     
     let network_bar_data_map: Vec<(&str, u64)> = network_bar_data.iter().map(|(s, u)| (s.as_str(), *u)).collect();
@@ -252,15 +718,15 @@ pub fn display_network(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdou
         .bar_width(3)
         .group_gap(3)
         .bar_gap(1)
-        .value_style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD))
-        .label_style(Style::default().fg(Color::White))
-        .bar_style(Style::default().fg(Color::LightBlue))
+        .value_style(theme.text.add_modifier(Modifier::BOLD))
+        .label_style(theme.text)
+        .bar_style(theme.bar)
         .direction(Direction::Vertical);
 
     app.vertical_scroll_state = app.vertical_scroll_state.content_length(network_usage.len() as u16);
     let network_paragraph = Paragraph::new(network_usage.clone())
         .block(Block::default().borders(Borders::ALL).title("Network").borders(Borders::ALL))
-        .style(Style::default().fg(Color::White))
+        .style(theme.text)
         .alignment(ratatui::layout::Alignment::Left)
         .scroll((app.vertical_scroll as u16 , 0));
     
@@ -281,11 +747,15 @@ pub fn display_network(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdou
     rect.render_widget(network_barchart, network_chunk[1]);
 }
 
-pub fn display_process(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdout>>, chunks: Rect, app: &mut App) {
+pub fn display_process<B: Backend>(snapshot: &SystemSnapshot, rect: &mut Frame<'_, B>, chunks: Rect, app: &mut App, theme: &Theme) {
+    if let Some(err) = snapshot.error(Subsystem::Processes) {
+        display_unavailable(rect, chunks, "Process", err, theme);
+        return;
+    }
     let mut process_usage = vec![];
-    for (pid, process) in sys.processes() {
-        let formatted_pid = format!("Process ID: {:7}", pid);
-        let process_stat = format!("[{:17}] {:40} {:.2} MB", formatted_pid, process.name(), process.memory() as f64 / 1_048_576.0);
+    for process in &snapshot.processes {
+        let formatted_pid = format!("Process ID: {:7}", process.pid);
+        let process_stat = format!("[{:17}] {:40} {}", formatted_pid, process.name, app.units.memory(process.memory));
         process_usage.push(Line::from(vec![
             Span::raw(process_stat),
         ]));
@@ -294,7 +764,7 @@ pub fn display_process(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdou
 
     let process_paragraph = Paragraph::new(process_usage.clone())
         .block(Block::default().borders(Borders::ALL).title("Process"))
-        .style(Style::default().fg(Color::White))
+        .style(theme.text)
         .alignment(ratatui::layout::Alignment::Left)
         .scroll((app.vertical_scroll as u16 , 0));
 
@@ -313,13 +783,20 @@ pub fn display_process(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdou
     );
 }
 
-pub fn display_disk(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdout>>, chunks: Rect) {
-    let disk = sys.disks();
-    let disk_stat = format!("Name: {:?}", disk[0].name());
-    let disk_type = format!("Type: {:?}", disk[0].kind());
-    let disk_total = format!("Total space: {:.2} GB", disk[0].total_space() as f64 / 1_073_741_824.0);
-    let disk_used = format!("Used space: {:.2} GB", disk[0].total_space()as f64 / 1_073_741_824.0 - disk[0].available_space() as f64 / 1_073_741_824.0);
-    let disk_free = format!("Free space: {:.2} GB", disk[0].available_space() as f64 / 1_073_741_824.0);
+pub fn display_disk<B: Backend>(snapshot: &SystemSnapshot, rect: &mut Frame<'_, B>, chunks: Rect, app: &App, theme: &Theme) {
+    if let Some(err) = snapshot.error(Subsystem::Disks) {
+        display_unavailable(rect, chunks, "Disk", err, theme);
+        return;
+    }
+    let Some(disk) = snapshot.disks.first() else {
+        display_unavailable(rect, chunks, "Disk", &Error::NotFound(Subsystem::Disks), theme);
+        return;
+    };
+    let disk_stat = format!("Name: {:?}", disk.name);
+    let disk_type = format!("Type: {}", disk.kind);
+    let disk_total = format!("Total space: {}", app.units.memory(disk.total_space));
+    let disk_used = format!("Used space: {}", app.units.memory(disk.used_space()));
+    let disk_free = format!("Free space: {}", app.units.memory(disk.available_space));
 
     let disk_chunk = Layout::default()
         .direction(Direction::Horizontal)
@@ -344,9 +821,9 @@ pub fn display_disk(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdout>>
 
     let disk_gauge = Gauge::default()
         .block(Block::default().title("Disk Gauge").borders(Borders::ALL))
-        .gauge_style(Style::default().fg(Color::Magenta))
+        .gauge_style(theme.gauge)
         .use_unicode(true)
-        .ratio(((disk[0].total_space() as f64 / 1_073_741_824.0 - disk[0].available_space() as f64 / 1_073_741_824.0) / (disk[0].total_space() as f64 / 1_073_741_824.0)).into());
+        .ratio(disk.used_ratio());
     
     let disk_paragraph = Paragraph::new(vec![
         Line::from(vec![
@@ -366,17 +843,21 @@ pub fn display_disk(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdout>>
         ]),
     ])
     .block(Block::default().borders(Borders::ALL).title("Disk"))
-    .style(Style::default().fg(Color::White))
+    .style(theme.text)
     .alignment(ratatui::layout::Alignment::Left);
 
     rect.render_widget(disk_paragraph, disk_chunk[0]);
     rect.render_widget(disk_gauge, disk_chunk2[1]);
 }
 
-pub fn display_temperature(sys: &System, rect: &mut Frame<'_, CrosstermBackend<Stdout>>, chunks: Rect, app: &mut App) {
+pub fn display_temperature<B: Backend>(snapshot: &SystemSnapshot, rect: &mut Frame<'_, B>, chunks: Rect, app: &mut App, theme: &Theme) {
+    if let Some(err) = snapshot.error(Subsystem::Components) {
+        display_unavailable(rect, chunks, "Temperature", err, theme);
+        return;
+    }
     let mut temp_usage = vec![];
-    for components in sys.components(){
-        let temp_stat = format!("[{:17}], {:.1}°C", components.label(), components.temperature());
+    for components in &snapshot.components {
+        let temp_stat = format!("[{:17}], {}", components.label, app.units.temperature(components.temperature));
         temp_usage.push(Line::from(vec![
             Span::raw(temp_stat)
         ]))
@@ -393,9 +874,9 @@ pub fn display_temperature(sys: &System, rect: &mut Frame<'_, CrosstermBackend<S
         .split(chunks);
     
     let mut temp_bar_data: Vec<(String, u64)> = vec![];
-    for components in sys.components(){
-        let temp_stat = format!("{:10}", components.label());
-        temp_bar_data.push((temp_stat, components.temperature() as u64));
+    for components in &snapshot.components {
+        let temp_stat = format!("{:10}", components.label);
+        temp_bar_data.push((temp_stat, app.units.temperature_value(components.temperature) as u64));
     }
 
     let temp_bar_data_map: Vec<(&str, u64)> = temp_bar_data.iter().map(|(s, u)| (s.as_str(), *u)).collect();
@@ -406,16 +887,16 @@ pub fn display_temperature(sys: &System, rect: &mut Frame<'_, CrosstermBackend<S
         .bar_width(3)
         .group_gap(3)
         .bar_gap(1)
-        .value_style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD))
-        .label_style(Style::default().fg(Color::White))
-        .bar_style(Style::default().fg(Color::LightBlue))
+        .value_style(theme.text.add_modifier(Modifier::BOLD))
+        .label_style(theme.text)
+        .bar_style(theme.bar)
         .direction(Direction::Vertical);
 
     app.vertical_scroll_state = app.vertical_scroll_state.content_length(temp_usage.len() as u16);
 
     let temp_paragraph = Paragraph::new(temp_usage)
         .block(Block::default().borders(Borders::ALL).title("Temperature"))
-        .style(Style::default().fg(Color::White))
+        .style(theme.text)
         .alignment(ratatui::layout::Alignment::Left)
         .scroll((app.vertical_scroll as u16 , 0));
     
@@ -436,13 +917,15 @@ pub fn display_temperature(sys: &System, rect: &mut Frame<'_, CrosstermBackend<S
 
 }
 
-pub fn display_battery(rect: &mut Frame<'_, CrosstermBackend<Stdout>>, chunks: Rect) {
-    let manager = battery::Manager::new().unwrap();
+pub fn display_battery<B: Backend>(snapshot: &SystemSnapshot, rect: &mut Frame<'_, B>, chunks: Rect, _app: &App, theme: &Theme) {
+    if let Some(err) = snapshot.error(Subsystem::Batteries) {
+        display_unavailable(rect, chunks, "Battery", err, theme);
+        return;
+    }
     let mut battery_usage = vec![];
 
-    for (idx, battery) in manager.batteries().unwrap().enumerate() {
-        let battery = battery.unwrap();
-        let battery_stat = format!("Battery {}: {:?}, Current Battery {:.2}%", idx + 1, battery.state(), battery.state_of_charge().value * 100.0);
+    for (idx, battery) in snapshot.batteries.iter().enumerate() {
+        let battery_stat = format!("Battery {}: {:?}, Current Battery {:.2}%", idx + 1, battery.state, battery.charge * 100.0);
         battery_usage.push(Line::from(vec![
             Span::raw(battery_stat)
         ]));
@@ -471,23 +954,358 @@ pub fn display_battery(rect: &mut Frame<'_, CrosstermBackend<Stdout>>, chunks: R
 
     let battery_paragraph = Paragraph::new(battery_usage)
         .block(Block::default().borders(Borders::ALL).title("Battery"))
-        .style(Style::default().fg(Color::White))
+        .style(theme.text)
         .alignment(ratatui::layout::Alignment::Left);
 
-    let battery = battery::Manager::new().unwrap();
-    let mut gauge_battery = battery.batteries().unwrap();
-    let used_battery = if let Some(battery) = gauge_battery.next() {
-        battery.expect("Reason").state_of_charge().value * 100.0
+    let used_battery = if let Some(battery) = snapshot.batteries.first() {
+        battery.charge * 100.0
     } else {
         0.0
     };
 
     let gauge = Gauge::default()
         .block(Block::default().title("Battery Gauge").borders(Borders::ALL))
-        .gauge_style(Style::default().fg(Color::Green))
+        .gauge_style(theme.battery)
         .use_unicode(true)
-        .ratio((used_battery / 100.0).into());
+        .ratio((used_battery / 100.0) as f64);
 
     rect.render_widget(battery_paragraph, chunk_battery[0]);
     rect.render_widget(gauge, chunk_battery2[1]);
 }
+
+pub fn display_alerts<B: Backend>(rect: &mut Frame<'_, B>, chunks: Rect, app: &mut App, theme: &Theme) {
+    let mut rules = vec![];
+    for (rule, state) in app.alerts.rules() {
+        let (status, style) = match state {
+            AlertState::Ok => ("ok".to_string(), theme.text),
+            AlertState::Pending(since) => (format!("pending since {}", format_timestamp(*since)), theme.highlight),
+            AlertState::Firing(since) => (format!("firing since {}", format_timestamp(*since)), theme.error.add_modifier(Modifier::BOLD)),
+        };
+        rules.push(Line::from(vec![
+            Span::styled(format!("{:<20} ", rule.name), style),
+            Span::raw(format!("{:<40} ", rule.to_string())),
+            Span::styled(status, style),
+        ]));
+    }
+    if rules.is_empty() {
+        rules.push(Line::from("No alerts are configured; add [[alerts]] rules to the configuration file."));
+    }
+
+    // Newest first.
+    let mut events = vec![];
+    for event in app.alerts.events().rev() {
+        let ended = match event.ended {
+            Some(ended) => format_timestamp(ended),
+            None => "still firing".to_string(),
+        };
+        let style = if event.ended.is_none() { theme.error } else { theme.text };
+        events.push(Line::from(vec![
+            Span::styled(format!("{:<20} ", event.name), style),
+            Span::raw(format!("{} until {}", format_timestamp(event.started), ended)),
+        ]));
+    }
+    if events.is_empty() {
+        events.push(Line::from("No alert has fired yet."));
+    }
+
+    let chunk_alerts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(rules.len() as u16 + 2),
+                Constraint::Min(3),
+            ].as_ref()
+        )
+        .split(chunks);
+
+    app.vertical_scroll_state = app.vertical_scroll_state.content_length(events.len() as u16);
+
+    let rules_paragraph = Paragraph::new(rules)
+        .block(Block::default().borders(Borders::ALL).title("Alerts"))
+        .style(theme.text)
+        .alignment(Alignment::Left);
+
+    let events_paragraph = Paragraph::new(events)
+        .block(Block::default().borders(Borders::ALL).title("Alert History"))
+        .style(theme.text)
+        .alignment(Alignment::Left)
+        .scroll((app.vertical_scroll as u16, 0));
+
+    rect.render_widget(rules_paragraph, chunk_alerts[0]);
+    rect.render_widget(events_paragraph, chunk_alerts[1]);
+}
+
+pub fn display_command<B: Backend>(rect: &mut Frame<'_, B>, chunks: Rect, config: &CommandConfig, state: &CommandState, theme: &Theme) {
+    let unit = config.unit.as_deref().unwrap_or("");
+    let format_value = |value: f64| {
+        let number = if value.fract() == 0.0 { format!("{:.0}", value) } else { format!("{:.2}", value) };
+        match unit {
+            "" | "%" => format!("{}{}", number, unit),
+            unit => format!("{} {}", number, unit),
+        }
+    };
+
+    let mut command_text = vec![
+        Line::from(vec![
+            Span::styled("Command: ", theme.title),
+            Span::raw(config.command.clone()),
+        ]),
+        Line::from(format!("Every {}, timeout {}", format_duration(config.interval), format_duration(config.timeout))),
+        Line::from(match state.updated {
+            Some(updated) => format!("Updated: {}", format_timestamp(updated)),
+            None if state.error.is_none() => "Waiting for the first run".to_string(),
+            None => "No successful run yet".to_string(),
+        }),
+        Line::from(""),
+    ];
+    for (key, value) in &state.values {
+        command_text.push(Line::from(format!("{}: {}", key, format_value(*value))));
+    }
+    if let Some(err) = &state.error {
+        command_text.push(Line::from(""));
+        command_text.push(Line::styled("Last run failed:", theme.error.add_modifier(Modifier::BOLD)));
+        command_text.push(Line::styled(err.to_string(), theme.error));
+    }
+    let command_paragraph = Paragraph::new(command_text)
+        .block(Block::default().borders(Borders::ALL).title(config.name.clone()))
+        .style(theme.text)
+        .alignment(Alignment::Left)
+        .wrap(Wrap { trim: true });
+
+    // Nothing to draw until the command has printed something.
+    if state.values.is_empty() {
+        rect.render_widget(command_paragraph, chunks);
+        return;
+    }
+
+    let chunk_command = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage(30),
+                Constraint::Percentage(70),
+            ].as_ref()
+        )
+        .split(chunks);
+    rect.render_widget(command_paragraph, chunk_command[0]);
+
+    match config.widget {
+        CommandWidget::Gauge => {
+            let max = config.max.unwrap_or(1.0);
+            let mut constraints = vec![Constraint::Length(3); state.values.len()];
+            constraints.push(Constraint::Min(0));
+            let chunk_gauges = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(constraints)
+                .split(chunk_command[1]);
+            for ((key, value), chunk) in state.values.iter().zip(chunk_gauges.iter()) {
+                let gauge = Gauge::default()
+                    .block(Block::default().title(key.clone()).borders(Borders::ALL))
+                    .gauge_style(theme.gauge)
+                    .use_unicode(true)
+                    .label(format!("{} / {}", format_value(*value), format_value(max)))
+                    .ratio((value / max).clamp(0.0, 1.0));
+                rect.render_widget(gauge, *chunk);
+            }
+        }
+        CommandWidget::Bar => {
+            // Bars only hold whole numbers; the exact values are on the left.
+            let bar_data: Vec<(&str, u64)> = state.values.iter().map(|(key, value)| (key.as_str(), value.max(0.0).round() as u64)).collect();
+            let bar_width = state.values.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0).clamp(3, 10) as u16;
+            let mut barchart = BarChart::default()
+                .block(Block::default().title(format!("{} Bar Graph", config.name)).borders(Borders::ALL))
+                .data(&bar_data)
+                .bar_width(bar_width)
+                .group_gap(3)
+                .bar_gap(1)
+                .value_style(theme.text.add_modifier(Modifier::BOLD))
+                .label_style(theme.text)
+                .bar_style(theme.bar)
+                .direction(Direction::Vertical);
+            if let Some(max) = config.max {
+                barchart = barchart.max(max.round() as u64);
+            }
+            rect.render_widget(barchart, chunk_command[1]);
+        }
+        CommandWidget::Line => {
+            // The last 60 runs, ending now on the right.
+            let window = (config.interval * 60).as_secs_f64();
+            let now = SystemTime::now();
+            let points: Vec<(String, Vec<(f64, f64)>)> = state.values.iter()
+                .filter_map(|(key, _)| state.history.get(key).map(|series| (key.clone(), series.chart_points(now))))
+                .collect();
+            let values = points.iter().flat_map(|(_, points)| points.iter().map(|(_, value)| *value));
+            let lowest = values.clone().fold(0.0, f64::min);
+            let highest = config.max.unwrap_or_else(|| values.fold(0.0, f64::max) * 1.1).max(lowest + 1.0);
+            let styles = [theme.chart, theme.gauge, theme.bar, theme.battery, theme.highlight];
+            let datasets = points.iter().enumerate().map(|(i, (key, points))| {
+                Dataset::default()
+                    .name(key.clone())
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(styles[i % styles.len()])
+                    .data(points)
+            }).collect();
+            let chart = Chart::new(datasets)
+                .block(Block::default().title(format!("{} Chart", config.name)).borders(Borders::ALL))
+                .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)))
+                .x_axis(Axis::default()
+                .title(Span::styled("Seconds ago", theme.axis))
+                .style(theme.text)
+                .bounds([-window, 0.0])
+                .labels([format!("{:.0}", window), format!("{:.0}", window / 2.0), "0".to_string()].into_iter().map(Span::from).collect()))
+                .y_axis(Axis::default()
+                .title(Span::styled(unit.to_string(), theme.axis))
+                .style(theme.text)
+                .bounds([lowest, highest])
+                .labels([lowest, (lowest + highest) / 2.0, highest].into_iter().map(|value| Span::from(format_value(value))).collect()));
+            rect.render_widget(chart, chunk_command[1]);
+        }
+    }
+}
+
+pub fn display_fleet<B: Backend>(rect: &mut Frame<'_, B>, chunks: Rect, app: &mut App, theme: &Theme) {
+    if app.fleet.is_empty() {
+        let fleet_paragraph = Paragraph::new("No hosts are configured; list agents under [fleet] hosts in the configuration file.")
+            .block(Block::default().borders(Borders::ALL).title("Fleet"))
+            .style(theme.text)
+            .wrap(Wrap { trim: true });
+        rect.render_widget(fleet_paragraph, chunks);
+        return;
+    }
+
+    let (sort, descending) = app.fleet.sort();
+    let columns = [
+        ("Host", FleetSort::Name),
+        ("Address", FleetSort::Name),
+        ("Status", FleetSort::Status),
+        ("CPU", FleetSort::Cpu),
+        ("Memory", FleetSort::Memory),
+        ("Disk", FleetSort::Disk),
+        ("Temp", FleetSort::Temperature),
+    ];
+    let header = Row::new(columns.iter().enumerate().map(|(index, (title, column))| {
+        if *column == sort && index != 1 {
+            Cell::from(format!("{} {}", title, if descending { "▼" } else { "▲" })).style(theme.highlight.add_modifier(Modifier::BOLD))
+        } else {
+            Cell::from(*title).style(theme.title.add_modifier(Modifier::BOLD))
+        }
+    }));
+
+    let rows = app.fleet.rows();
+    let percent = |value: Option<f64>| value.map(|value| format!("{:.1}%", value)).unwrap_or_else(|| "-".to_string());
+    let table_rows: Vec<Row> = rows.iter().map(|row| {
+        let (status, style) = match &row.status {
+            HostStatus::Connecting => ("connecting".to_string(), theme.text),
+            HostStatus::Up => ("up".to_string(), theme.text),
+            HostStatus::Stale(age) => (format!("stale ({} s)", age.as_secs()), theme.highlight),
+            HostStatus::Unreachable(_) => ("unreachable".to_string(), theme.error),
+        };
+        Row::new(vec![
+            Cell::from(row.name.clone()),
+            Cell::from(row.endpoint.to_string()),
+            Cell::from(status).style(style),
+            Cell::from(percent(row.cpu.map(f64::from))),
+            Cell::from(percent(row.memory.map(|ratio| ratio * 100.0))),
+            Cell::from(percent(row.disk.map(|ratio| ratio * 100.0))),
+            Cell::from(row.temperature.map(|temperature| app.units.temperature(temperature)).unwrap_or_else(|| "-".to_string())),
+        ])
+    }).collect();
+
+    let chunk_fleet = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Min(3),
+                Constraint::Length(3),
+            ].as_ref()
+        )
+        .split(chunks);
+
+    let widths = [
+        Constraint::Percentage(20),
+        Constraint::Percentage(20),
+        Constraint::Percentage(16),
+        Constraint::Percentage(11),
+        Constraint::Percentage(11),
+        Constraint::Percentage(11),
+        Constraint::Percentage(11),
+    ];
+    let table = Table::new(table_rows)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(format!("Fleet (sorted by {})", sort.name())))
+        .style(theme.text)
+        .widths(&widths)
+        .highlight_style(theme.highlight.add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    let mut state = TableState::default();
+    state.select(Some(app.fleet.selected()));
+
+    let details = match rows.get(app.fleet.selected()) {
+        Some(row) => match &row.status {
+            HostStatus::Unreachable(err) => Line::from(Span::styled(err.clone(), theme.error)),
+            _ => Line::from(titled(&row.name, &[&app.key_hints(FLEET_KEYS)])),
+        },
+        None => Line::from(""),
+    };
+    let details_paragraph = Paragraph::new(details)
+        .block(Block::default().borders(Borders::ALL).title("Host"))
+        .style(theme.text);
+
+    rect.render_stateful_widget(table, chunk_fleet[0], &mut state);
+    rect.render_widget(details_paragraph, chunk_fleet[1]);
+}
+
+// One line naming every firing alert, shown above the status bar.
+pub fn display_alert_banner<B: Backend>(rect: &mut Frame<'_, B>, chunks: Rect, alerts: &Alerts, theme: &Theme) {
+    let mut banner = vec![];
+    for (i, (rule, since)) in alerts.firing().enumerate() {
+        if i > 0 {
+            banner.push(Span::raw(" | "));
+        }
+        banner.push(Span::styled(rule.name.clone(), theme.error.add_modifier(Modifier::BOLD)));
+        banner.push(Span::raw(format!(": {} (since {})", rule, format_timestamp(since))));
+    }
+
+    let banner_paragraph = Paragraph::new(Line::from(banner))
+        .block(Block::default().borders(Borders::ALL).title("Alerts").border_style(theme.error))
+        .style(theme.text)
+        .alignment(Alignment::Left);
+    rect.render_widget(banner_paragraph, chunks);
+}
+
+// Shown in place of a panel whose data could not be collected.
+pub fn display_unavailable<B: Backend>(rect: &mut Frame<'_, B>, chunks: Rect, title: &str, err: &Error, theme: &Theme) {
+    let unavailable_text = vec![
+        Line::styled(format!("{} information is not available on this system.", title), theme.title.add_modifier(Modifier::BOLD)),
+        Line::from("\n"),
+        Line::from(vec![
+            Span::raw("Details: "),
+            Span::styled(err.to_string(), theme.error),
+        ]),
+    ];
+
+    let unavailable_paragraph = Paragraph::new(unavailable_text)
+        .block(Block::default().borders(Borders::ALL).title(title.to_string()))
+        .style(theme.text)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    rect.render_widget(unavailable_paragraph, chunks);
+}
+
+pub fn display_status<B: Backend>(rect: &mut Frame<'_, B>, chunks: Rect, items: &[(&str, String)], theme: &Theme) {
+    let mut status = vec![];
+    for (i, (label, value)) in items.iter().enumerate() {
+        if i > 0 {
+            status.push(Span::raw(" | "));
+        }
+        status.push(Span::styled(format!("{}: ", label), theme.highlight));
+        status.push(Span::raw(value.clone()));
+    }
+
+    let status_paragraph = Paragraph::new(Line::from(status))
+        .block(Block::default().borders(Borders::ALL).title("Status"))
+        .style(theme.text)
+        .alignment(Alignment::Left);
+    rect.render_widget(status_paragraph, chunks);
+}
@@ -1,106 +1,223 @@
-use sysinfo::*;
-use systemmor::{display_cpu, display_memory, display_network, display_process, display_disk, display_temperature, display_battery, display_home};
-use std::{io, thread::{self}, time::Duration};
-use ratatui::{
-    backend::CrosstermBackend,
-    widgets::*,
-    layout::{Layout, Constraint, Direction},
-    Terminal,
-    text::{Span, Line},
-    style::*
-};
-use std::time::Instant;
-use std::sync::mpsc;
-use crossterm::{
-    event::{self, KeyCode, DisableMouseCapture, EnableMouseCapture},
-    terminal::{disable_raw_mode, enable_raw_mode},
-    execute,
-};
-use systemmor::App;
+use std::{io, thread::{self}};
+use ratatui::layout::{Layout, Constraint, Direction};
+use std::io::Write;
+use std::time::{Duration, Instant, SystemTime};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use clap::Parser;
+use crossterm::event;
+use systemmor::{display_alert_banner, display_status, Action, Alerts, App, Config, Fleet, History, Keymap, PanelRegistry, Sampler, Subsystem, SystemSnapshot, Themes, Units};
+use systemmor::keymap::{format_sequence, KeyResult};
+use systemmor::interval::{faster, format_duration, slower};
+use systemmor::exporter::{Exporter, SharedSnapshot};
+use systemmor::agent::{Agent, Endpoint, RemoteSource};
+use systemmor::alerts::AlertChange;
+use systemmor::config::RefreshConfig;
+use systemmor::json::{to_json, to_json_line, Document};
+use systemmor::replay::{format_clock, format_timestamp, Recorder, Replay};
+use systemmor::report::{report, report_alerts};
+use systemmor::terminal::TerminalGuard;
+use systemmor::theme::no_color;
+use systemmor::cli::{Cli, Command, Format, Mode};
+
+// Exit status of `--once` and `--count` when an alert fired.
+const ALERT_EXIT_STATUS: i32 = 3;
 
 enum Event<T> {
     Input(T),
-    Tick
-}
-
-#[allow(dead_code)]
-#[derive(Copy, Clone, Debug)]
-enum MenuItem {
-    Home,
-    CPU,
-    Memory,
-    Network,
-    Process,
-    Disk,
-    Temperature,
-    Battery,
+    // SIGINT, SIGTERM or SIGHUP was received.
     Quit,
+    InputError(io::Error),
 }
 
-impl From<MenuItem> for usize {
-    fn from(input: MenuItem) -> usize {
-        match input {
-            MenuItem::Home => 0,
-            MenuItem::CPU => 1,
-            MenuItem::Memory => 2,
-            MenuItem::Network => 3,
-            MenuItem::Process => 4,
-            MenuItem::Disk => 5,
-            MenuItem::Temperature => 6,
-            MenuItem::Battery => 7,
-            MenuItem::Quit => 8,
+fn main() -> Result<(), io::Error> {
+    let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref()).unwrap_or_else(|err| fail(err));
+
+    let mut panels = PanelRegistry::default();
+    panels.register_commands(&config.commands).unwrap_or_else(|err| fail(err));
+    if let Some(names) = &config.panels {
+        panels.configure(names).unwrap_or_else(|err| fail(err));
+    }
+    if let Some(name) = &config.default_panel {
+        if !panels.select_name(name) {
+            fail(format!("default_panel '{}' is not one of the enabled panels", name));
         }
     }
-}
-fn main() -> Result<(), io::Error> {
-    enable_raw_mode().expect("can run in raw mode");
-    
+    if let Some(name) = &cli.panel {
+        if !panels.select_name(name) {
+            let known: Vec<String> = panels.hotkeys().into_iter().map(|(name, _)| name).collect();
+            fail(format!("unknown panel '{}' (available: {})", name, known.join(", ")));
+        }
+    }
+
+    let keymap = Keymap::new(&panels.hotkeys(), &config.keys).unwrap_or_else(|err| fail(err));
+    let mut themes = Themes::new(config.theme.as_deref(), &config.themes, no_color()).unwrap_or_else(|err| fail(err));
+    let mut alerts = Alerts::new(&config.alerts).unwrap_or_else(|err| fail(err));
+
+    let mut interval = cli.interval.unwrap_or(config.refresh.interval);
+    // With --connect, the agent's host name and address for the status bar.
+    let mut remote = None;
+    let mut sampler = match &cli.connect {
+        Some(address) => {
+            let source = address.parse::<Endpoint>()
+                .and_then(|endpoint| RemoteSource::connect(&endpoint).map_err(|err| err.to_string()))
+                .unwrap_or_else(|err| fail(err));
+            remote = Some(format!("{} ({})", source.hello().hostname, address));
+            Sampler::with_source(Box::new(source))
+        }
+        None => Sampler::new(),
+    };
+    configure(&mut sampler, &config.refresh);
+
+    let mode = cli.mode();
+    if let Mode::Print { format, count } = mode {
+        // Without --panel (or on a panel with no data) report everything.
+        let needs = match panels.active().map(|panel| panel.needs()) {
+            Some(needs) if cli.panel.is_some() && !needs.is_empty() => needs.to_vec(),
+            _ => Subsystem::ALL.to_vec(),
+        };
+        if print_samples(&mut sampler, &needs, interval, format, count, &config.units, &mut alerts)? {
+            std::process::exit(ALERT_EXIT_STATUS);
+        }
+        return Ok(());
+    }
+
+    let shared = SharedSnapshot::default();
+    let exporter = cli.listen.as_ref().or(config.exporter.listen.as_ref()).map(|address| {
+        Exporter::start(address, shared.clone(), config.exporter.processes)
+            .unwrap_or_else(|err| fail(format!("cannot serve metrics on {}: {}", address, err)))
+    });
+    let mut recorder = cli.record.as_ref()
+        .map(|path| Recorder::create(path).unwrap_or_else(|err| fail(format!("cannot record to {}: {}", path.display(), err))));
+    let mut replay = cli.replay.as_ref().map(|path| Replay::load(path).unwrap_or_else(|err| fail(err)));
+
+    if let Some(Command::Agent { address }) = &cli.command {
+        let address = address.as_ref().unwrap_or(&config.agent.listen);
+        let agent = address.parse::<Endpoint>()
+            .and_then(|endpoint| Agent::start(&endpoint, shared.clone()).map_err(|err| format!("cannot listen on {}: {}", address, err)))
+            .unwrap_or_else(|err| fail(err));
+        eprintln!("systemmor: agent listening on {}", agent.endpoint());
+        if let Some(exporter) = &exporter {
+            eprintln!("systemmor: serving metrics on http://{}/metrics", exporter.address());
+        }
+        return serve(&mut sampler, interval, &shared, recorder, &mut alerts);
+    }
+
+    if let Mode::Headless = mode {
+        if exporter.is_none() && recorder.is_none() {
+            fail("--headless needs an address to serve metrics on (--listen or [exporter] listen) or a file to --record to");
+        }
+        if let Some(exporter) = &exporter {
+            eprintln!("systemmor: serving metrics on http://{}/metrics", exporter.address());
+        }
+        if let Some(path) = &cli.record {
+            eprintln!("systemmor: recording to {}", path.display());
+        }
+        return serve(&mut sampler, interval, &shared, recorder, &mut alerts);
+    }
+
+    let mut endpoints = vec![];
+    for host in &config.fleet.hosts {
+        endpoints.push(host.parse::<Endpoint>().unwrap_or_else(|err| fail(format!("[fleet] hosts: {}", err))));
+    }
+
+    let mut app = App {
+        history: History::new(config.refresh.history_length, config.refresh.history_resolution),
+        units: config.units,
+        keymap,
+        alerts,
+        fleet: Fleet::start(&endpoints, config.fleet.refresh, config.fleet.stale),
+        ..App::default()
+    };
+    let mut snapshot = match &replay {
+        Some(replay) => replay.snapshot().clone(),
+        None => sampler.sample_all(),
+    };
+    app.history.record(&snapshot);
+    app.alerts.evaluate(&snapshot);
+    publish(&shared, &snapshot);
+    // Set when writing the recording failed; recording stops and the
+    // reason is shown in the status bar.
+    let mut recording_failed = None;
+    record(&mut recorder, &snapshot, &mut recording_failed);
+
+    panels.start();
+
+    // Restores the terminal when dropped, on every return path below and
+    // on panic.
+    let mut terminal = TerminalGuard::enter()?;
+
+    // Key presses only trigger a redraw; sampling runs on its own clock in
+    // the main loop so CPU percentages always cover a full interval.
     let (tx, rx) = mpsc::channel();
-    let tick_rate = Duration::from_millis(250);
+    let input_tx = tx.clone();
     thread::spawn(move || {
-        let mut last_tick = Instant::now();
         loop {
-            let timeout = tick_rate.checked_sub(last_tick.elapsed()).unwrap_or_else(|| Duration::from_secs(0));
-            
-            if event::poll(timeout).expect("poll works") {
-                if let event::Event::Key(key) = event::read().expect("can read event") {
-                    tx.send(Event::Input(key)).expect("can send event");
-                }
-            }
-            
-            if last_tick.elapsed() >= tick_rate {
-                if tx.send(Event::Tick).is_err() {
-                    break;
-                }
-                last_tick = Instant::now();
+            let event = match event::read() {
+                Ok(event::Event::Key(key)) => Event::Input(key),
+                Ok(_) => continue,
+                Err(err) => Event::InputError(err),
+            };
+            let failed = matches!(event, Event::InputError(_));
+            if input_tx.send(event).is_err() || failed {
+                break;
             }
         }
     });
-    
-    execute!(io::stdout(), EnableMouseCapture).expect("can enable mouse capture");
-    let mut app = App::default();
-    let mut sys = System::new_all();
-    let stdout = io::stdout();
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    terminal.clear()?;
-
-    let menu_titles = vec![
-        "Home",
-        "CPU",
-        "Memory",
-        "Network",
-        "Process",
-        "Disk",
-        "Temperature",
-        "Battery",
-        "Quit",];
-
-    let mut active_menu_item = MenuItem::Home;
+    #[cfg(unix)]
+    {
+        use signal_hook::consts::signal::{SIGHUP, SIGINT, SIGTERM};
+        let mut signals = signal_hook::iterator::Signals::new([SIGINT, SIGTERM, SIGHUP])?;
+        thread::spawn(move || {
+            if signals.forever().next().is_some() {
+                let _ = tx.send(Event::Quit);
+            }
+        });
+    }
+
+    let mut next_sample = Instant::now() + interval;
+    let mut paused = false;
+    // While a fleet host is open: this machine's sampler, history and host
+    // label, put back by `Action::Back`.
+    let mut local: Option<(Sampler, History, Option<String>)> = None;
+    // Why the last fleet host could not be opened.
+    let mut fleet_error = None;
 
     loop {
+        if let Some(row) = app.fleet.take_opened() {
+            if replay.is_none() {
+                match RemoteSource::connect(&row.endpoint) {
+                    Ok(source) => {
+                        let mut remote_sampler = Sampler::with_source(Box::new(source));
+                        configure(&mut remote_sampler, &config.refresh);
+                        let remote_history = History::new(config.refresh.history_length, config.refresh.history_resolution);
+                        let label = match app.keymap.keys_for(&Action::Back).first() {
+                            Some(keys) => format!("{} ({}), {} to return", row.name, row.endpoint, format_sequence(keys)),
+                            None => format!("{} ({})", row.name, row.endpoint),
+                        };
+                        let saved = (
+                            std::mem::replace(&mut sampler, remote_sampler),
+                            std::mem::replace(&mut app.history, remote_history),
+                            remote.replace(label),
+                        );
+                        // Opening another host from the fleet panel keeps
+                        // the first saved state, which is this machine's.
+                        local.get_or_insert(saved);
+                        fleet_error = None;
+                        snapshot = sampler.sample_all();
+                        app.history.record(&snapshot);
+                        panels.select_name("cpu");
+                        next_sample = Instant::now() + interval;
+                    }
+                    Err(err) => fleet_error = Some(format!("cannot open {}: {}", row.name, err)),
+                }
+            }
+        }
+
         terminal.draw(|rect| {
             let size = rect.size();
+            // A banner row appears above the status bar while alerts fire.
+            let banner = app.alerts.firing().next().is_some();
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .margin(2)
@@ -108,124 +225,289 @@ fn main() -> Result<(), io::Error> {
                     [
                         Constraint::Length(3),
                         Constraint::Min(2),
+                        Constraint::Length(if banner { 3 } else { 0 }),
                         Constraint::Length(3),
                     ].as_ref()
                 )
                 .split(size);
-            let menu = menu_titles.iter()
-                .map(|t| {
-                    let (first, rest) = t.split_at(1);
-                    Line::from(vec![
-                        Span::styled(first, Style::default().fg(Color::Yellow).add_modifier(Modifier::UNDERLINED)),
-                        Span::styled(rest, Style::default().fg(Color::White)),
-                    ])
-                }).collect();
-            
-            let tabs = Tabs::new(menu)
-                .block(Block::default().borders(Borders::ALL).title("Menu"))
-                .select(active_menu_item.into())
-                .style(Style::default().fg(Color::White))
-                .highlight_style(Style::default().fg(Color::Yellow))
-                .divider(Span::raw("|"));
-
-            match active_menu_item {
-                MenuItem::Home => {
-                    display_home(rect, chunks[1])
-                }
-                MenuItem::CPU => {
-                    sys.refresh_all();
-                    display_cpu(&sys, rect, chunks[1])
-                }
-                MenuItem::Memory => {
-                    sys.refresh_all();
-                    display_memory(&sys, rect, chunks[1])
-                }
-                MenuItem::Network => {
-                    sys.refresh_all();
-                    display_network(&sys, rect, chunks[1], &mut app)
-                }
-                MenuItem::Process => {
-                    sys.refresh_all();
-                    display_process(&sys, rect, chunks[1], &mut app)
-                }
-                MenuItem::Disk => {
-                    sys.refresh_all();
-                    display_disk(&sys, rect, chunks[1])
-                }
-                MenuItem::Temperature => {
-                    sys.refresh_all();
-                    display_temperature(&sys, rect, chunks[1], &mut app)
-                }
-                MenuItem::Battery => {
-                    sys.refresh_all();
-                    display_battery(rect, chunks[1])
+
+            let theme = themes.active();
+            rect.render_widget(panels.tabs(&app, theme), chunks[0]);
+            if let Some(panel) = panels.active_mut() {
+                panel.render(rect, chunks[1], &snapshot, &mut app, theme);
+            }
+
+            let mut status = match &replay {
+                Some(replay) => {
+                    let since_start = |time: SystemTime| format_clock(time.duration_since(replay.start()).unwrap_or_default());
+                    let speed = if replay.is_finished() {
+                        "end of recording".to_string()
+                    } else if replay.is_paused() {
+                        format!("{}x (paused)", replay.speed())
+                    } else {
+                        format!("{}x", replay.speed())
+                    };
+                    vec![
+                        ("Replay", format!("{} ({} / {})", format_timestamp(replay.position()), since_start(replay.position()), since_start(replay.end()))),
+                        ("Speed", speed),
+                    ]
                 }
-                MenuItem::Quit => {
-                    let block = Block::default()
-                        .title("Quit")
-                        .borders(Borders::ALL);
-                    rect.render_widget(block, chunks[1]);
+                None => {
+                    let needs = sampled(panels.active().map(|panel| panel.needs()).unwrap_or_default(), &app.alerts, exporter.as_ref());
+                    let sampling = if needs.is_empty() {
+                        "background only".to_string()
+                    } else {
+                        needs.iter().map(Subsystem::name).collect::<Vec<_>>().join(", ")
+                    };
+                    let interval = if paused { format!("{} (paused)", format_duration(interval)) } else { format_duration(interval) };
+                    let mut status = vec![];
+                    if let Some(remote) = &remote {
+                        status.push(("Host", remote.clone()));
+                    }
+                    status.extend([
+                        ("Interval", interval),
+                        ("Sampling", sampling),
+                        ("Sample cost", format!("{:.1} ms", sampler.last_cost().as_secs_f64() * 1000.0)),
+                    ]);
+                    status
                 }
+            };
+            if let Some(load) = snapshot.load {
+                status.push(("Load", format!("{:.2} {:.2} {:.2}", load.one, load.five, load.fifteen)));
             }
-
-            rect.render_widget(tabs, chunks[0]);
+            status.push(("Theme", theme.name.clone()));
+            if let Some(path) = &cli.record {
+                let recording = match &recording_failed {
+                    Some(err) => format!("stopped, {}", err),
+                    None => path.display().to_string(),
+                };
+                status.push(("Recording", recording));
+            }
+            if let Some(exporter) = &exporter {
+                status.push(("Metrics", exporter.address().to_string()));
+            }
+            if let Some(err) = &fleet_error {
+                status.push(("Fleet", err.clone()));
+            }
+            if !app.keymap.pending().is_empty() {
+                status.push(("Keys", format_sequence(app.keymap.pending())));
+            }
+            if banner {
+                display_alert_banner(rect, chunks[2], &app.alerts, theme);
+            }
+            display_status(rect, chunks[3], &status, theme);
         })?;
                 
-        match rx.recv() {
-            Ok(event) => match event { //check if event is a keypress or tick
-                Event::Input(event) => match event.code { // check if keypress is a key if not ignore
-                    KeyCode::Char('q') => {
-                        disable_raw_mode()?;
-                        execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen).expect("can disable mouse capture");
-                        break;
-                    }
-                    KeyCode::Char('h') => {
-                        active_menu_item = MenuItem::Home;
-                    }
-                    KeyCode::Char('c') => {
-                        active_menu_item = MenuItem::CPU;
-                    }
-                    KeyCode::Char('m') => {
-                        active_menu_item = MenuItem::Memory;
-                    }
-                    KeyCode::Char('n') => {
-                        active_menu_item = MenuItem::Network;
-                    }
-                    KeyCode::Char('p') => {
-                        active_menu_item = MenuItem::Process;
-                    }
-                    KeyCode::Char('d') => {
-                        active_menu_item = MenuItem::Disk;
+        let timeout = next_sample.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(timeout) {
+            Ok(event) => match event { //check if event is a keypress
+                Event::Quit => break,
+                Event::InputError(err) => return Err(err),
+                Event::Input(event) => {
+                    // The active panel gets the first say on every action.
+                    let result = app.keymap.feed(event);
+                    if let (Some(panel), KeyResult::Action(action)) = (panels.active_mut(), &result) {
+                        if panel.handle_action(action, &mut app) {
+                            continue;
+                        }
                     }
-                    KeyCode::Char('t') => {
-                        active_menu_item = MenuItem::Temperature;
+                    match result {
+                        KeyResult::Action(Action::Quit) => break,
+                        KeyResult::Action(Action::Faster) => match &mut replay {
+                            Some(replay) => replay.faster(),
+                            None => {
+                                interval = faster(interval);
+                                next_sample = Instant::now() + interval;
+                            }
+                        },
+                        KeyResult::Action(Action::Slower) => match &mut replay {
+                            Some(replay) => replay.slower(),
+                            None => {
+                                interval = slower(interval);
+                                next_sample = Instant::now() + interval;
+                            }
+                        },
+                        KeyResult::Action(Action::Pause) => match &mut replay {
+                            Some(replay) => replay.toggle_pause(),
+                            None => paused = !paused,
+                        },
+                        KeyResult::Action(action @ (Action::SeekForward | Action::SeekBackward)) => {
+                            if let Some(replay) = &mut replay {
+                                replay.seek_by(if action == Action::SeekForward { 1 } else { -1 }, &mut app.history);
+                                snapshot = replay.snapshot().clone();
+                                app.alerts.evaluate(&snapshot);
+                                publish(&shared, &snapshot);
+                            }
+                        }
+                        KeyResult::Action(Action::NextTheme) => {
+                            themes.cycle();
+                        }
+                        KeyResult::Action(Action::ShowPanel(name)) => {
+                            panels.select_name(&name);
+                        }
+                        KeyResult::Action(Action::ScrollDown) => app.scroll_down(),
+                        KeyResult::Action(Action::ScrollUp) => app.scroll_up(),
+                        KeyResult::Action(Action::Back) => {
+                            if let Some((local_sampler, local_history, local_remote)) = local.take() {
+                                sampler = local_sampler;
+                                app.history = local_history;
+                                remote = local_remote;
+                                let needs = sampled(panels.active().map(|panel| panel.needs()).unwrap_or_default(), &app.alerts, exporter.as_ref());
+                                snapshot = sampler.sample(&needs);
+                                app.history.record(&snapshot);
+                                panels.select_name("fleet");
+                                next_sample = Instant::now() + interval;
+                            }
+                            fleet_error = None;
+                        }
+                        // Panel actions do nothing on other panels.
+                        KeyResult::Action(Action::CpuView | Action::CpuNextCore | Action::CpuPrevCore
+                            | Action::CpuHeatmap | Action::CpuBreakdown
+                            | Action::FleetSort | Action::FleetReverse | Action::FleetOpen) => {}
+                        KeyResult::Pending | KeyResult::Unbound => {}
                     }
-                    KeyCode::Char('b') => {
-                        active_menu_item = MenuItem::Battery;
-                    }
-                    KeyCode::Down => {
-                        app.vertical_scroll = app.vertical_scroll.saturating_add(1);
-                        app.vertical_scroll_state = app
-                            .vertical_scroll_state
-                            .position(app.vertical_scroll as u16);
-                    }
-                    KeyCode::Up => {
-                        app.vertical_scroll = app.vertical_scroll.saturating_sub(1);
-                        app.vertical_scroll_state = app
-                            .vertical_scroll_state
-                            .position(app.vertical_scroll as u16);
-                    }
-                    _ => {}
                 },
-                Event::Tick => {}
             },
+            Err(RecvTimeoutError::Timeout) => {
+                match &mut replay {
+                    Some(replay) => {
+                        if replay.advance(interval, &mut app.history) {
+                            snapshot = replay.snapshot().clone();
+                            app.alerts.evaluate(&snapshot);
+                            publish(&shared, &snapshot);
+                        }
+                    }
+                    None if paused => {}
+                    None => {
+                        let needs = sampled(panels.active().map(|panel| panel.needs()).unwrap_or_default(), &app.alerts, exporter.as_ref());
+                        snapshot = sampler.sample(&needs);
+                        app.history.record(&snapshot);
+                        // Alerts, the exporter and the recording are about
+                        // this machine, not the fleet host being looked at.
+                        if local.is_none() {
+                            app.alerts.evaluate(&snapshot);
+                            publish(&shared, &snapshot);
+                            record(&mut recorder, &snapshot, &mut recording_failed);
+                        }
+                    }
+                }
+                next_sample += interval;
+                if next_sample < Instant::now() {
+                    next_sample = Instant::now() + interval;
+                }
+            }
             Err(err) => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("RecvError: {:?}", err),
-                ));
+                return Err(std::io::Error::other(format!("RecvError: {:?}", err)));
             }
         }
     }
     Ok(())
 }
+
+// Prints `count` samples one interval apart, or keeps going when `count` is
+// `None`. The first sample is taken an interval before the first one printed
+// so CPU usage covers a full interval, and every sample refreshes all of
+// `needs` regardless of cadence. Returns whether an alert fired in any
+// printed sample.
+fn print_samples(sampler: &mut Sampler, needs: &[Subsystem], interval: Duration, format: Format, count: Option<u64>, units: &Units, alerts: &mut Alerts) -> Result<bool, io::Error> {
+    let sampling = sampled(needs, alerts, None);
+    sampler.set_background_interval(None);
+    for subsystem in &sampling {
+        sampler.set_cadence(*subsystem, Duration::ZERO);
+    }
+    alerts.evaluate(&sampler.sample(&sampling));
+    let mut fired = false;
+    let mut stdout = io::stdout().lock();
+    for i in 0.. {
+        if count.is_some_and(|count| i >= count) {
+            break;
+        }
+        thread::sleep(interval);
+        let snapshot = sampler.sample(&sampling);
+        alerts.evaluate(&snapshot);
+        fired |= alerts.firing().next().is_some();
+        let mut document = Document::new(&snapshot, needs);
+        if !alerts.is_empty() {
+            document = document.with_alerts(alerts);
+        }
+        let output = match format {
+            Format::Text => {
+                let mut text = report(&snapshot, needs, units);
+                if !alerts.is_empty() {
+                    text.push_str(&report_alerts(alerts));
+                }
+                if i > 0 { format!("\n{}", text) } else { text }
+            }
+            Format::Json => format!("{}\n", to_json(&document)?),
+            Format::Ndjson => format!("{}\n", to_json_line(&document)?),
+        };
+        let written = stdout.write_all(output.as_bytes()).and_then(|_| stdout.flush());
+        match written {
+            // The reader went away, e.g. `systemmor --count 100 | head`.
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return Ok(fired),
+            result => result?,
+        }
+    }
+    Ok(fired)
+}
+
+// Samples everything for the exporter and recorder until the process is
+// stopped or the recording cannot be written. Alerts that start or end are
+// logged to stderr.
+fn serve(sampler: &mut Sampler, interval: Duration, shared: &SharedSnapshot, mut recorder: Option<Recorder>, alerts: &mut Alerts) -> Result<(), io::Error> {
+    loop {
+        let snapshot = sampler.sample_all();
+        for change in alerts.evaluate(&snapshot) {
+            match change {
+                AlertChange::Started(name) => {
+                    let rule = alerts.rule(&name).map(|rule| rule.to_string()).unwrap_or_default();
+                    eprintln!("systemmor: alert '{}' firing: {}", name, rule);
+                }
+                AlertChange::Ended(name) => eprintln!("systemmor: alert '{}' resolved", name),
+            }
+        }
+        publish(shared, &snapshot);
+        if let Some(recorder) = &mut recorder {
+            recorder.record(&snapshot)?;
+        }
+        thread::sleep(interval);
+    }
+}
+
+fn configure(sampler: &mut Sampler, refresh: &RefreshConfig) {
+    sampler.set_background_interval(refresh.background);
+    for subsystem in Subsystem::ALL {
+        sampler.set_cadence(subsystem, refresh.cadence.get(subsystem));
+    }
+}
+
+fn record(recorder: &mut Option<Recorder>, snapshot: &SystemSnapshot, failed: &mut Option<String>) {
+    if let Some(active) = recorder {
+        if let Err(err) = active.record(snapshot) {
+            *failed = Some(err.to_string());
+            *recorder = None;
+        }
+    }
+}
+
+// The subsystems to sample: `needs` plus whatever the alert rules read.
+// Reports an error in the arguments or configuration and exits.
+fn fail(err: impl std::fmt::Display) -> ! {
+    eprintln!("systemmor: {}", err);
+    std::process::exit(1)
+}
+
+fn sampled(needs: &[Subsystem], alerts: &Alerts, exporter: Option<&Exporter>) -> Vec<Subsystem> {
+    let mut sampled = needs.to_vec();
+    sampled.extend(alerts.needs());
+    sampled.extend(exporter.map(Exporter::needs).unwrap_or_default());
+    sampled.sort();
+    sampled.dedup();
+    sampled
+}
+
+fn publish(shared: &SharedSnapshot, snapshot: &SystemSnapshot) {
+    if let Ok(mut latest) = shared.lock() {
+        *latest = snapshot.clone();
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt::Write;
+use std::time::UNIX_EPOCH;
+use crate::snapshot::{BatteryState, CpuTimes, Subsystem, SystemSnapshot};
+
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+const BATTERY_STATES: [(BatteryState, &str); 5] = [
+    (BatteryState::Unknown, "unknown"),
+    (BatteryState::Charging, "charging"),
+    (BatteryState::Discharging, "discharging"),
+    (BatteryState::Empty, "empty"),
+    (BatteryState::Full, "full"),
+];
+
+// Writes one metric family at a time in the OpenMetrics text format.
+struct Writer {
+    out: String,
+}
+
+impl Writer {
+    // `unit` must be the suffix of `name`, as OpenMetrics requires.
+    fn family(&mut self, name: &str, kind: &str, unit: Option<&str>, help: &str) {
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+        if let Some(unit) = unit {
+            let _ = writeln!(self.out, "# UNIT {} {}", name, unit);
+        }
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels.iter().map(|(label, value)| format!("{}=\"{}\"", label, escape(value))).collect();
+            let _ = write!(self.out, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.out, " {}", value);
+    }
+}
+
+// Renders the snapshot for a `/metrics` scrape. Subsystems that have not
+// been sampled, or could not be read, are left out apart from their
+// `systemmor_subsystem_up` series. Only the `top_processes` busiest
+// processes are exported to keep the number of series bounded.
+pub fn render(snapshot: &SystemSnapshot, top_processes: usize) -> String {
+    let mut w = Writer { out: String::new() };
+    let available = |subsystem: Subsystem| snapshot.refreshed_at(subsystem).is_some() && snapshot.error(subsystem).is_none();
+
+    w.family("systemmor_subsystem_up", "gauge", None, "Whether the subsystem was read successfully (1) or not (0).");
+    for subsystem in Subsystem::ALL {
+        if snapshot.refreshed_at(subsystem).is_some() {
+            w.sample("systemmor_subsystem_up", &[("subsystem", subsystem.name())], if available(subsystem) { 1.0 } else { 0.0 });
+        }
+    }
+    w.family("systemmor_snapshot_timestamp_seconds", "gauge", Some("seconds"), "Time the snapshot was taken.");
+    let timestamp = snapshot.timestamp.duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs_f64()).unwrap_or_default();
+    w.sample("systemmor_snapshot_timestamp_seconds", &[], timestamp);
+
+    if available(Subsystem::Cpu) {
+        w.family("systemmor_cpu_usage_ratio", "gauge", Some("ratio"), "CPU usage per core over the last sampling interval.");
+        for (i, cpu) in snapshot.cpus.iter().enumerate() {
+            w.sample("systemmor_cpu_usage_ratio", &[("cpu", &i.to_string())], cpu.usage as f64 / 100.0);
+        }
+        if snapshot.cpu_frequency().is_some() {
+            w.family("systemmor_cpu_frequency_hertz", "gauge", Some("hertz"), "Current clock per core.");
+            for (i, cpu) in snapshot.cpus.iter().enumerate().filter(|(_, cpu)| cpu.frequency > 0) {
+                w.sample("systemmor_cpu_frequency_hertz", &[("cpu", &i.to_string())], cpu.frequency as f64 * 1e6);
+            }
+        }
+        if snapshot.cpu_times().is_some() {
+            w.family("systemmor_cpu_time_ratio", "gauge", Some("ratio"), "Share of each core's time per mode over the last sampling interval.");
+            for (i, cpu) in snapshot.cpus.iter().enumerate() {
+                let Some(times) = cpu.times else { continue };
+                for (mode, value) in CpuTimes::NAMES.iter().zip(times.values()) {
+                    w.sample("systemmor_cpu_time_ratio", &[("cpu", &i.to_string()), ("mode", mode)], value as f64 / 100.0);
+                }
+            }
+        }
+        if let Some(load) = snapshot.load {
+            w.family("systemmor_load_average", "gauge", None, "Load average over the last 1, 5 and 15 minutes.");
+            for (minutes, value) in [("1", load.one), ("5", load.five), ("15", load.fifteen)] {
+                w.sample("systemmor_load_average", &[("minutes", minutes)], value);
+            }
+            for (name, help, value) in [
+                ("systemmor_tasks_running", "Tasks running or waiting for a core.", load.running),
+                ("systemmor_tasks_blocked", "Tasks blocked on I/O.", load.blocked),
+            ] {
+                if let Some(value) = value {
+                    w.family(name, "gauge", None, help);
+                    w.sample(name, &[], value as f64);
+                }
+            }
+        }
+    }
+
+    if available(Subsystem::Memory) {
+        let memory = &snapshot.memory;
+        for (name, help, value) in [
+            ("systemmor_memory_total_bytes", "Total physical memory.", memory.total),
+            ("systemmor_memory_used_bytes", "Physical memory in use.", memory.used),
+            ("systemmor_swap_total_bytes", "Total swap space.", memory.total_swap),
+            ("systemmor_swap_used_bytes", "Swap space in use.", memory.used_swap),
+        ] {
+            w.family(name, "gauge", Some("bytes"), help);
+            w.sample(name, &[], value as f64);
+        }
+    }
+
+    if available(Subsystem::Networks) {
+        w.family("systemmor_network_received_bytes", "counter", Some("bytes"), "Bytes received since the interface came up.");
+        for network in &snapshot.networks {
+            w.sample("systemmor_network_received_bytes_total", &[("interface", &network.interface)], network.total_received as f64);
+        }
+        w.family("systemmor_network_transmitted_bytes", "counter", Some("bytes"), "Bytes transmitted since the interface came up.");
+        for network in &snapshot.networks {
+            w.sample("systemmor_network_transmitted_bytes_total", &[("interface", &network.interface)], network.total_transmitted as f64);
+        }
+    }
+
+    if available(Subsystem::Disks) {
+        w.family("systemmor_disk_total_bytes", "gauge", Some("bytes"), "Size of the file system.");
+        for disk in &snapshot.disks {
+            w.sample("systemmor_disk_total_bytes", &disk_labels(&disk.name, &disk.mount_point, &disk.kind), disk.total_space as f64);
+        }
+        w.family("systemmor_disk_available_bytes", "gauge", Some("bytes"), "Free space on the file system.");
+        for disk in &snapshot.disks {
+            w.sample("systemmor_disk_available_bytes", &disk_labels(&disk.name, &disk.mount_point, &disk.kind), disk.available_space as f64);
+        }
+    }
+
+    if available(Subsystem::Components) {
+        // Sensors can share a label (one "Composite" per NVMe drive), so
+        // the index keeps their series apart.
+        w.family("systemmor_temperature_celsius", "gauge", Some("celsius"), "Current temperature of the sensor.");
+        for (i, component) in snapshot.components.iter().enumerate() {
+            w.sample("systemmor_temperature_celsius", &[("sensor", &component.label), ("index", &i.to_string())], component.temperature as f64);
+        }
+        w.family("systemmor_temperature_max_celsius", "gauge", Some("celsius"), "Highest temperature seen by the sensor.");
+        for (i, component) in snapshot.components.iter().enumerate() {
+            w.sample("systemmor_temperature_max_celsius", &[("sensor", &component.label), ("index", &i.to_string())], component.max as f64);
+        }
+        w.family("systemmor_temperature_critical_celsius", "gauge", Some("celsius"), "Critical temperature of the sensor, where known.");
+        for (i, component) in snapshot.components.iter().enumerate() {
+            if let Some(critical) = component.critical {
+                w.sample("systemmor_temperature_critical_celsius", &[("sensor", &component.label), ("index", &i.to_string())], critical as f64);
+            }
+        }
+    }
+
+    if available(Subsystem::Batteries) {
+        w.family("systemmor_battery_charge_ratio", "gauge", Some("ratio"), "State of charge of the battery.");
+        for (i, battery) in snapshot.batteries.iter().enumerate() {
+            w.sample("systemmor_battery_charge_ratio", &[("battery", &(i + 1).to_string())], battery.charge as f64);
+        }
+        w.family("systemmor_battery_state", "stateset", None, "Whether the battery is charging, discharging, empty or full.");
+        for (i, battery) in snapshot.batteries.iter().enumerate() {
+            let index = (i + 1).to_string();
+            for (state, name) in BATTERY_STATES {
+                let value = if battery.state == state { 1.0 } else { 0.0 };
+                w.sample("systemmor_battery_state", &[("battery", &index), ("systemmor_battery_state", name)], value);
+            }
+        }
+    }
+
+    if available(Subsystem::Processes) {
+        let mut processes: Vec<_> = snapshot.processes.iter().collect();
+        processes.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage));
+        processes.truncate(top_processes);
+        w.family("systemmor_process_cpu_usage_ratio", "gauge", Some("ratio"), "CPU usage of the busiest processes, where 1 is one full core.");
+        for process in &processes {
+            w.sample("systemmor_process_cpu_usage_ratio", &[("pid", &process.pid.to_string()), ("name", &process.name)], process.cpu_usage as f64 / 100.0);
+        }
+        w.family("systemmor_process_memory_bytes", "gauge", Some("bytes"), "Resident memory of the busiest processes.");
+        for process in &processes {
+            w.sample("systemmor_process_memory_bytes", &[("pid", &process.pid.to_string()), ("name", &process.name)], process.memory as f64);
+        }
+    }
+
+    w.out.push_str("# EOF\n");
+    w.out
+}
+
+fn disk_labels<'a>(device: &'a str, mount_point: &'a str, kind: &'a str) -> [(&'static str, &'a str); 3] {
+    [("device", device), ("mount_point", mount_point), ("kind", kind)]
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
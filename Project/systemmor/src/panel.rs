@@ -0,0 +1,424 @@
+use crossterm::event::KeyCode;
+use ratatui::{
+    backend::Backend,
+    layout::Rect,
+    style::*,
+    text::{Line, Span},
+    widgets::*,
+    Frame,
+};
+use crate::{Action, App, Error, Result, Subsystem, SystemSnapshot, Theme};
+use crate::command::{CommandConfig, CommandRunner, CommandState};
+use crate::{display_home, display_cpu, display_memory, display_network, display_process, display_disk, display_temperature, display_battery, display_alerts, display_fleet, display_command};
+
+// A view that can be shown in the main area and selected from the tab bar.
+//
+// `handle_action` is offered every action while the panel is active, before
+// it is handled globally; return `true` to consume it. `start` is called
+// once before the interface is shown, for panels that collect their own
+// data.
+pub trait Panel<B: Backend> {
+    fn title(&self) -> &str;
+    fn hotkey(&self) -> char;
+    fn needs(&self) -> &[Subsystem];
+    fn render(&mut self, rect: &mut Frame<'_, B>, chunks: Rect, snapshot: &SystemSnapshot, app: &mut App, theme: &Theme);
+    fn handle_action(&mut self, _action: &Action, _app: &mut App) -> bool {
+        false
+    }
+    fn start(&mut self) {}
+}
+
+// Ordered set of panels backing the tab bar.
+pub struct PanelRegistry<B: Backend> {
+    panels: Vec<Box<dyn Panel<B>>>,
+    active: usize,
+}
+
+impl<B: Backend> Default for PanelRegistry<B> {
+    fn default() -> Self {
+        let mut registry = PanelRegistry::new();
+        registry.register(Box::new(HomePanel));
+        registry.register(Box::new(CpuPanel::default()));
+        registry.register(Box::new(MemoryPanel));
+        registry.register(Box::new(NetworkPanel));
+        registry.register(Box::new(ProcessPanel));
+        registry.register(Box::new(DiskPanel));
+        registry.register(Box::new(TemperaturePanel));
+        registry.register(Box::new(BatteryPanel));
+        registry.register(Box::new(AlertsPanel));
+        registry.register(Box::new(FleetPanel));
+        registry
+    }
+}
+
+impl<B: Backend> PanelRegistry<B> {
+    pub fn new() -> Self {
+        PanelRegistry {
+            panels: vec![],
+            active: 0,
+        }
+    }
+
+    pub fn register(&mut self, panel: Box<dyn Panel<B>>) {
+        self.panels.push(panel);
+    }
+
+    pub fn len(&self) -> usize {
+        self.panels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.panels.is_empty()
+    }
+
+    pub fn panels(&self) -> impl Iterator<Item = &dyn Panel<B>> {
+        self.panels.iter().map(|panel| panel.as_ref())
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> Option<&dyn Panel<B>> {
+        self.panels.get(self.active).map(|panel| panel.as_ref())
+    }
+
+    pub fn active_mut(&mut self) -> Option<&mut (dyn Panel<B> + 'static)> {
+        self.panels.get_mut(self.active).map(|panel| panel.as_mut())
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.panels.len() {
+            self.active = index;
+        }
+    }
+
+    // Switches to the panel with the given name, see `panel_name`.
+    pub fn select_name(&mut self, name: &str) -> bool {
+        match self.panels.iter().position(|panel| panel_name(panel.as_ref()) == name) {
+            Some(index) => {
+                self.active = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Keeps only the named panels, in the given order.
+    pub fn configure(&mut self, names: &[String]) -> Result<()> {
+        let mut available: Vec<Option<Box<dyn Panel<B>>>> = self.panels.drain(..).map(Some).collect();
+        let known: Vec<String> = available.iter().flatten().map(|panel| panel_name(panel.as_ref())).collect();
+        for name in names {
+            let panel = known.iter()
+                .position(|known| known == name)
+                .and_then(|index| available[index].take());
+            match panel {
+                Some(panel) => self.panels.push(panel),
+                None if known.contains(name) => {
+                    return Err(Error::Config(format!("panel '{}' is listed more than once", name)));
+                }
+                None => {
+                    return Err(Error::Config(format!("unknown panel '{}' (available: {})", name, known.join(", "))));
+                }
+            }
+        }
+        self.active = 0;
+        Ok(())
+    }
+
+    // Adds a panel for each `[[commands]]` entry, after the built-in ones.
+    pub fn register_commands(&mut self, commands: &[CommandConfig]) -> Result<()> {
+        let mut number = 0;
+        for command in commands {
+            command.validate()?;
+            let name = command.name.to_lowercase();
+            if self.panels.iter().any(|panel| panel_name(panel.as_ref()) == name) {
+                return Err(Error::Config(format!("command panel '{}' has the same name as another panel", command.name)));
+            }
+            // 1 to 9 and then 0; later panels need their own key.
+            let hotkey = match command.key {
+                Some(key) => key,
+                None if number < 10 => {
+                    number += 1;
+                    char::from_digit(number % 10, 10).unwrap_or('0')
+                }
+                None => return Err(Error::Config(format!("command panel '{}' needs a key, only the first 10 are numbered", command.name))),
+            };
+            self.register(Box::new(CommandPanel::new(command.clone(), hotkey)));
+        }
+        Ok(())
+    }
+
+    // Starts the panels that collect their own data.
+    pub fn start(&mut self) {
+        for panel in &mut self.panels {
+            panel.start();
+        }
+    }
+
+    // The (name, default hotkey) of every panel, for building the keymap.
+    pub fn hotkeys(&self) -> Vec<(String, char)> {
+        self.panels.iter().map(|panel| (panel_name(panel.as_ref()), panel.hotkey())).collect()
+    }
+
+    pub fn tabs(&self, app: &App, theme: &Theme) -> Tabs<'_> {
+        let mut menu: Vec<Line> = self.panels.iter()
+            .map(|panel| tab_title(panel.title(), app.keymap.panel_hotkey(&panel_name(panel.as_ref())), theme))
+            .collect();
+        // Quitting is not a panel, but the tab bar has always advertised it.
+        let quit = app.keymap.keys_for(&Action::Quit).into_iter().find_map(|sequence| match sequence.as_slice() {
+            [chord] if chord.modifiers.is_empty() => match chord.code {
+                KeyCode::Char(c) => Some(c),
+                _ => None,
+            },
+            _ => None,
+        });
+        menu.push(tab_title("Quit", quit, theme));
+
+        Tabs::new(menu)
+            .block(Block::default().borders(Borders::ALL).title("Menu"))
+            .select(self.active)
+            .style(theme.text)
+            .highlight_style(theme.highlight)
+            .divider(Span::raw("|"))
+    }
+}
+
+// The name a panel is referred to by in the configuration: its title in
+// lower case.
+pub fn panel_name<B: Backend>(panel: &dyn Panel<B>) -> String {
+    panel.title().to_lowercase()
+}
+
+// Underlines the hotkey inside the title, e.g. "[C]PU" for 'c'.
+fn tab_title<'a>(title: &'a str, hotkey: Option<char>, theme: &Theme) -> Line<'a> {
+    let position = title.char_indices()
+        .find(|(_, c)| hotkey.is_some_and(|hotkey| c.eq_ignore_ascii_case(&hotkey)))
+        .map(|(i, c)| (i, i + c.len_utf8()));
+    match position {
+        Some((start, end)) => Line::from(vec![
+            Span::styled(&title[..start], theme.text),
+            Span::styled(&title[start..end], theme.highlight.add_modifier(Modifier::UNDERLINED)),
+            Span::styled(&title[end..], theme.text),
+        ]),
+        None => Line::from(Span::styled(title, theme.text)),
+    }
+}
+
+pub struct HomePanel;
+#[derive(Default)]
+pub struct CpuPanel {
+    view: CpuView,
+    graph: CpuGraph,
+    // Cores in the last rendered snapshot, to wrap the core selection.
+    cores: usize,
+}
+pub struct MemoryPanel;
+pub struct NetworkPanel;
+pub struct ProcessPanel;
+pub struct DiskPanel;
+pub struct TemperaturePanel;
+pub struct BatteryPanel;
+pub struct AlertsPanel;
+pub struct FleetPanel;
+
+// What the CPU chart plots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CpuView {
+    #[default]
+    Average,
+    // The average and every core.
+    AllCores,
+    Core(usize),
+}
+
+// How the CPU panel draws the current usage of each core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CpuGraph {
+    // One bar per core, which only fits a few dozen cores.
+    #[default]
+    Bars,
+    // One row per core (or group of cores) over time.
+    Heatmap,
+    // Where each core's time goes, and the same for all cores over time.
+    Breakdown,
+}
+
+impl CpuPanel {
+    pub fn view(&self) -> CpuView {
+        self.view
+    }
+
+    pub fn graph(&self) -> CpuGraph {
+        self.graph
+    }
+}
+
+// A `[[commands]]` panel. Its command only runs once the panel is started,
+// so `--once` and the agent never run it.
+pub struct CommandPanel {
+    config: CommandConfig,
+    hotkey: char,
+    runner: Option<CommandRunner>,
+}
+
+impl CommandPanel {
+    pub fn new(config: CommandConfig, hotkey: char) -> Self {
+        CommandPanel { config, hotkey, runner: None }
+    }
+
+    // The latest results, or `None` before the panel is started.
+    pub fn state(&self) -> Option<CommandState> {
+        self.runner.as_ref().map(CommandRunner::state)
+    }
+}
+
+impl<B: Backend> Panel<B> for HomePanel {
+    fn title(&self) -> &str { "Home" }
+    fn hotkey(&self) -> char { 'h' }
+    fn needs(&self) -> &[Subsystem] { &[] }
+    fn render(&mut self, rect: &mut Frame<'_, B>, chunks: Rect, _snapshot: &SystemSnapshot, app: &mut App, theme: &Theme) {
+        display_home(rect, chunks, app, theme)
+    }
+}
+
+impl<B: Backend> Panel<B> for CpuPanel {
+    fn title(&self) -> &str { "CPU" }
+    fn hotkey(&self) -> char { 'c' }
+    fn needs(&self) -> &[Subsystem] { &[Subsystem::Cpu] }
+    fn render(&mut self, rect: &mut Frame<'_, B>, chunks: Rect, snapshot: &SystemSnapshot, app: &mut App, theme: &Theme) {
+        self.cores = snapshot.cpus.len();
+        display_cpu(snapshot, rect, chunks, app, self.view, self.graph, theme)
+    }
+    fn handle_action(&mut self, action: &Action, _app: &mut App) -> bool {
+        let graph = match action {
+            Action::CpuHeatmap => Some(CpuGraph::Heatmap),
+            Action::CpuBreakdown => Some(CpuGraph::Breakdown),
+            _ => None,
+        };
+        if let Some(graph) = graph {
+            self.graph = if self.graph == graph { CpuGraph::Bars } else { graph };
+            return true;
+        }
+        let last = self.cores.saturating_sub(1);
+        self.view = match (action, self.view) {
+            (Action::CpuView, CpuView::Average) => CpuView::AllCores,
+            (Action::CpuView, CpuView::AllCores) => CpuView::Core(0),
+            (Action::CpuView, CpuView::Core(_)) => CpuView::Average,
+            (Action::CpuNextCore, CpuView::Core(core)) => CpuView::Core(if core >= last { 0 } else { core + 1 }),
+            (Action::CpuPrevCore, CpuView::Core(core)) => CpuView::Core(match core.min(last) {
+                0 => last,
+                core => core - 1,
+            }),
+            // Picking a core switches to it from the other views.
+            (Action::CpuNextCore, _) => CpuView::Core(0),
+            (Action::CpuPrevCore, _) => CpuView::Core(last),
+            _ => return false,
+        };
+        true
+    }
+}
+
+impl<B: Backend> Panel<B> for MemoryPanel {
+    fn title(&self) -> &str { "Memory" }
+    fn hotkey(&self) -> char { 'm' }
+    fn needs(&self) -> &[Subsystem] { &[Subsystem::Memory] }
+    fn render(&mut self, rect: &mut Frame<'_, B>, chunks: Rect, snapshot: &SystemSnapshot, app: &mut App, theme: &Theme) {
+        display_memory(snapshot, rect, chunks, app, theme)
+    }
+}
+
+impl<B: Backend> Panel<B> for NetworkPanel {
+    fn title(&self) -> &str { "Network" }
+    fn hotkey(&self) -> char { 'n' }
+    fn needs(&self) -> &[Subsystem] { &[Subsystem::Networks] }
+    fn render(&mut self, rect: &mut Frame<'_, B>, chunks: Rect, snapshot: &SystemSnapshot, app: &mut App, theme: &Theme) {
+        display_network(snapshot, rect, chunks, app, theme)
+    }
+}
+
+impl<B: Backend> Panel<B> for ProcessPanel {
+    fn title(&self) -> &str { "Process" }
+    fn hotkey(&self) -> char { 'p' }
+    fn needs(&self) -> &[Subsystem] { &[Subsystem::Processes] }
+    fn render(&mut self, rect: &mut Frame<'_, B>, chunks: Rect, snapshot: &SystemSnapshot, app: &mut App, theme: &Theme) {
+        display_process(snapshot, rect, chunks, app, theme)
+    }
+}
+
+impl<B: Backend> Panel<B> for DiskPanel {
+    fn title(&self) -> &str { "Disk" }
+    fn hotkey(&self) -> char { 'd' }
+    fn needs(&self) -> &[Subsystem] { &[Subsystem::Disks] }
+    fn render(&mut self, rect: &mut Frame<'_, B>, chunks: Rect, snapshot: &SystemSnapshot, app: &mut App, theme: &Theme) {
+        display_disk(snapshot, rect, chunks, app, theme)
+    }
+}
+
+impl<B: Backend> Panel<B> for TemperaturePanel {
+    fn title(&self) -> &str { "Temperature" }
+    fn hotkey(&self) -> char { 't' }
+    fn needs(&self) -> &[Subsystem] { &[Subsystem::Components] }
+    fn render(&mut self, rect: &mut Frame<'_, B>, chunks: Rect, snapshot: &SystemSnapshot, app: &mut App, theme: &Theme) {
+        display_temperature(snapshot, rect, chunks, app, theme)
+    }
+}
+
+impl<B: Backend> Panel<B> for BatteryPanel {
+    fn title(&self) -> &str { "Battery" }
+    fn hotkey(&self) -> char { 'b' }
+    fn needs(&self) -> &[Subsystem] { &[Subsystem::Batteries] }
+    fn render(&mut self, rect: &mut Frame<'_, B>, chunks: Rect, snapshot: &SystemSnapshot, app: &mut App, theme: &Theme) {
+        display_battery(snapshot, rect, chunks, app, theme)
+    }
+}
+
+impl<B: Backend> Panel<B> for AlertsPanel {
+    fn title(&self) -> &str { "Alerts" }
+    fn hotkey(&self) -> char { 'a' }
+    // The rules' own needs are always sampled.
+    fn needs(&self) -> &[Subsystem] { &[] }
+    fn render(&mut self, rect: &mut Frame<'_, B>, chunks: Rect, _snapshot: &SystemSnapshot, app: &mut App, theme: &Theme) {
+        display_alerts(rect, chunks, app, theme)
+    }
+}
+
+impl<B: Backend> Panel<B> for FleetPanel {
+    fn title(&self) -> &str { "Fleet" }
+    fn hotkey(&self) -> char { 'f' }
+    // Hosts are polled on their own threads.
+    fn needs(&self) -> &[Subsystem] { &[] }
+    fn render(&mut self, rect: &mut Frame<'_, B>, chunks: Rect, _snapshot: &SystemSnapshot, app: &mut App, theme: &Theme) {
+        display_fleet(rect, chunks, app, theme)
+    }
+    // The scroll keys select a host.
+    fn handle_action(&mut self, action: &Action, app: &mut App) -> bool {
+        if app.fleet.is_empty() {
+            return false;
+        }
+        match action {
+            Action::ScrollUp => app.fleet.select_previous(),
+            Action::ScrollDown => app.fleet.select_next(),
+            Action::FleetSort => app.fleet.next_sort(),
+            Action::FleetReverse => app.fleet.reverse(),
+            Action::FleetOpen => app.fleet.open_selected(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl<B: Backend> Panel<B> for CommandPanel {
+    fn title(&self) -> &str { &self.config.name }
+    fn hotkey(&self) -> char { self.hotkey }
+    // The command runs on its own thread.
+    fn needs(&self) -> &[Subsystem] { &[] }
+    fn render(&mut self, rect: &mut Frame<'_, B>, chunks: Rect, _snapshot: &SystemSnapshot, _app: &mut App, theme: &Theme) {
+        display_command(rect, chunks, &self.config, &self.state().unwrap_or_default(), theme)
+    }
+    fn start(&mut self) {
+        if self.runner.is_none() {
+            self.runner = Some(CommandRunner::start(&self.config));
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::fs;
+use crate::snapshot::{CpuTimes, LoadSample};
+
+// Readers for Linux's /proc files. The parsers take the file contents so
+// they can be tested on any system.
+
+// Load averages and task counts from /proc/loadavg and /proc/stat.
+pub fn load() -> Option<LoadSample> {
+    let mut load = parse_loadavg(&fs::read_to_string("/proc/loadavg").ok()?)?;
+    if let Ok(stat) = fs::read_to_string("/proc/stat") {
+        let (running, blocked) = parse_stat_tasks(&stat);
+        load.running = running.or(load.running);
+        load.blocked = blocked;
+    }
+    Some(load)
+}
+
+// "0.52 0.58 0.59 2/1234 5678": three load averages, runnable and total
+// tasks, and the last process id.
+pub fn parse_loadavg(text: &str) -> Option<LoadSample> {
+    let mut fields = text.split_whitespace();
+    let mut average = || fields.next()?.parse::<f64>().ok();
+    let (one, five, fifteen) = (average()?, average()?, average()?);
+    let tasks = fields.next().and_then(|tasks| tasks.split_once('/'));
+    Some(LoadSample {
+        one,
+        five,
+        fifteen,
+        running: tasks.and_then(|(running, _)| running.parse().ok()),
+        blocked: None,
+        tasks: tasks.and_then(|(_, total)| total.parse().ok()),
+    })
+}
+
+// The `procs_running` and `procs_blocked` lines of /proc/stat.
+pub fn parse_stat_tasks(text: &str) -> (Option<u64>, Option<u64>) {
+    let field = |name: &str| text.lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(' ')?.trim().parse().ok());
+    (field("procs_running"), field("procs_blocked"))
+}
+
+// Time each core has spent in each state since boot, in clock ticks, from
+// a `cpuN` line of /proc/stat.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuTicks {
+    pub user: u64,
+    pub nice: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub iowait: u64,
+    pub irq: u64,
+    pub softirq: u64,
+    pub steal: u64,
+    // Already counted in `user` and `nice`.
+    pub guest: u64,
+    pub guest_nice: u64,
+}
+
+impl CpuTicks {
+    // Where the time went between `previous` and these ticks, or `None`
+    // if no time passed.
+    pub fn times_since(&self, previous: &CpuTicks) -> Option<CpuTimes> {
+        let delta = |current: u64, previous: u64| current.saturating_sub(previous) as f64;
+        let guest = delta(self.guest, previous.guest);
+        let guest_nice = delta(self.guest_nice, previous.guest_nice);
+        let user = (delta(self.user, previous.user) - guest).max(0.0) + (delta(self.nice, previous.nice) - guest_nice).max(0.0);
+        let system = delta(self.system, previous.system);
+        let iowait = delta(self.iowait, previous.iowait);
+        let irq = delta(self.irq, previous.irq) + delta(self.softirq, previous.softirq);
+        let steal = delta(self.steal, previous.steal);
+        let total = user + guest + guest_nice + system + delta(self.idle, previous.idle) + iowait + irq + steal;
+        if total <= 0.0 {
+            return None;
+        }
+        let share = |ticks: f64| (ticks / total * 100.0) as f32;
+        Some(CpuTimes {
+            user: share(user),
+            system: share(system),
+            iowait: share(iowait),
+            irq: share(irq),
+            steal: share(steal),
+            guest: share(guest + guest_nice),
+        })
+    }
+}
+
+// The ticks of every online core, in core order.
+pub fn cpu_ticks() -> Option<Vec<CpuTicks>> {
+    Some(parse_stat_cpus(&fs::read_to_string("/proc/stat").ok()?))
+}
+
+// The per-core `cpuN` lines of /proc/stat; the `cpu` total is skipped.
+// Older kernels have fewer columns, which count as zero.
+pub fn parse_stat_cpus(text: &str) -> Vec<CpuTicks> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?.strip_prefix("cpu")?;
+            if name.is_empty() || !name.bytes().all(|byte| byte.is_ascii_digit()) {
+                return None;
+            }
+            let mut ticks = fields.map(|field| field.parse::<u64>().unwrap_or(0));
+            let mut next = || ticks.next().unwrap_or(0);
+            Some(CpuTicks {
+                user: next(),
+                nice: next(),
+                system: next(),
+                idle: next(),
+                iowait: next(),
+                irq: next(),
+                softirq: next(),
+                steal: next(),
+                guest: next(),
+                guest_nice: next(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::error::{Error, Result};
+use crate::history::History;
+use crate::json::{to_json_line, Document, FORMAT_VERSION};
+use crate::snapshot::{Subsystem, SystemSnapshot};
+
+// Playback speeds `faster`/`slower` step through during a replay.
+pub const REPLAY_SPEEDS: [f64; 11] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0, 256.0];
+
+// Recording time skipped by one seek at normal speed; seeks scale with the
+// playback speed.
+pub const SEEK_STEP: Duration = Duration::from_secs(10);
+
+// Appends samples to a recording, one JSON document per line (the same
+// documents as `--format ndjson`). Each line only holds the subsystems
+// refreshed since the previous one.
+pub struct Recorder {
+    file: File,
+    last: BTreeMap<Subsystem, SystemTime>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Recorder { file, last: BTreeMap::new() })
+    }
+
+    pub fn record(&mut self, snapshot: &SystemSnapshot) -> io::Result<()> {
+        let changed: Vec<Subsystem> = snapshot.refreshed.iter()
+            .filter(|(subsystem, refreshed)| self.last.get(subsystem) != Some(refreshed))
+            .map(|(subsystem, _)| *subsystem)
+            .collect();
+        if changed.is_empty() {
+            return Ok(());
+        }
+        // Written in one go and flushed, so a crash loses at most a line.
+        let line = format!("{}\n", to_json_line(&Document::new(snapshot, &changed))?);
+        self.file.write_all(line.as_bytes())?;
+        self.file.flush()?;
+        self.last.extend(changed.into_iter().filter_map(|subsystem| Some((subsystem, snapshot.refreshed_at(subsystem)?))));
+        Ok(())
+    }
+}
+
+// Plays a recording back as a stream of snapshots, on a clock that can be
+// paused, sped up and moved.
+pub struct Replay {
+    documents: Vec<Document<'static>>,
+    // Index of the next document to apply.
+    next: usize,
+    // State before the first document, for seeking backwards.
+    base: SystemSnapshot,
+    snapshot: SystemSnapshot,
+    position: SystemTime,
+    speed: f64,
+    paused: bool,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self> {
+        let fail = |line: usize, message: String| Error::Recording(format!("{}:{}: {}", path.display(), line, message));
+        let file = File::open(path).map_err(|err| Error::Recording(format!("{}: {}", path.display(), err)))?;
+        let mut documents = vec![];
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|err| fail(i + 1, err.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let document: Document = serde_json::from_str(&line).map_err(|err| fail(i + 1, err.to_string()))?;
+            if document.version > FORMAT_VERSION {
+                return Err(fail(i + 1, format!("format version {} is newer than this build supports ({})", document.version, FORMAT_VERSION)));
+            }
+            documents.push(document);
+        }
+        Replay::new(documents).map_err(|err| match err {
+            Error::Recording(message) => Error::Recording(format!("{}: {}", path.display(), message)),
+            err => err,
+        })
+    }
+
+    pub fn new(mut documents: Vec<Document<'static>>) -> Result<Self> {
+        if documents.is_empty() {
+            return Err(Error::Recording("no samples".to_string()));
+        }
+        // Appending to an existing file can leave samples out of order.
+        documents.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+
+        // Subsystems that never show up would otherwise look empty.
+        let mut base = SystemSnapshot::default();
+        for subsystem in Subsystem::ALL {
+            if !documents.iter().any(|document| document.subsystems().contains(&subsystem)) {
+                base.errors.insert(subsystem, Error::Replayed("not in the recording".to_string()));
+            }
+        }
+
+        let mut replay = Replay {
+            documents,
+            next: 0,
+            snapshot: base.clone(),
+            base,
+            position: UNIX_EPOCH,
+            speed: 1.0,
+            paused: false,
+        };
+        replay.position = replay.start();
+        replay.apply_until(replay.position, None);
+        Ok(replay)
+    }
+
+    pub fn snapshot(&self) -> &SystemSnapshot {
+        &self.snapshot
+    }
+
+    pub fn start(&self) -> SystemTime {
+        self.snapshot_time(0)
+    }
+
+    pub fn end(&self) -> SystemTime {
+        self.snapshot_time(self.documents.len() - 1)
+    }
+
+    pub fn position(&self) -> SystemTime {
+        self.position
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next == self.documents.len()
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = REPLAY_SPEEDS.iter().copied().find(|speed| *speed > self.speed).unwrap_or(self.speed);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = REPLAY_SPEEDS.iter().rev().copied().find(|speed| *speed < self.speed).unwrap_or(self.speed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    // Moves the clock on by `elapsed` real time, scaled by the speed, and
+    // applies the samples that were due. Returns whether the snapshot
+    // changed.
+    pub fn advance(&mut self, elapsed: Duration, history: &mut History) -> bool {
+        if self.paused || self.is_finished() {
+            return false;
+        }
+        let target = (self.position + elapsed.mul_f64(self.speed)).min(self.end());
+        self.position = target;
+        self.apply_until(target, Some(history)) > 0
+    }
+
+    // Jumps forward (positive `steps`) or backward by `SEEK_STEP` scaled
+    // by the speed.
+    pub fn seek_by(&mut self, steps: i32, history: &mut History) {
+        let step = SEEK_STEP.mul_f64(self.speed * steps.unsigned_abs() as f64);
+        let target = if steps >= 0 {
+            self.position + step
+        } else {
+            self.position.checked_sub(step).unwrap_or(UNIX_EPOCH)
+        };
+        self.seek(target, history);
+    }
+
+    // Moves to `target`, clamped to the recording. Short jumps forward play
+    // through; otherwise the history is rebuilt from the samples that fit
+    // in it before `target`.
+    pub fn seek(&mut self, target: SystemTime, history: &mut History) {
+        let target = target.clamp(self.start(), self.end());
+        let rebuild = match target.duration_since(self.position) {
+            Ok(ahead) => ahead > history.span(),
+            Err(_) => true,
+        };
+        if rebuild {
+            if target < self.position {
+                self.snapshot = self.base.clone();
+                self.next = 0;
+            }
+            *history = History::new(history.capacity(), history.resolution());
+            let from = target.checked_sub(history.span()).unwrap_or(UNIX_EPOCH);
+            self.apply_until(from, None);
+        }
+        self.apply_until(target, Some(history));
+        self.position = target;
+    }
+
+    // Applies every document up to `until`, recording each into `history`.
+    fn apply_until(&mut self, until: SystemTime, mut history: Option<&mut History>) -> usize {
+        let mut applied = 0;
+        while self.next < self.documents.len() && self.snapshot_time(self.next) <= until {
+            self.documents[self.next].apply(&mut self.snapshot);
+            if let Some(history) = history.as_deref_mut() {
+                history.record(&self.snapshot);
+            }
+            self.next += 1;
+            applied += 1;
+        }
+        applied
+    }
+
+    fn snapshot_time(&self, index: usize) -> SystemTime {
+        UNIX_EPOCH + Duration::try_from_secs_f64(self.documents[index].timestamp).unwrap_or_default()
+    }
+}
+
+// "2024-03-01 03:00:12 UTC"
+pub fn format_timestamp(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (days, rest) = (seconds / 86_400, seconds % 86_400);
+    // Civil date from days since the epoch, after Howard Hinnant's
+    // `civil_from_days`.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, rest / 3_600, rest % 3_600 / 60, rest % 60)
+}
+
+// "1:02:03", or "2:03" under an hour.
+pub fn format_clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3_600 {
+        format!("{}:{:02}:{:02}", seconds / 3_600, seconds % 3_600 / 60, seconds % 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt::Write;
+use crate::alerts::Alerts;
+use crate::replay::format_timestamp;
+use crate::snapshot::{CpuTimes, Subsystem, SystemSnapshot};
+use crate::units::{format_frequency, Units};
+
+// Number of processes listed in a report, busiest first.
+pub const REPORT_PROCESSES: usize = 10;
+
+// Plain-text summary of the given subsystems, for `--once` and `--count`.
+// Subsystems that could not be read are reported with the reason.
+pub fn report(snapshot: &SystemSnapshot, subsystems: &[Subsystem], units: &Units) -> String {
+    let mut out = String::new();
+    for subsystem in Subsystem::ALL.iter().filter(|subsystem| subsystems.contains(subsystem)) {
+        if let Some(err) = snapshot.error(*subsystem) {
+            let _ = writeln!(out, "{}: not available ({})", subsystem.name(), err);
+            continue;
+        }
+        match subsystem {
+            Subsystem::Cpu => {
+                let _ = writeln!(out, "cpu: {:.1}% average over {} cores", snapshot.cpu_average(), snapshot.cpus.len());
+                if let Some(brand) = snapshot.cpu_brand() {
+                    let _ = writeln!(out, "  model {}", brand);
+                }
+                if let Some(load) = snapshot.load {
+                    let _ = write!(out, "  load {:.2} {:.2} {:.2}", load.one, load.five, load.fifteen);
+                    if let (Some(running), Some(blocked)) = (load.running, load.blocked) {
+                        let _ = write!(out, ", {} running, {} blocked", running, blocked);
+                    }
+                    let _ = writeln!(out);
+                }
+                if let Some(times) = snapshot.cpu_times() {
+                    let parts: Vec<String> = CpuTimes::NAMES.iter().zip(times.values())
+                        .map(|(name, value)| format!("{:.1}% {}", value, name))
+                        .collect();
+                    let _ = writeln!(out, "  time {}", parts.join(", "));
+                }
+                for (i, cpu) in snapshot.cpus.iter().enumerate() {
+                    let _ = write!(out, "  cpu {:<3} {:5.1}%", i, cpu.usage);
+                    if cpu.frequency > 0 {
+                        let _ = write!(out, " {:>9}", format_frequency(cpu.frequency));
+                    }
+                    let _ = writeln!(out);
+                }
+            }
+            Subsystem::Memory => {
+                let memory = &snapshot.memory;
+                let _ = writeln!(out, "memory: {} / {} used, {} available",
+                    units.memory(memory.used), units.memory(memory.total), units.memory(memory.available()));
+                let _ = writeln!(out, "  swap {} / {} used", units.memory(memory.used_swap), units.memory(memory.total_swap));
+            }
+            Subsystem::Networks => {
+                let _ = writeln!(out, "networks: {} interfaces", snapshot.networks.len());
+                for network in &snapshot.networks {
+                    let _ = writeln!(out, "  {:<16} received {:>12} transmitted {:>12}",
+                        network.interface, units.network(network.received), units.network(network.transmitted));
+                }
+            }
+            Subsystem::Processes => {
+                let mut processes: Vec<_> = snapshot.processes.iter().collect();
+                processes.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage));
+                let _ = writeln!(out, "processes: {} total", processes.len());
+                for process in processes.into_iter().take(REPORT_PROCESSES) {
+                    let _ = writeln!(out, "  {:>7} {:<32} {:5.1}% {:>12}",
+                        process.pid, process.name, process.cpu_usage, units.memory(process.memory));
+                }
+            }
+            Subsystem::Disks => {
+                let _ = writeln!(out, "disks: {}", snapshot.disks.len());
+                for disk in &snapshot.disks {
+                    let _ = writeln!(out, "  {:<16} {} / {} used ({})",
+                        disk.mount_point, units.memory(disk.used_space()), units.memory(disk.total_space), disk.kind);
+                }
+            }
+            Subsystem::Components => {
+                let _ = writeln!(out, "components: {} sensors", snapshot.components.len());
+                for component in &snapshot.components {
+                    let _ = writeln!(out, "  {:<24} {}", component.label, units.temperature(component.temperature));
+                }
+            }
+            Subsystem::Batteries => {
+                let _ = writeln!(out, "batteries: {}", snapshot.batteries.len());
+                for (i, battery) in snapshot.batteries.iter().enumerate() {
+                    let _ = writeln!(out, "  battery {} {:?} {:.1}%", i + 1, battery.state, battery.charge * 100.0);
+                }
+            }
+        }
+    }
+    out
+}
+
+// The firing alerts, appended to a report when rules are configured.
+pub fn report_alerts(alerts: &Alerts) -> String {
+    let mut out = String::new();
+    let firing: Vec<_> = alerts.firing().collect();
+    let _ = writeln!(out, "alerts: {} firing", firing.len());
+    for (rule, since) in firing {
+        let _ = writeln!(out, "  {:<24} {} since {}", rule.name, rule, format_timestamp(since));
+    }
+    out
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
+use crate::snapshot::*;
+use crate::error::{Error, Result};
+use crate::source::{DataSource, SysinfoSource};
+
+// Turns a `DataSource` (this machine's, unless given another one) into
+// `SystemSnapshot`s.
+//
+// Each call to `sample` only refreshes the subsystems the caller needs,
+// and no more often than that subsystem's cadence allows. Subsystems
+// nobody needs are still refreshed on the slower background interval
+// so the history keeps moving; set it to `None` to skip them entirely.
+pub struct Sampler {
+    source: Box<dyn DataSource>,
+    cadence: HashMap<Subsystem, Duration>,
+    background: Option<Duration>,
+    last_refresh: HashMap<Subsystem, Instant>,
+    current: SystemSnapshot,
+    last_cost: Duration,
+}
+
+pub const DEFAULT_BACKGROUND_INTERVAL: Duration = Duration::from_secs(5);
+
+pub fn default_cadence(subsystem: Subsystem) -> Duration {
+    match subsystem {
+        Subsystem::Cpu | Subsystem::Memory | Subsystem::Networks => Duration::ZERO,
+        Subsystem::Processes | Subsystem::Components => Duration::from_secs(1),
+        Subsystem::Disks => Duration::from_secs(2),
+        Subsystem::Batteries => Duration::from_secs(5),
+    }
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sampler {
+    pub fn new() -> Self {
+        Sampler::with_source(Box::new(SysinfoSource::new()))
+    }
+
+    pub fn with_source(source: Box<dyn DataSource>) -> Self {
+        Sampler {
+            source,
+            cadence: Subsystem::ALL.iter().map(|subsystem| (*subsystem, default_cadence(*subsystem))).collect(),
+            background: Some(DEFAULT_BACKGROUND_INTERVAL),
+            last_refresh: HashMap::new(),
+            current: SystemSnapshot::default(),
+            last_cost: Duration::ZERO,
+        }
+    }
+
+    // Minimum time between two refreshes of `subsystem` while it is needed.
+    pub fn cadence(&self, subsystem: Subsystem) -> Duration {
+        self.cadence.get(&subsystem).copied().unwrap_or_default()
+    }
+
+    pub fn set_cadence(&mut self, subsystem: Subsystem, cadence: Duration) {
+        self.cadence.insert(subsystem, cadence);
+    }
+
+    pub fn background_interval(&self) -> Option<Duration> {
+        self.background
+    }
+
+    pub fn set_background_interval(&mut self, interval: Option<Duration>) {
+        self.background = interval;
+    }
+
+    // Wall-clock time spent inside the last `sample` call.
+    pub fn last_cost(&self) -> Duration {
+        self.last_cost
+    }
+
+    pub fn sample(&mut self, needs: &[Subsystem]) -> SystemSnapshot {
+        let started = Instant::now();
+        self.source.begin_sample();
+        for subsystem in Subsystem::ALL {
+            let interval = if needs.contains(&subsystem) {
+                self.cadence(subsystem)
+            } else {
+                match self.background {
+                    Some(background) => background.max(self.cadence(subsystem)),
+                    None => continue,
+                }
+            };
+            let due = match self.last_refresh.get(&subsystem) {
+                Some(last) => started.duration_since(*last) >= interval,
+                None => true,
+            };
+            if due {
+                self.refresh(subsystem);
+                self.last_refresh.insert(subsystem, started);
+            }
+        }
+        self.current.timestamp = SystemTime::now();
+        self.last_cost = started.elapsed();
+        self.current.clone()
+    }
+
+    pub fn sample_all(&mut self) -> SystemSnapshot {
+        self.sample(&Subsystem::ALL)
+    }
+
+    fn refresh(&mut self, subsystem: Subsystem) {
+        let result = match subsystem {
+            Subsystem::Cpu => self.source.cpus()
+                .and_then(|cpus| non_empty(subsystem, cpus))
+                .map(|cpus| {
+                    self.current.cpus = cpus;
+                    self.current.load = self.source.load();
+                }),
+            Subsystem::Memory => self.source.memory().and_then(|memory| {
+                self.current.memory = memory;
+                if memory.total == 0 { Err(Error::NotFound(subsystem)) } else { Ok(()) }
+            }),
+            Subsystem::Networks => self.source.networks()
+                .and_then(|networks| non_empty(subsystem, networks))
+                .map(|networks| self.current.networks = networks),
+            Subsystem::Processes => self.source.processes()
+                .and_then(|processes| non_empty(subsystem, processes))
+                .map(|processes| self.current.processes = processes),
+            Subsystem::Disks => self.source.disks()
+                .and_then(|disks| non_empty(subsystem, disks))
+                .map(|disks| self.current.disks = disks),
+            Subsystem::Components => self.source.components()
+                .and_then(|components| non_empty(subsystem, components))
+                .map(|components| self.current.components = components),
+            Subsystem::Batteries => self.source.batteries()
+                .and_then(|batteries| non_empty(subsystem, batteries))
+                .map(|batteries| self.current.batteries = batteries),
+        };
+        match result {
+            Ok(()) => {
+                self.current.errors.remove(&subsystem);
+            }
+            Err(err) => {
+                self.clear(subsystem);
+                self.current.errors.insert(subsystem, err);
+            }
+        }
+        self.current.refreshed.insert(subsystem, SystemTime::now());
+    }
+
+    fn clear(&mut self, subsystem: Subsystem) {
+        match subsystem {
+            Subsystem::Cpu => {
+                self.current.cpus.clear();
+                self.current.load = None;
+            }
+            Subsystem::Memory => {}
+            Subsystem::Networks => self.current.networks.clear(),
+            Subsystem::Processes => self.current.processes.clear(),
+            Subsystem::Disks => self.current.disks.clear(),
+            Subsystem::Components => self.current.components.clear(),
+            Subsystem::Batteries => self.current.batteries.clear(),
+        }
+    }
+}
+
+fn non_empty<T>(subsystem: Subsystem, items: Vec<T>) -> Result<Vec<T>> {
+    if items.is_empty() {
+        Err(Error::NotFound(subsystem))
+    } else {
+        Ok(items)
+    }
+}
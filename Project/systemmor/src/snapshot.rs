@@ -0,0 +1,297 @@
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::error::Error;
+
+// The groups of data the sampler refreshes independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Subsystem {
+    Cpu,
+    Memory,
+    Networks,
+    Processes,
+    Disks,
+    Components,
+    Batteries,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 7] = [
+        Subsystem::Cpu,
+        Subsystem::Memory,
+        Subsystem::Networks,
+        Subsystem::Processes,
+        Subsystem::Disks,
+        Subsystem::Components,
+        Subsystem::Batteries,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Subsystem::Cpu => "cpu",
+            Subsystem::Memory => "memory",
+            Subsystem::Networks => "networks",
+            Subsystem::Processes => "processes",
+            Subsystem::Disks => "disks",
+            Subsystem::Components => "components",
+            Subsystem::Batteries => "batteries",
+        }
+    }
+
+    // Human readable name of the devices the subsystem reports on.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Subsystem::Cpu => "CPUs",
+            Subsystem::Memory => "memory",
+            Subsystem::Networks => "network interfaces",
+            Subsystem::Processes => "processes",
+            Subsystem::Disks => "disks",
+            Subsystem::Components => "temperature sensors",
+            Subsystem::Batteries => "batteries",
+        }
+    }
+}
+
+// An owned, point-in-time copy of everything the panels display.
+// Produced by `Sampler::sample` and safe to keep around after the
+// underlying `sysinfo::System` has been refreshed again.
+//
+// Subsystems are refreshed on their own cadence, so `refreshed` records
+// when each part was last read; a subsystem missing from it has never
+// been sampled and its fields are empty. A subsystem that could not be
+// read, or has nothing to report on this machine, has an entry in
+// `errors` and empty fields.
+#[derive(Debug, Clone)]
+pub struct SystemSnapshot {
+    pub timestamp: SystemTime,
+    pub refreshed: BTreeMap<Subsystem, SystemTime>,
+    pub cpus: Vec<CpuSample>,
+    // Read with the CPUs; `None` where the system has no load average.
+    pub load: Option<LoadSample>,
+    pub memory: MemorySample,
+    pub networks: Vec<NetworkSample>,
+    pub processes: Vec<ProcessSample>,
+    pub disks: Vec<DiskSample>,
+    pub components: Vec<ComponentSample>,
+    pub batteries: Vec<BatterySample>,
+    pub errors: BTreeMap<Subsystem, Error>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoadSample {
+    // 1, 5 and 15 minute load averages.
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+    // Tasks running or waiting for a core, tasks blocked on I/O, and all
+    // tasks; Linux only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub running: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tasks: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CpuSample {
+    pub name: String,
+    pub usage: f32,
+    // Current clock in MHz, 0 when the system does not report it.
+    #[serde(default)]
+    pub frequency: u64,
+    // Lowest and highest clock the core supports, in MHz, where cpufreq
+    // reports them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_frequency: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_frequency: Option<u64>,
+    // Model name, e.g. "AMD Ryzen 7 5800X 8-Core Processor", and vendor id.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub brand: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub vendor: String,
+    // Where the core's time went since the previous sample; Linux only,
+    // and not on the first sample.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub times: Option<CpuTimes>,
+}
+
+// Shares of a core's time in percent; the rest is idle. `user` includes
+// niced tasks, `irq` soft interrupts and `guest` niced guests. `iowait` is
+// idle time with I/O outstanding, which `usage` counts as idle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CpuTimes {
+    pub user: f32,
+    pub system: f32,
+    pub iowait: f32,
+    pub irq: f32,
+    pub steal: f32,
+    pub guest: f32,
+}
+
+impl CpuTimes {
+    pub const NAMES: [&'static str; 6] = ["user", "system", "iowait", "irq", "steal", "guest"];
+
+    // The shares in `NAMES` order.
+    pub fn values(&self) -> [f32; 6] {
+        [self.user, self.system, self.iowait, self.irq, self.steal, self.guest]
+    }
+
+    // The average of `times`, or `None` if there are none.
+    pub fn average<'a>(times: impl IntoIterator<Item = &'a CpuTimes>) -> Option<CpuTimes> {
+        let mut sum = [0.0; 6];
+        let mut count = 0;
+        for times in times {
+            for (sum, value) in sum.iter_mut().zip(times.values()) {
+                *sum += value;
+            }
+            count += 1;
+        }
+        let [user, system, iowait, irq, steal, guest] = sum.map(|sum| sum / count as f32);
+        (count > 0).then_some(CpuTimes { user, system, iowait, irq, steal, guest })
+    }
+}
+
+// All values in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MemorySample {
+    pub total: u64,
+    pub used: u64,
+    pub total_swap: u64,
+    pub used_swap: u64,
+}
+
+// `received`/`transmitted` are bytes since the previous refresh,
+// the `total_*` fields are bytes since the interface came up.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkSample {
+    pub interface: String,
+    pub received: u64,
+    pub transmitted: u64,
+    pub total_received: u64,
+    pub total_transmitted: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessSample {
+    pub pid: u32,
+    pub name: String,
+    pub memory: u64,
+    pub cpu_usage: f32,
+    pub disk_read: u64,
+    pub disk_written: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiskSample {
+    pub name: String,
+    pub kind: String,
+    pub mount_point: String,
+    pub total_space: u64,
+    pub available_space: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentSample {
+    pub label: String,
+    pub temperature: f32,
+    pub max: f32,
+    pub critical: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatteryState {
+    Unknown,
+    Charging,
+    Discharging,
+    Empty,
+    Full,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatterySample {
+    pub state: BatteryState,
+    // State of charge between 0.0 and 1.0.
+    pub charge: f32,
+}
+
+impl Default for SystemSnapshot {
+    fn default() -> Self {
+        SystemSnapshot {
+            timestamp: UNIX_EPOCH,
+            refreshed: BTreeMap::new(),
+            cpus: vec![],
+            load: None,
+            memory: MemorySample::default(),
+            networks: vec![],
+            processes: vec![],
+            disks: vec![],
+            components: vec![],
+            batteries: vec![],
+            errors: BTreeMap::new(),
+        }
+    }
+}
+
+impl SystemSnapshot {
+    pub fn refreshed_at(&self, subsystem: Subsystem) -> Option<SystemTime> {
+        self.refreshed.get(&subsystem).copied()
+    }
+
+    pub fn error(&self, subsystem: Subsystem) -> Option<&Error> {
+        self.errors.get(&subsystem)
+    }
+
+    pub fn cpu_average(&self) -> f32 {
+        if self.cpus.is_empty() {
+            return 0.0;
+        }
+        self.cpus.iter().map(|cpu| cpu.usage).sum::<f32>() / self.cpus.len() as f32
+    }
+
+    // Average and fastest clock in MHz of the cores that report one.
+    pub fn cpu_frequency(&self) -> Option<(f64, u64)> {
+        let known: Vec<u64> = self.cpus.iter().map(|cpu| cpu.frequency).filter(|frequency| *frequency > 0).collect();
+        let fastest = known.iter().copied().max()?;
+        Some((known.iter().sum::<u64>() as f64 / known.len() as f64, fastest))
+    }
+
+    // The time breakdown of all cores together.
+    pub fn cpu_times(&self) -> Option<CpuTimes> {
+        CpuTimes::average(self.cpus.iter().filter_map(|cpu| cpu.times.as_ref()))
+    }
+
+    // The model name, e.g. "Intel(R) Core(TM) i7-8565U CPU @ 1.80GHz".
+    pub fn cpu_brand(&self) -> Option<&str> {
+        self.cpus.iter().map(|cpu| cpu.brand.as_str()).find(|brand| !brand.is_empty())
+    }
+}
+
+impl MemorySample {
+    pub fn available(&self) -> u64 {
+        self.total.saturating_sub(self.used)
+    }
+
+    pub fn used_ratio(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.used as f64 / self.total as f64
+    }
+}
+
+impl DiskSample {
+    pub fn used_space(&self) -> u64 {
+        self.total_space.saturating_sub(self.available_space)
+    }
+
+    pub fn used_ratio(&self) -> f64 {
+        if self.total_space == 0 {
+            return 0.0;
+        }
+        self.used_space() as f64 / self.total_space as f64
+    }
+}
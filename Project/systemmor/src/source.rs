@@ -0,0 +1,202 @@
+use sysinfo::{System, SystemExt, CpuExt, CpuRefreshKind, NetworkExt, DiskExt, ComponentExt, ProcessExt, PidExt, NetworksExt};
+use crate::snapshot::*;
+use crate::error::{Error, Result};
+use crate::procfs::CpuTicks;
+
+// Where samples come from. Each method refreshes one subsystem and returns
+// its current values; the sampler decides when to call them and turns an
+// empty list into `Error::NotFound`.
+//
+// `begin_sample` is called once at the start of every sample, before the
+// subsystems that are due, for sources that fetch everything at once.
+pub trait DataSource {
+    fn begin_sample(&mut self) {}
+    fn cpus(&mut self) -> Result<Vec<CpuSample>>;
+    // Read right after `cpus`; most sources have no load average.
+    fn load(&mut self) -> Option<LoadSample> {
+        None
+    }
+    fn memory(&mut self) -> Result<MemorySample>;
+    fn networks(&mut self) -> Result<Vec<NetworkSample>>;
+    fn processes(&mut self) -> Result<Vec<ProcessSample>>;
+    fn disks(&mut self) -> Result<Vec<DiskSample>>;
+    fn components(&mut self) -> Result<Vec<ComponentSample>>;
+    fn batteries(&mut self) -> Result<Vec<BatterySample>>;
+}
+
+// The name of this machine, if the system has one.
+pub fn host_name() -> Option<String> {
+    System::new().host_name()
+}
+
+// This machine, read through sysinfo and the battery crate.
+pub struct SysinfoSource {
+    sys: System,
+    battery_manager: Result<battery::Manager>,
+    // (min, max) clock of each core; these never change, so they are only
+    // read once.
+    frequency_limits: Vec<(Option<u64>, Option<u64>)>,
+    // /proc/stat at the previous CPU refresh, for the time breakdown.
+    cpu_ticks: Vec<CpuTicks>,
+}
+
+impl Default for SysinfoSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SysinfoSource {
+    pub fn new() -> Self {
+        SysinfoSource {
+            sys: System::new(),
+            battery_manager: battery::Manager::new().map_err(Error::from),
+            frequency_limits: vec![],
+            cpu_ticks: vec![],
+        }
+    }
+}
+
+impl DataSource for SysinfoSource {
+    fn cpus(&mut self) -> Result<Vec<CpuSample>> {
+        self.sys.refresh_cpu_specifics(CpuRefreshKind::new().with_cpu_usage().with_frequency());
+        let cpus = self.sys.cpus();
+        if self.frequency_limits.len() != cpus.len() {
+            self.frequency_limits = (0..cpus.len()).map(|core| (cpufreq(core, "cpuinfo_min_freq"), cpufreq(core, "cpuinfo_max_freq"))).collect();
+        }
+        let times = cpu_times(&mut self.cpu_ticks, cpus.len());
+        Ok(cpus.iter().zip(&self.frequency_limits).zip(times).map(|((cpu, (min_frequency, max_frequency)), times)| CpuSample {
+            name: cpu.name().to_string(),
+            usage: cpu.cpu_usage(),
+            frequency: cpu.frequency(),
+            min_frequency: *min_frequency,
+            max_frequency: *max_frequency,
+            brand: cpu.brand().trim().to_string(),
+            vendor: cpu.vendor_id().to_string(),
+            times,
+        }).collect())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn load(&mut self) -> Option<LoadSample> {
+        crate::procfs::load()
+    }
+
+    // Windows has no load average; sysinfo reports zeros there.
+    #[cfg(not(target_os = "linux"))]
+    fn load(&mut self) -> Option<LoadSample> {
+        if cfg!(windows) {
+            return None;
+        }
+        let load = self.sys.load_average();
+        Some(LoadSample { one: load.one, five: load.five, fifteen: load.fifteen, ..LoadSample::default() })
+    }
+
+    fn memory(&mut self) -> Result<MemorySample> {
+        self.sys.refresh_memory();
+        Ok(MemorySample {
+            total: self.sys.total_memory(),
+            used: self.sys.used_memory(),
+            total_swap: self.sys.total_swap(),
+            used_swap: self.sys.used_swap(),
+        })
+    }
+
+    fn networks(&mut self) -> Result<Vec<NetworkSample>> {
+        self.sys.refresh_networks_list();
+        let mut networks: Vec<NetworkSample> = self.sys.networks().iter().map(|(name, network)| NetworkSample {
+            interface: name.clone(),
+            received: network.received(),
+            transmitted: network.transmitted(),
+            total_received: network.total_received(),
+            total_transmitted: network.total_transmitted(),
+        }).collect();
+        networks.sort_by(|a, b| a.interface.cmp(&b.interface));
+        Ok(networks)
+    }
+
+    fn processes(&mut self) -> Result<Vec<ProcessSample>> {
+        self.sys.refresh_processes();
+        let mut processes: Vec<ProcessSample> = self.sys.processes().iter().map(|(pid, process)| {
+            let disk = process.disk_usage();
+            ProcessSample {
+                pid: pid.as_u32(),
+                name: process.name().to_string(),
+                memory: process.memory(),
+                cpu_usage: process.cpu_usage(),
+                disk_read: disk.read_bytes,
+                disk_written: disk.written_bytes,
+            }
+        }).collect();
+        processes.sort_by_key(|process| process.pid);
+        Ok(processes)
+    }
+
+    fn disks(&mut self) -> Result<Vec<DiskSample>> {
+        self.sys.refresh_disks_list();
+        Ok(self.sys.disks().iter().map(|disk| DiskSample {
+            name: disk.name().to_string_lossy().into_owned(),
+            kind: format!("{:?}", disk.kind()),
+            mount_point: disk.mount_point().display().to_string(),
+            total_space: disk.total_space(),
+            available_space: disk.available_space(),
+        }).collect())
+    }
+
+    fn components(&mut self) -> Result<Vec<ComponentSample>> {
+        self.sys.refresh_components_list();
+        Ok(self.sys.components().iter().map(|component| ComponentSample {
+            label: component.label().to_string(),
+            temperature: component.temperature(),
+            max: component.max(),
+            critical: component.critical(),
+        }).collect())
+    }
+
+    fn batteries(&mut self) -> Result<Vec<BatterySample>> {
+        let manager = self.battery_manager.as_ref().map_err(Clone::clone)?;
+        let batteries = manager.batteries()?.collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(batteries.into_iter().map(|battery| BatterySample {
+            state: match battery.state() {
+                battery::State::Charging => BatteryState::Charging,
+                battery::State::Discharging => BatteryState::Discharging,
+                battery::State::Empty => BatteryState::Empty,
+                battery::State::Full => BatteryState::Full,
+                _ => BatteryState::Unknown,
+            },
+            charge: battery.state_of_charge().value,
+        }).collect())
+    }
+}
+
+// A cpufreq value of core `core` in MHz (sysfs reports kHz).
+#[cfg(target_os = "linux")]
+fn cpufreq(core: usize, file: &str) -> Option<u64> {
+    let path = format!("/sys/devices/system/cpu/cpu{}/cpufreq/{}", core, file);
+    let khz: u64 = std::fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    Some(khz / 1000).filter(|mhz| *mhz > 0)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpufreq(_core: usize, _file: &str) -> Option<u64> {
+    None
+}
+
+// Each core's time breakdown since the previous call, when /proc/stat lists
+// the same cores as sysinfo.
+#[cfg(target_os = "linux")]
+fn cpu_times(previous: &mut Vec<CpuTicks>, cores: usize) -> Vec<Option<CpuTimes>> {
+    let current = crate::procfs::cpu_ticks().unwrap_or_default();
+    let times = if current.len() == cores && previous.len() == cores {
+        current.iter().zip(previous.iter()).map(|(current, previous)| current.times_since(previous)).collect()
+    } else {
+        vec![None; cores]
+    };
+    *previous = current;
+    times
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_times(_previous: &mut Vec<CpuTicks>, cores: usize) -> Vec<Option<CpuTimes>> {
+    vec![None; cores]
+}
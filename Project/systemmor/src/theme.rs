@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+use crate::config::deserialize_optional_color;
+use crate::error::{Error, Result};
+
+pub const DEFAULT_THEME: &str = "dark";
+
+// Styles for every part of the UI that has one. Panels take these from the
+// active theme instead of naming colours themselves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub text: Style,
+    pub title: Style,
+    pub highlight: Style,
+    pub key: Style,
+    pub gauge: Style,
+    pub battery: Style,
+    pub bar: Style,
+    pub chart: Style,
+    pub axis: Style,
+    pub error: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    // The original colours, for dark terminal backgrounds.
+    pub fn dark() -> Self {
+        Theme {
+            name: "dark".to_string(),
+            text: Style::default().fg(Color::White),
+            title: Style::default().fg(Color::Yellow),
+            highlight: Style::default().fg(Color::Yellow),
+            key: Style::default().fg(Color::LightYellow),
+            gauge: Style::default().fg(Color::Magenta),
+            battery: Style::default().fg(Color::Green),
+            bar: Style::default().fg(Color::LightBlue),
+            chart: Style::default().fg(Color::Yellow),
+            axis: Style::default().fg(Color::Red),
+            error: Style::default().fg(Color::LightRed),
+        }
+    }
+
+    // Dark colours only, so nothing washes out on a white background.
+    pub fn light() -> Self {
+        Theme {
+            name: "light".to_string(),
+            text: Style::default().fg(Color::Black),
+            title: Style::default().fg(Color::Blue),
+            highlight: Style::default().fg(Color::Blue),
+            key: Style::default().fg(Color::Magenta),
+            gauge: Style::default().fg(Color::Magenta),
+            battery: Style::default().fg(Color::Green),
+            bar: Style::default().fg(Color::Blue),
+            chart: Style::default().fg(Color::Blue),
+            axis: Style::default().fg(Color::Red),
+            error: Style::default().fg(Color::Red),
+        }
+    }
+
+    // Bright colours on black, with bold where colour alone carries meaning.
+    pub fn high_contrast() -> Self {
+        Theme {
+            name: "high-contrast".to_string(),
+            text: Style::default().fg(Color::White).bg(Color::Black),
+            title: Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            highlight: Style::default().fg(Color::Black).bg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            key: Style::default().fg(Color::LightCyan).add_modifier(Modifier::BOLD),
+            gauge: Style::default().fg(Color::LightCyan),
+            battery: Style::default().fg(Color::LightGreen),
+            bar: Style::default().fg(Color::LightCyan),
+            chart: Style::default().fg(Color::LightYellow),
+            axis: Style::default().fg(Color::White),
+            error: Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD),
+        }
+    }
+
+    pub fn builtin() -> Vec<Theme> {
+        vec![Theme::dark(), Theme::light(), Theme::high_contrast()]
+    }
+
+    // The same theme with every colour removed, for `NO_COLOR`. Highlights
+    // and keys fall back to modifiers so they still stand out.
+    pub fn without_colors(&self) -> Self {
+        let plain = |style: Style| Style { fg: None, bg: None, underline_color: None, ..style };
+        Theme {
+            name: self.name.clone(),
+            text: plain(self.text),
+            title: plain(self.title).add_modifier(Modifier::BOLD),
+            highlight: plain(self.highlight).add_modifier(Modifier::REVERSED),
+            key: plain(self.key).add_modifier(Modifier::BOLD),
+            gauge: plain(self.gauge),
+            battery: plain(self.battery),
+            bar: plain(self.bar),
+            chart: plain(self.chart),
+            axis: plain(self.axis),
+            error: plain(self.error).add_modifier(Modifier::BOLD),
+        }
+    }
+
+    fn apply(&mut self, config: &ThemeConfig) {
+        let overrides = [
+            (&mut self.text, config.text),
+            (&mut self.title, config.title),
+            (&mut self.highlight, config.highlight),
+            (&mut self.key, config.key),
+            (&mut self.gauge, config.gauge),
+            (&mut self.battery, config.battery),
+            (&mut self.bar, config.bar),
+            (&mut self.chart, config.chart),
+            (&mut self.axis, config.axis),
+            (&mut self.error, config.error),
+        ];
+        for (style, color) in overrides {
+            if let Some(color) = color {
+                *style = style.fg(color);
+            }
+        }
+        if let Some(background) = config.background {
+            self.text = self.text.bg(background);
+        }
+    }
+}
+
+// A `[themes.NAME]` table: a built-in theme to start from and the colours
+// to change. Colours accept names ("lightblue"), 256-colour indexes ("208")
+// and "#rrggbb" values.
+//
+//     [themes.solarized]
+//     base = "dark"
+//     text = "#839496"
+//     background = "#002b36"
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub base: Option<String>,
+    #[serde(deserialize_with = "deserialize_optional_color")]
+    pub background: Option<Color>,
+    #[serde(deserialize_with = "deserialize_optional_color")]
+    pub text: Option<Color>,
+    #[serde(deserialize_with = "deserialize_optional_color")]
+    pub title: Option<Color>,
+    #[serde(deserialize_with = "deserialize_optional_color")]
+    pub highlight: Option<Color>,
+    #[serde(deserialize_with = "deserialize_optional_color")]
+    pub key: Option<Color>,
+    #[serde(deserialize_with = "deserialize_optional_color")]
+    pub gauge: Option<Color>,
+    #[serde(deserialize_with = "deserialize_optional_color")]
+    pub battery: Option<Color>,
+    #[serde(deserialize_with = "deserialize_optional_color")]
+    pub bar: Option<Color>,
+    #[serde(deserialize_with = "deserialize_optional_color")]
+    pub chart: Option<Color>,
+    #[serde(deserialize_with = "deserialize_optional_color")]
+    pub axis: Option<Color>,
+    #[serde(deserialize_with = "deserialize_optional_color")]
+    pub error: Option<Color>,
+}
+
+// The themes that can be switched between at runtime: the built-in ones
+// followed by the user's, in name order.
+#[derive(Debug, Clone)]
+pub struct Themes {
+    themes: Vec<Theme>,
+    active: usize,
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        Themes {
+            themes: Theme::builtin(),
+            active: 0,
+        }
+    }
+}
+
+impl Themes {
+    // A user theme named like a built-in one replaces it. With `no_color`
+    // set every theme is stripped of its colours.
+    pub fn new(active: Option<&str>, config: &BTreeMap<String, ThemeConfig>, no_color: bool) -> Result<Self> {
+        let mut themes = Themes::default();
+        for (name, theme_config) in config {
+            let base = theme_config.base.as_deref().unwrap_or(DEFAULT_THEME);
+            let mut theme = Theme::builtin().into_iter().find(|theme| theme.name == base).ok_or_else(|| {
+                Error::Config(format!("unknown base theme '{}' for theme '{}' (available: {})", base, name, builtin_names()))
+            })?;
+            theme.name = name.clone();
+            theme.apply(theme_config);
+            match themes.themes.iter().position(|existing| existing.name == *name) {
+                Some(index) => themes.themes[index] = theme,
+                None => themes.themes.push(theme),
+            }
+        }
+        if no_color {
+            themes.themes = themes.themes.iter().map(Theme::without_colors).collect();
+        }
+        if let Some(name) = active {
+            if !themes.select(name) {
+                let known: Vec<&str> = themes.names().collect();
+                return Err(Error::Config(format!("unknown theme '{}' (available: {})", name, known.join(", "))));
+            }
+        }
+        Ok(themes)
+    }
+
+    pub fn active(&self) -> &Theme {
+        &self.themes[self.active]
+    }
+
+    pub fn select(&mut self, name: &str) -> bool {
+        match self.themes.iter().position(|theme| theme.name == name) {
+            Some(index) => {
+                self.active = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Switches to the next theme, wrapping around.
+    pub fn cycle(&mut self) -> &Theme {
+        self.active = (self.active + 1) % self.themes.len();
+        self.active()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.themes.iter().map(|theme| theme.name.as_str())
+    }
+}
+
+// `NO_COLOR` disables colours when it is set to anything but the empty
+// string, see https://no-color.org.
+pub fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+fn builtin_names() -> String {
+    Themes::default().names().collect::<Vec<_>>().join(", ")
+}
@@ -0,0 +1,88 @@
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryUnit {
+    // KiB, MiB, GiB (powers of 1024)
+    #[default]
+    Binary,
+    // kB, MB, GB (powers of 1000)
+    Decimal,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkUnit {
+    #[default]
+    Bits,
+    Bytes,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+// How sizes, traffic and temperatures are written on screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Units {
+    pub memory: MemoryUnit,
+    pub network: NetworkUnit,
+    pub temperature: TemperatureUnit,
+}
+
+impl Units {
+    // Formats a size in bytes, scaled to the largest unit below it.
+    pub fn memory(&self, bytes: u64) -> String {
+        let (base, names) = match self.memory {
+            MemoryUnit::Binary => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB"]),
+            MemoryUnit::Decimal => (1000.0, ["B", "kB", "MB", "GB", "TB"]),
+        };
+        scale(bytes as f64, base, &names)
+    }
+
+    // Formats an amount of network traffic given in bytes.
+    pub fn network(&self, bytes: u64) -> String {
+        match self.network {
+            NetworkUnit::Bits => scale(bytes as f64 * 8.0, 1000.0, &["b", "kb", "Mb", "Gb", "Tb"]),
+            NetworkUnit::Bytes => scale(bytes as f64, 1000.0, &["B", "kB", "MB", "GB", "TB"]),
+        }
+    }
+
+    pub fn temperature(&self, celsius: f32) -> String {
+        match self.temperature {
+            TemperatureUnit::Celsius => format!("{:.1}°C", celsius),
+            TemperatureUnit::Fahrenheit => format!("{:.1}°F", celsius * 9.0 / 5.0 + 32.0),
+        }
+    }
+
+    // Converts a Celsius reading into the configured scale, for charts.
+    pub fn temperature_value(&self, celsius: f32) -> f32 {
+        match self.temperature {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+}
+
+fn scale(mut value: f64, base: f64, names: &[&str]) -> String {
+    let mut unit = 0;
+    while value >= base && unit < names.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0} {}", value, names[unit])
+    } else {
+        format!("{:.2} {}", value, names[unit])
+    }
+}
+
+// Formats a clock given in MHz, e.g. "800 MHz" or "3.60 GHz".
+pub fn format_frequency(mhz: u64) -> String {
+    scale(mhz as f64, 1000.0, &["MHz", "GHz"])
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+use common::sample;
+use systemmor::agent::{Agent, Client, Endpoint, Hello, RemoteSource, Response, MAX_REQUEST_LEN, PROTOCOL_VERSION};
+use systemmor::exporter::SharedSnapshot;
+use systemmor::fake::FakeData;
+use systemmor::json::Document;
+use systemmor::snapshot::{BatteryState, CpuSample};
+use systemmor::{Error, Sampler, Subsystem};
+
+fn agent(data: FakeData) -> Agent {
+    let shared = SharedSnapshot::default();
+    *shared.lock().unwrap() = sample(data);
+    Agent::start(&"127.0.0.1:0".parse().unwrap(), shared).unwrap()
+}
+
+#[test]
+fn remote_machine_samples_like_a_local_one() {
+    let data = FakeData::default().with_cpus(128, 12.5).with_disks(0).with_battery(BatteryState::Full, 1.0);
+    let agent = agent(data.clone());
+    let source = RemoteSource::connect(agent.endpoint()).unwrap();
+    assert_eq!(source.hello().protocol, PROTOCOL_VERSION);
+
+    let remote = Sampler::with_source(Box::new(source)).sample_all();
+    let local = sample(data);
+    assert_eq!(remote.cpus, local.cpus);
+    assert_eq!(remote.load, local.load);
+    assert_eq!(remote.memory, local.memory);
+    assert_eq!(remote.processes, local.processes);
+    assert_eq!(remote.batteries, local.batteries);
+    assert_eq!(remote.error(Subsystem::Disks).map(Error::to_string), Some("no disks found".to_string()));
+}
+
+#[test]
+fn a_silent_agent_does_not_hold_up_sampling() {
+    // Answers the first request, then reads the others without answering.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let hello = Hello { protocol: PROTOCOL_VERSION, agent: "test".to_string(), hostname: "silent".to_string() };
+        writeln!(writer, "{}", serde_json::to_string(&hello).unwrap()).unwrap();
+        let mut lines = BufReader::new(stream).lines();
+        lines.next();
+        let snapshot = sample(FakeData::default().with_cpus(2, 75.0));
+        let response = Response::Snapshot(Box::new(Document::new(&snapshot, &Subsystem::ALL)));
+        writeln!(writer, "{}", serde_json::to_string(&response).unwrap()).unwrap();
+        let _ = lines.count();
+    });
+
+    let source = RemoteSource::connect(&address.parse().unwrap()).unwrap();
+    assert_eq!(source.hello().hostname, "silent");
+    let mut sampler = Sampler::with_source(Box::new(source));
+    let started = Instant::now();
+    for _ in 0..3 {
+        // The latest snapshot, while the next request is still waiting.
+        assert_eq!(sampler.sample_all().cpu_average(), 75.0);
+    }
+    assert!(started.elapsed() < Duration::from_secs(1), "{:?}", started.elapsed());
+}
+
+#[test]
+fn protocol_is_newline_delimited_json() {
+    let agent = agent(FakeData::default());
+    let Endpoint::Tcp(address) = agent.endpoint() else { panic!("not tcp") };
+    let stream = TcpStream::connect(address).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = stream;
+    let mut line = String::new();
+
+    reader.read_line(&mut line).unwrap();
+    let hello: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(hello["protocol"], 1);
+
+    writer.write_all(b"{\"request\":\"snapshot\"}\n").unwrap();
+    line.clear();
+    reader.read_line(&mut line).unwrap();
+    let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(response["snapshot"]["version"], 1);
+    assert_eq!(response["snapshot"]["cpus"].as_array().unwrap().len(), 4);
+
+    writer.write_all(b"{\"request\":\"shutdown\"}\n").unwrap();
+    line.clear();
+    reader.read_line(&mut line).unwrap();
+    let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert!(response["error"].as_str().unwrap().starts_with("invalid request"), "{}", line);
+}
+
+#[test]
+fn oversized_requests_are_refused() {
+    let agent = agent(FakeData::default());
+    let Endpoint::Tcp(address) = agent.endpoint() else { panic!("not tcp") };
+    let stream = TcpStream::connect(address).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = stream;
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+
+    // No newline, so only the limit ends the request.
+    writer.write_all(&vec![b'x'; MAX_REQUEST_LEN as usize + 100]).unwrap();
+    line.clear();
+    reader.read_line(&mut line).unwrap();
+    let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(response["error"], format!("request longer than {} bytes", MAX_REQUEST_LEN));
+    line.clear();
+    // The agent hangs up, possibly before reading the rest.
+    assert!(reader.read_line(&mut line).map_or(true, |read| read == 0));
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_sockets() {
+    let path = std::env::temp_dir().join(format!("systemmor-test-{}.sock", std::process::id()));
+    let endpoint = Endpoint::Unix(path.clone());
+    let shared = SharedSnapshot::default();
+    *shared.lock().unwrap() = sample(FakeData::default().with_cpus(2, 50.0));
+    let _agent = Agent::start(&endpoint, shared.clone()).unwrap();
+
+    assert!(Agent::start(&endpoint, shared).is_err(), "a second agent took over the socket");
+    let snapshot = Client::connect(&endpoint).unwrap().snapshot().unwrap();
+    assert_eq!(snapshot.cpu_average(), 50.0);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn unreachable_agents_are_reported() {
+    // Bind and drop to find a port nobody listens on.
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let err = RemoteSource::connect(&format!("127.0.0.1:{}", port).parse().unwrap()).err().unwrap();
+    assert!(err.to_string().starts_with("remote agent: cannot reach"), "{}", err);
+}
+
+#[test]
+fn addresses() {
+    assert_eq!("lab1:9185".parse(), Ok(Endpoint::Tcp("lab1:9185".to_string())));
+    assert_eq!("[::1]:9185".parse(), Ok(Endpoint::Tcp("[::1]:9185".to_string())));
+    assert!("lab1".parse::<Endpoint>().is_err());
+    assert!("lab1:http".parse::<Endpoint>().is_err());
+    #[cfg(unix)]
+    assert_eq!("unix:/run/systemmor.sock".parse(), Ok(Endpoint::Unix("/run/systemmor.sock".into())));
+}
+
+#[test]
+fn cores_from_agents_without_clocks() {
+    let cpu: CpuSample = serde_json::from_str(r#"{"name": "cpu0", "usage": 12.5}"#).unwrap();
+    assert_eq!((cpu.frequency, cpu.max_frequency, cpu.brand.as_str()), (0, None, ""));
+    let json = serde_json::to_string(&cpu).unwrap();
+    assert!(!json.contains("max_frequency") && !json.contains("brand"), "{}", json);
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use std::time::{Duration, UNIX_EPOCH};
+use common::sample;
+use systemmor::alerts::{AlertChange, AlertConfig, AlertState};
+use systemmor::fake::FakeData;
+use systemmor::snapshot::{BatterySample, BatteryState};
+use systemmor::{Alerts, Error, Subsystem, SystemSnapshot};
+
+fn rule(name: &str, when: &str, hold: u64) -> AlertConfig {
+    AlertConfig { name: name.to_string(), when: when.to_string(), hold: Duration::from_secs(hold) }
+}
+
+fn at(seconds: u64, data: FakeData) -> SystemSnapshot {
+    let mut snapshot = sample(data);
+    snapshot.timestamp = UNIX_EPOCH + Duration::from_secs(seconds);
+    snapshot
+}
+
+fn state(alerts: &Alerts, name: &str) -> AlertState {
+    alerts.rules().find(|(rule, _)| rule.name == name).map(|(_, state)| *state).unwrap()
+}
+
+#[test]
+fn alert_fires_after_the_condition_holds_long_enough() {
+    let mut alerts = Alerts::new(&[rule("busy", "cpu > 90%", 30)]).unwrap();
+    let busy = || FakeData::default().with_cpus(4, 95.0);
+
+    assert!(alerts.evaluate(&at(100, busy())).is_empty());
+    assert_eq!(state(&alerts, "busy"), AlertState::Pending(UNIX_EPOCH + Duration::from_secs(100)));
+    assert!(alerts.evaluate(&at(120, busy())).is_empty());
+    assert_eq!(alerts.evaluate(&at(130, busy())), vec![AlertChange::Started("busy".to_string())]);
+    assert_eq!(alerts.evaluate(&at(140, FakeData::default())), vec![AlertChange::Ended("busy".to_string())]);
+
+    let events: Vec<_> = alerts.events().collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].started, UNIX_EPOCH + Duration::from_secs(100));
+    assert_eq!(events[0].ended, Some(UNIX_EPOCH + Duration::from_secs(140)));
+}
+
+#[test]
+fn a_dip_restarts_the_hold() {
+    let mut alerts = Alerts::new(&[rule("busy", "cpu > 90", 30)]).unwrap();
+    alerts.evaluate(&at(0, FakeData::default().with_cpus(1, 95.0)));
+    alerts.evaluate(&at(20, FakeData::default()));
+    alerts.evaluate(&at(30, FakeData::default().with_cpus(1, 95.0)));
+    assert!(alerts.evaluate(&at(50, FakeData::default().with_cpus(1, 95.0))).is_empty());
+    assert_eq!(alerts.firing().count(), 0);
+}
+
+#[test]
+fn per_device_metrics_match_any_device() {
+    let mut alerts = Alerts::new(&[
+        rule("core", "cpu.core >= 100", 0),
+        rule("hot", "temperature > critical", 0),
+        rule("disk", "disk > 70%", 0),
+        rule("memory", "memory > 95%", 0),
+    ]).unwrap();
+    let mut data = FakeData::default().with_cpus(8, 10.0);
+    if let Ok(cpus) = &mut data.cpus {
+        cpus[7].usage = 100.0;
+    }
+    if let Ok(components) = &mut data.components {
+        components[1].temperature = 101.0;
+    }
+    alerts.evaluate(&at(0, data));
+    let firing: Vec<&str> = alerts.firing().map(|(rule, _)| rule.name.as_str()).collect();
+    assert_eq!(firing, vec!["core", "hot", "disk"]);
+}
+
+#[test]
+fn battery_low_and_discharging() {
+    let mut alerts = Alerts::new(&[rule("battery", "battery < 10% and battery.state == discharging", 0)]).unwrap();
+    alerts.evaluate(&at(0, FakeData::default().with_battery(BatteryState::Charging, 0.05)));
+    assert_eq!(alerts.firing().count(), 0);
+    alerts.evaluate(&at(1, FakeData::default().with_battery(BatteryState::Discharging, 0.05)));
+    assert_eq!(alerts.firing().count(), 1);
+    alerts.evaluate(&at(2, FakeData::default().without_batteries()));
+    assert_eq!(alerts.firing().count(), 0);
+}
+
+#[test]
+fn comparisons_of_the_same_devices_must_match_one_device() {
+    let mut alerts = Alerts::new(&[
+        rule("battery", "battery < 10% and battery.state == discharging", 0),
+        rule("mixed", "battery < 10% and memory > 40%", 0),
+    ]).unwrap();
+    let two = |first: (BatteryState, f32), second: (BatteryState, f32)| FakeData {
+        batteries: Ok(vec![
+            BatterySample { state: first.0, charge: first.1 },
+            BatterySample { state: second.0, charge: second.1 },
+        ]),
+        ..FakeData::default()
+    };
+    // One battery is low, the other is discharging.
+    alerts.evaluate(&at(0, two((BatteryState::Charging, 0.05), (BatteryState::Discharging, 0.9))));
+    let firing: Vec<&str> = alerts.firing().map(|(rule, _)| rule.name.as_str()).collect();
+    assert_eq!(firing, vec!["mixed"]);
+    alerts.evaluate(&at(1, two((BatteryState::Charging, 0.9), (BatteryState::Discharging, 0.05))));
+    let firing: Vec<&str> = alerts.firing().map(|(rule, _)| rule.name.as_str()).collect();
+    assert_eq!(firing, vec!["battery", "mixed"]);
+}
+
+#[test]
+fn unavailable_data_does_not_fire() {
+    let mut alerts = Alerts::new(&[rule("hot", "temperature > 0", 0)]).unwrap();
+    let data = FakeData { components: Err(Error::NotFound(Subsystem::Components)), ..FakeData::default() };
+    alerts.evaluate(&at(0, data));
+    assert_eq!(alerts.firing().count(), 0);
+    assert_eq!(alerts.needs(), vec![Subsystem::Components]);
+}
+
+#[test]
+fn going_back_in_time_resets_alerts() {
+    let mut alerts = Alerts::new(&[rule("busy", "cpu > 90", 0)]).unwrap();
+    alerts.evaluate(&at(100, FakeData::default().with_cpus(1, 95.0)));
+    assert_eq!(alerts.events().count(), 1);
+    alerts.evaluate(&at(50, FakeData::default()));
+    assert_eq!(alerts.events().count(), 0);
+    assert_eq!(state(&alerts, "busy"), AlertState::Ok);
+}
+
+#[test]
+fn invalid_rules_are_config_errors() {
+    let cases = [
+        ("cpux > 1", "unknown metric 'cpux'"),
+        ("cpu > lots", "expected a number after '>'"),
+        ("memory > critical", "expected a number after '>'"),
+        ("battery.state < full", "can only be compared with == or !="),
+        ("battery.state == flat", "unknown battery state 'flat'"),
+        ("cpu is high", "expected a comparison"),
+    ];
+    for (when, expected) in cases {
+        let err = Alerts::new(&[rule("x", when, 0)]).unwrap_err().to_string();
+        assert!(err.contains(expected), "{}: {}", when, err);
+    }
+    let err = Alerts::new(&[rule("x", "cpu > 1", 0), rule("x", "cpu > 2", 0)]).unwrap_err();
+    assert_eq!(err, Error::Config("alert 'x' is defined more than once".to_string()));
+}
+
+#[test]
+fn rules_load_from_the_config_file() {
+    let config: systemmor::Config = "[[alerts]]\nname = \"busy\"\nwhen = \"cpu >= 90\"\nfor = \"1m\"\n".parse().unwrap();
+    let alerts = Alerts::new(&config.alerts).unwrap();
+    let (rule, _) = alerts.rules().next().unwrap();
+    assert_eq!(rule.hold, Duration::from_secs(60));
+    assert_eq!(rule.to_string(), "cpu >= 90 for 60 s");
+}
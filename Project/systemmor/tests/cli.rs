@@ -0,0 +1,62 @@
+use std::time::Duration;
+use clap::error::ErrorKind;
+use clap::Parser;
+use ratatui::backend::TestBackend;
+use systemmor::cli::{Cli, Format, Mode};
+use systemmor::PanelRegistry;
+
+fn parse(args: &[&str]) -> Cli {
+    Cli::try_parse_from([&["systemmor"], args].concat()).unwrap()
+}
+
+fn error(args: &[&str]) -> ErrorKind {
+    Cli::try_parse_from([&["systemmor"], args].concat()).unwrap_err().kind()
+}
+
+#[test]
+fn no_arguments_start_the_interface() {
+    let cli = parse(&[]);
+    assert!(cli.panel.is_none() && cli.interval.is_none());
+    assert!(matches!(cli.mode(), Mode::Interface));
+}
+
+#[test]
+fn panel_and_interval() {
+    let cli = parse(&["--panel", "memory", "-i", "2s"]);
+    assert_eq!(cli.panel.as_deref(), Some("memory"));
+    assert_eq!(cli.interval, Some(Duration::from_secs(2)));
+    assert_eq!(error(&["--panel"]), ErrorKind::InvalidValue);
+    assert_eq!(error(&["--interval", "0"]), ErrorKind::ValueValidation);
+}
+
+#[test]
+fn unknown_panels_are_not_selected() {
+    let cli = parse(&["--panel", "gpu"]);
+    let mut panels = PanelRegistry::<TestBackend>::default();
+    assert!(!panels.select_name(cli.panel.as_deref().unwrap()));
+    assert!(panels.select_name("memory"));
+}
+
+#[test]
+fn once_and_count_print_samples() {
+    assert!(matches!(parse(&["--once"]).mode(), Mode::Print { format: Format::Text, count: Some(1) }));
+    assert!(matches!(parse(&["-n", "3", "-f", "ndjson"]).mode(), Mode::Print { format: Format::Ndjson, count: Some(3) }));
+    assert!(matches!(parse(&["--format", "ndjson"]).mode(), Mode::Print { format: Format::Ndjson, count: None }));
+    assert!(matches!(parse(&["--format", "json"]).mode(), Mode::Print { format: Format::Json, count: Some(1) }));
+    assert_eq!(error(&["--once", "--count", "2"]), ErrorKind::ArgumentConflict);
+    assert_eq!(error(&["--once", "--listen", "127.0.0.1:9184"]), ErrorKind::ArgumentConflict);
+}
+
+#[test]
+fn count_must_be_positive() {
+    assert_eq!(error(&["--count", "0"]), ErrorKind::ValueValidation);
+    assert_eq!(error(&["--count", "many"]), ErrorKind::ValueValidation);
+}
+
+#[test]
+fn agent_subcommand() {
+    let cli = parse(&["agent", "unix:/run/systemmor.sock", "-i", "1s"]);
+    assert!(matches!(cli.mode(), Mode::Agent));
+    assert_eq!(cli.interval, Some(Duration::from_secs(1)));
+    assert_eq!(error(&["--once", "agent"]), ErrorKind::ArgumentConflict);
+}
@@ -0,0 +1,149 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use common::render;
+use ratatui::backend::TestBackend;
+use systemmor::command::{parse_output, run, CommandConfig, CommandState, OutputFormat, DEFAULT_COMMAND_INTERVAL};
+use systemmor::panel::{CommandPanel, Panel, PanelRegistry};
+use systemmor::{App, Error, SystemSnapshot};
+
+fn command(toml: &str) -> CommandConfig {
+    toml::from_str(toml).unwrap()
+}
+
+fn values(pairs: &[(&str, f64)]) -> Vec<(String, f64)> {
+    pairs.iter().map(|(key, value)| (key.to_string(), *value)).collect()
+}
+
+// Starts `panel` and renders it once its command has run.
+fn render_started(panel: &mut CommandPanel) -> String {
+    Panel::<TestBackend>::start(panel);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while panel.state().is_some_and(|state| state.updated.is_none() && state.error.is_none()) && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(20));
+    }
+    render(panel, &SystemSnapshot::default(), &mut App::default(), 120, 20)
+}
+
+#[test]
+fn output_formats() {
+    assert_eq!(parse_output(" 42\n", OutputFormat::Number, "queue"), Ok(values(&[("queue", 42.0)])));
+    assert_eq!(
+        parse_output("# slots\nfree = 3\n\nused=5.5\n", OutputFormat::KeyValue, "farm"),
+        Ok(values(&[("free", 3.0), ("used", 5.5)])),
+    );
+    assert_eq!(
+        parse_output(r#"{"licenses": {"used": 7, "total": 10}, "pools": [1, 2], "owner": "ops"}"#, OutputFormat::Json, "x"),
+        Ok(values(&[("licenses.total", 10.0), ("licenses.used", 7.0), ("pools.0", 1.0), ("pools.1", 2.0)])),
+    );
+    assert_eq!(parse_output("12", OutputFormat::Json, "queue"), Ok(values(&[("queue", 12.0)])));
+}
+
+#[test]
+fn unreadable_output_is_an_error() {
+    let error = |output: &str, format: OutputFormat| parse_output(output, format, "x").unwrap_err().to_string();
+    assert_eq!(error("many", OutputFormat::Number), "command printed unreadable output: 'many' is not a number");
+    assert_eq!(error("a=1\nb", OutputFormat::KeyValue), "command printed unreadable output: line 2: expected key=value, got 'b'");
+    assert_eq!(error("a=lots", OutputFormat::KeyValue), "command printed unreadable output: line 1: 'lots' is not a number");
+    assert_eq!(error(r#"{"name": "ops"}"#, OutputFormat::Json), "command printed unreadable output: no values found");
+    assert_eq!(error("", OutputFormat::KeyValue), "command printed unreadable output: no values found");
+}
+
+#[test]
+fn commands_run_in_a_shell() {
+    assert_eq!(run("echo 42", Duration::from_secs(5)), Ok("42\n".to_string()));
+    assert_eq!(
+        run("echo partial; echo 'no such queue' >&2; exit 3", Duration::from_secs(5)),
+        Err(Error::Command("exited with status 3: no such queue".to_string())),
+    );
+
+    let started = Instant::now();
+    assert_eq!(run("sleep 10", Duration::from_millis(200)), Err(Error::Command("timed out after 200 ms".to_string())));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn failed_runs_keep_the_last_values() {
+    let mut state = CommandState::default();
+    let now = SystemTime::now();
+    state.update(Ok(values(&[("queue", 4.0)])), now);
+    state.update(Err(Error::Command("timed out after 2 s".to_string())), now + Duration::from_secs(5));
+    assert_eq!(state.values, values(&[("queue", 4.0)]));
+    assert_eq!(state.updated, Some(now));
+    assert_eq!(state.history["queue"].len(), 1);
+    assert!(state.error.is_some());
+
+    state.update(Ok(values(&[("queue", 6.0)])), now + Duration::from_secs(10));
+    assert_eq!(state.history["queue"].latest(), Some(6.0));
+    assert_eq!(state.error, None);
+}
+
+#[test]
+fn command_panels_are_registered_after_the_built_in_ones() {
+    let queue = command("name = \"Queue\"\ncommand = \"echo 1\"");
+    assert_eq!(queue.interval, DEFAULT_COMMAND_INTERVAL);
+    let slots = command("name = \"Slots\"\ncommand = \"echo 2\"\nkey = \"s\"\nwidget = \"line\"");
+    let licenses = command("name = \"Licenses\"\ncommand = \"echo 3\"");
+
+    let mut registry = PanelRegistry::<TestBackend>::default();
+    let built_in = registry.len();
+    registry.register_commands(&[queue.clone(), slots, licenses]).unwrap();
+    let hotkeys = registry.hotkeys();
+    assert_eq!(hotkeys[built_in..], [("queue".to_string(), '1'), ("slots".to_string(), 's'), ("licenses".to_string(), '2')]);
+
+    let error = |commands: &[CommandConfig]| PanelRegistry::<TestBackend>::default().register_commands(commands).unwrap_err().to_string();
+    let cpu = command("name = \"CPU\"\ncommand = \"echo 1\"");
+    assert_eq!(error(&[cpu]), "invalid configuration: command panel 'CPU' has the same name as another panel");
+    assert_eq!(error(&[queue.clone(), queue]), "invalid configuration: command panel 'Queue' has the same name as another panel");
+    let gauge = command("name = \"Queue\"\ncommand = \"echo 1\"\nwidget = \"gauge\"");
+    assert_eq!(error(&[gauge]), "invalid configuration: command panel 'Queue': gauges need a max");
+    assert!(toml::from_str::<CommandConfig>("name = \"Queue\"\ncommand = \"echo 1\"\nwidget = \"pie\"").is_err());
+}
+
+#[test]
+fn only_ten_command_panels_are_numbered() {
+    let commands: Vec<CommandConfig> = (1..=11).map(|i| command(&format!("name = \"Queue {}\"\ncommand = \"echo {}\"", i, i))).collect();
+    let mut registry = PanelRegistry::<TestBackend>::default();
+    let built_in = registry.len();
+    registry.register_commands(&commands[..10]).unwrap();
+    let hotkeys: String = registry.hotkeys()[built_in..].iter().map(|(_, key)| *key).collect();
+    assert_eq!(hotkeys, "1234567890");
+
+    let error = PanelRegistry::<TestBackend>::default().register_commands(&commands).unwrap_err().to_string();
+    assert_eq!(error, "invalid configuration: command panel 'Queue 11' needs a key, only the first 10 are numbered");
+    // Panels with their own key do not use up a number.
+    let mut keyed = commands.clone();
+    keyed[0].key = Some('x');
+    PanelRegistry::<TestBackend>::default().register_commands(&keyed).unwrap();
+}
+
+#[test]
+fn command_panels_render_each_widget() {
+    let mut bars = CommandPanel::new(command("name = \"Farm\"\ncommand = \"printf 'free=3\\nused=5'\"\nformat = \"keyvalue\""), '1');
+    let screen = render_started(&mut bars);
+    assert!(screen.contains("free: 3"), "{}", screen);
+    assert!(screen.contains("Farm Bar Graph"), "{}", screen);
+
+    let mut gauge = CommandPanel::new(command("name = \"Licenses\"\ncommand = \"echo 7\"\nwidget = \"gauge\"\nmax = 10\nunit = \"seats\""), '2');
+    let screen = render_started(&mut gauge);
+    assert!(screen.contains("7 seats / 10 seats"), "{}", screen);
+
+    let mut line = CommandPanel::new(command("name = \"Queue\"\ncommand = \"echo 12.5\"\nwidget = \"line\""), '3');
+    let screen = render_started(&mut line);
+    assert!(screen.contains("Queue Chart"), "{}", screen);
+    assert!(screen.contains("Seconds ago"), "{}", screen);
+}
+
+#[test]
+fn command_panels_show_errors() {
+    let mut panel = CommandPanel::new(command("name = \"Queue\"\ncommand = \"exit 2\""), '1');
+    let screen = render(&mut panel, &SystemSnapshot::default(), &mut App::default(), 120, 20);
+    assert!(screen.contains("Waiting for the first run"), "{}", screen);
+
+    let screen = render_started(&mut panel);
+    assert!(screen.contains("Last run failed:"), "{}", screen);
+    assert!(screen.contains("command exited with status 2"), "{}", screen);
+}
@@ -0,0 +1,38 @@
+#![allow(dead_code)]
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+use systemmor::fake::{FakeData, FakeSource};
+use systemmor::keymap::KeyResult;
+use systemmor::{App, Panel, Sampler, SystemSnapshot, Theme};
+
+// One sample of everything from a fake source.
+pub fn sample(data: FakeData) -> SystemSnapshot {
+    Sampler::with_source(Box::new(FakeSource::new(data))).sample_all()
+}
+
+// Offers a key press to `panel` the way the interface does, through the
+// key map first. Returns whether the panel used it.
+pub fn press(panel: &mut dyn Panel<TestBackend>, app: &mut App, code: KeyCode) -> bool {
+    let event = KeyEvent::new(code, KeyModifiers::NONE);
+    match app.keymap.feed(event) {
+        KeyResult::Action(action) => panel.handle_action(&action, app),
+        KeyResult::Pending | KeyResult::Unbound => false,
+    }
+}
+
+// Draws `panel` into a `width` x `height` buffer and returns its text, one
+// line per row.
+pub fn render(panel: &mut dyn Panel<TestBackend>, snapshot: &SystemSnapshot, app: &mut App, width: u16, height: u16) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|rect| {
+        let area = rect.size();
+        panel.render(rect, area, snapshot, app, &Theme::dark());
+    }).unwrap();
+    let buffer = terminal.backend().buffer();
+    (0..height)
+        .map(|y| (0..width).map(|x| buffer.get(x, y).symbol.as_str()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
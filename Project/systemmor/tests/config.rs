@@ -0,0 +1,63 @@
+use std::time::Duration;
+use systemmor::{Config, Error};
+
+fn error(contents: &str) -> String {
+    match contents.parse::<Config>() {
+        Err(Error::Config(message)) => message,
+        other => panic!("expected a config error, got {:?}", other),
+    }
+}
+
+#[test]
+fn valid_file_loads() {
+    let config: Config = r#"
+panels = ["cpu", "memory"]
+default_panel = "memory"
+
+[refresh]
+interval = "500ms"
+background = "off"
+history_length = 600
+history_resolution = "2s"
+
+[refresh.cadence]
+processes = "2s"
+"#.parse().unwrap();
+    assert_eq!(config.panels, Some(vec!["cpu".to_string(), "memory".to_string()]));
+    assert_eq!(config.default_panel.as_deref(), Some("memory"));
+    assert_eq!(config.refresh.interval, Duration::from_millis(500));
+    assert_eq!(config.refresh.background, None);
+    assert_eq!(config.refresh.history_length, 600);
+    assert_eq!(config.refresh.history_resolution, Duration::from_secs(2));
+    assert_eq!(config.refresh.cadence.processes, Duration::from_secs(2));
+}
+
+#[test]
+fn empty_file_uses_the_defaults() {
+    assert_eq!("".parse::<Config>().unwrap(), Config::default());
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    assert!(error("pannels = [\"cpu\"]\n").contains("unknown field `pannels`"));
+    assert!(error("[refresh]\nintervall = \"1s\"\n").contains("unknown field `intervall`"));
+}
+
+#[test]
+fn bad_durations_are_reported() {
+    let message = error("[refresh]\ninterval = \"soon\"\n");
+    assert!(message.contains("invalid duration 'soon'"), "{}", message);
+    let message = error("[refresh]\nhistory_resolution = \"0s\"\n");
+    assert!(message.contains("must be greater than zero"), "{}", message);
+}
+
+#[test]
+fn history_bounds_name_the_key() {
+    let message = error("[refresh]\nhistory_length = 0\n");
+    assert!(message.contains("refresh.history_length"), "{}", message);
+    let message = error("[refresh]\nhistory_length = 1000000\n");
+    assert!(message.contains("refresh.history_length"), "{}", message);
+    let message = error("[refresh]\nhistory_resolution = \"61m\"\n");
+    assert!(message.contains("refresh.history_resolution"), "{}", message);
+    assert!("[refresh]\nhistory_length = 86400\nhistory_resolution = \"60m\"\n".parse::<Config>().is_ok());
+}
@@ -0,0 +1,75 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+use systemmor::exporter::{Exporter, MAX_CONNECTIONS};
+use systemmor::snapshot::MemorySample;
+use systemmor::{Subsystem, SystemSnapshot};
+
+fn get(exporter: &Exporter, path: &str) -> String {
+    let mut stream = TcpStream::connect(exporter.address()).unwrap();
+    // Well under the exporter's own client timeout.
+    stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+fn start() -> Exporter {
+    let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let snapshot = Arc::new(Mutex::new(SystemSnapshot {
+        timestamp: time,
+        refreshed: [(Subsystem::Memory, time)].into(),
+        memory: MemorySample { total: 8 << 30, used: 2 << 30, total_swap: 0, used_swap: 0 },
+        ..SystemSnapshot::default()
+    }));
+    Exporter::start("127.0.0.1:0", snapshot, 10).unwrap()
+}
+
+#[test]
+fn metrics_are_served() {
+    let exporter = start();
+    let response = get(&exporter, "/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.contains("Content-Type: application/openmetrics-text"));
+    assert!(response.contains("systemmor_memory_used_bytes 2147483648\n"));
+    assert!(response.ends_with("# EOF\n"));
+    assert!(get(&exporter, "/other").starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
+
+#[test]
+fn idle_clients_do_not_hold_up_scrapes() {
+    let exporter = start();
+    let _idle = TcpStream::connect(exporter.address()).unwrap();
+    let mut half = TcpStream::connect(exporter.address()).unwrap();
+    half.write_all(b"GET /metrics HTTP/1.1\r\n").unwrap();
+    assert!(get(&exporter, "/metrics").starts_with("HTTP/1.1 200 OK\r\n"));
+}
+
+#[test]
+fn connections_past_the_limit_are_closed() {
+    let exporter = start();
+    let idle: Vec<TcpStream> = (0..MAX_CONNECTIONS).map(|_| TcpStream::connect(exporter.address()).unwrap()).collect();
+    let mut extra = TcpStream::connect(exporter.address()).unwrap();
+    extra.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    // Closed without an answer, rather than left waiting.
+    let mut response = vec![];
+    let closed = extra.read_to_end(&mut response);
+    assert!(matches!(closed, Ok(0)) || closed.is_err_and(|err| err.kind() == std::io::ErrorKind::ConnectionReset));
+
+    drop(idle);
+    // Served again once the idle clients are gone.
+    let deadline = std::time::Instant::now() + Duration::from_secs(2);
+    loop {
+        let mut stream = TcpStream::connect(exporter.address()).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let mut response = String::new();
+        let served = write!(stream, "GET /metrics HTTP/1.1\r\n\r\n").and_then(|_| stream.read_to_string(&mut response)).is_ok();
+        if served && response.starts_with("HTTP/1.1 200 OK\r\n") {
+            break;
+        }
+        assert!(std::time::Instant::now() < deadline, "no connection was freed");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
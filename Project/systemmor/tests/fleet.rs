@@ -0,0 +1,165 @@
+mod common;
+
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+use common::{press, render, sample};
+use crossterm::event::KeyCode;
+use systemmor::agent::{Agent, Endpoint};
+use systemmor::exporter::SharedSnapshot;
+use systemmor::fake::FakeData;
+use systemmor::fleet::{Fleet, FleetSort, HostRow, HostStatus};
+use systemmor::panel::FleetPanel;
+use systemmor::{App, Config, Keymap, SystemSnapshot};
+
+const REFRESH: Duration = Duration::from_millis(50);
+
+fn agent(data: FakeData) -> Agent {
+    let shared = SharedSnapshot::default();
+    *shared.lock().unwrap() = sample(data);
+    Agent::start(&"127.0.0.1:0".parse().unwrap(), shared).unwrap()
+}
+
+// An address nothing listens on.
+fn closed_port() -> Endpoint {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    Endpoint::Tcp(listener.local_addr().unwrap().to_string())
+}
+
+// Rows once every host has been polled at least once.
+fn polled(fleet: &Fleet) -> Vec<HostRow> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let rows = fleet.rows();
+        if rows.iter().all(|row| row.status != HostStatus::Connecting) || Instant::now() > deadline {
+            return rows;
+        }
+        thread::sleep(REFRESH);
+    }
+}
+
+fn row<'a>(rows: &'a [HostRow], endpoint: &Endpoint) -> &'a HostRow {
+    rows.iter().find(|row| &row.endpoint == endpoint).unwrap()
+}
+
+#[test]
+fn fleet_shows_each_agent() {
+    let idle = agent(FakeData::default().with_cpus(2, 5.0).with_memory_used(0.25));
+    let busy = agent(FakeData::default().with_cpus(8, 90.0).with_memory_used(0.75).with_components(0));
+    let endpoints = [idle.endpoint().clone(), busy.endpoint().clone()];
+    let fleet = Fleet::start(&endpoints, REFRESH, Duration::from_secs(60));
+    assert_eq!(fleet.len(), 2);
+
+    let rows = polled(&fleet);
+    let idle_row = row(&rows, idle.endpoint());
+    assert_eq!(idle_row.status, HostStatus::Up);
+    assert_eq!(idle_row.cpu, Some(5.0));
+    assert_eq!(idle_row.memory, Some(0.25));
+    assert_eq!(idle_row.disk, Some(0.75));
+    assert_eq!(idle_row.temperature, Some(41.0));
+    let busy_row = row(&rows, busy.endpoint());
+    assert_eq!(busy_row.cpu, Some(90.0));
+    assert_eq!(busy_row.temperature, None);
+}
+
+#[test]
+fn unreachable_agents_are_reported_and_sorted_first() {
+    let up = agent(FakeData::default());
+    let down = closed_port();
+    let mut fleet = Fleet::start(&[up.endpoint().clone(), down.clone()], REFRESH, Duration::from_secs(60));
+
+    let rows = polled(&fleet);
+    let down_row = row(&rows, &down);
+    assert!(matches!(&down_row.status, HostStatus::Unreachable(err) if err.contains(&down.to_string())), "{:?}", down_row.status);
+    assert_eq!(down_row.name, down.to_string());
+    assert_eq!(down_row.cpu, None);
+
+    fleet.next_sort();
+    assert_eq!(fleet.sort(), (FleetSort::Status, false));
+    assert_eq!(fleet.rows()[0].endpoint, down);
+}
+
+#[test]
+fn agents_that_stop_sampling_go_stale() {
+    // The fake agent keeps serving the same snapshot.
+    let agent = agent(FakeData::default());
+    let fleet = Fleet::start(&[agent.endpoint().clone()], REFRESH, Duration::ZERO);
+    thread::sleep(REFRESH * 3);
+    let rows = polled(&fleet);
+    assert!(matches!(rows[0].status, HostStatus::Stale(_)), "{:?}", rows[0].status);
+}
+
+#[test]
+fn sorting_and_opening_hosts() {
+    let agents: Vec<Agent> = [30.0, 10.0, 20.0].into_iter().map(|usage| agent(FakeData::default().with_cpus(4, usage))).collect();
+    let endpoints: Vec<Endpoint> = agents.iter().map(|agent| agent.endpoint().clone()).collect();
+    let mut fleet = Fleet::start(&endpoints, REFRESH, Duration::from_secs(60));
+    polled(&fleet);
+
+    fleet.next_sort();
+    fleet.next_sort();
+    assert_eq!(fleet.sort(), (FleetSort::Cpu, true));
+    let usage = |fleet: &Fleet| fleet.rows().iter().map(|row| row.cpu.unwrap()).collect::<Vec<_>>();
+    assert_eq!(usage(&fleet), vec![30.0, 20.0, 10.0]);
+    fleet.reverse();
+    assert_eq!(usage(&fleet), vec![10.0, 20.0, 30.0]);
+
+    fleet.select_next();
+    fleet.select_next();
+    fleet.select_next();
+    assert_eq!(fleet.selected(), 2);
+    fleet.open_selected();
+    assert_eq!(fleet.take_opened().map(|row| row.endpoint), Some(endpoints[0].clone()));
+    assert_eq!(fleet.take_opened(), None);
+}
+
+#[test]
+fn fleet_keys_go_through_the_key_map() {
+    let agents: Vec<Agent> = [30.0, 10.0, 20.0].into_iter().map(|usage| agent(FakeData::default().with_cpus(4, usage))).collect();
+    let endpoints: Vec<Endpoint> = agents.iter().map(|agent| agent.endpoint().clone()).collect();
+    let keys = "[keys]\nfleet_open = \"o\"\nfleet_reverse = []\n".parse::<Config>().unwrap().keys;
+    let mut app = App {
+        fleet: Fleet::start(&endpoints, REFRESH, Duration::from_secs(60)),
+        keymap: Keymap::new(&[("fleet".to_string(), 'f')], &keys).unwrap(),
+        ..App::default()
+    };
+    polled(&app.fleet);
+
+    assert!(press(&mut FleetPanel, &mut app, KeyCode::Char('s')));
+    assert!(press(&mut FleetPanel, &mut app, KeyCode::Char('s')));
+    assert_eq!(app.fleet.sort(), (FleetSort::Cpu, true));
+    assert!(!press(&mut FleetPanel, &mut app, KeyCode::Char('r')));
+    assert!(press(&mut FleetPanel, &mut app, KeyCode::Down));
+    assert_eq!(app.fleet.selected(), 1);
+    assert!(!press(&mut FleetPanel, &mut app, KeyCode::Enter));
+    assert!(press(&mut FleetPanel, &mut app, KeyCode::Char('o')));
+    assert_eq!(app.fleet.take_opened().map(|row| row.endpoint), Some(endpoints[2].clone()));
+
+    let screen = render(&mut FleetPanel, &SystemSnapshot::default(), &mut app, 150, 12);
+    assert!(screen.contains(&format!("{} (o: open, s: sort)", app.fleet.rows()[1].name)), "{}", screen);
+}
+
+#[test]
+fn fleet_panel_renders_a_table() {
+    let up = agent(FakeData::default().with_cpus(4, 42.0));
+    let down = closed_port();
+    let mut app = App {
+        fleet: Fleet::start(&[up.endpoint().clone(), down.clone()], REFRESH, Duration::from_secs(60)),
+        ..App::default()
+    };
+    polled(&app.fleet);
+
+    let screen = render(&mut FleetPanel, &SystemSnapshot::default(), &mut app, 150, 12);
+    assert!(screen.contains("Fleet (sorted by host)"), "{}", screen);
+    assert!(screen.contains(&up.endpoint().to_string()), "{}", screen);
+    assert!(screen.contains("42.0%"), "{}", screen);
+    assert!(screen.contains("unreachable"), "{}", screen);
+    // Unreachable hosts come first and show why below the table.
+    assert!(screen.contains("Connection refused"), "{}", screen);
+    assert!(press(&mut FleetPanel, &mut app, KeyCode::Down));
+    let screen = render(&mut FleetPanel, &SystemSnapshot::default(), &mut app, 150, 12);
+    assert!(screen.contains("(Enter: open, s: sort, r: reverse)"), "{}", screen);
+
+    let screen = render(&mut FleetPanel, &SystemSnapshot::default(), &mut App::default(), 150, 12);
+    assert!(screen.contains("No hosts are configured"), "{}", screen);
+}
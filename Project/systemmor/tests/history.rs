@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, UNIX_EPOCH};
+use systemmor::history::{History, Series, MAX_CAPACITY};
+use systemmor::snapshot::{ComponentSample, MemorySample};
+use systemmor::{Subsystem, SystemSnapshot};
+
+// A snapshot with `used` percent of memory in use, refreshed `seconds`
+// after the epoch.
+fn at(seconds: f64, used: u64) -> SystemSnapshot {
+    let time = UNIX_EPOCH + Duration::from_secs_f64(seconds);
+    SystemSnapshot {
+        timestamp: time,
+        refreshed: BTreeMap::from([(Subsystem::Memory, time)]),
+        memory: MemorySample { total: 100, used, total_swap: 0, used_swap: 0 },
+        ..SystemSnapshot::default()
+    }
+}
+
+fn values(series: &Series) -> Vec<f64> {
+    series.iter().map(|(_, value)| value.round()).collect()
+}
+
+#[test]
+fn series_drops_the_oldest_point_when_full() {
+    let mut series = Series::new(3);
+    for value in 1..=5 {
+        series.push(UNIX_EPOCH + Duration::from_secs(value), value as f64);
+    }
+    assert_eq!(series.len(), 3);
+    assert_eq!(values(&series), vec![3.0, 4.0, 5.0]);
+    assert_eq!(series.latest(), Some(5.0));
+    assert_eq!(series.max(), Some(5.0));
+}
+
+#[test]
+fn history_wraps_around_at_capacity() {
+    let mut history = History::new(4, Duration::from_secs(1));
+    for step in 0..10 {
+        history.record(&at(step as f64, step * 10));
+    }
+    assert_eq!(values(&history.memory), vec![60.0, 70.0, 80.0, 90.0]);
+}
+
+#[test]
+fn samples_closer_than_the_resolution_are_skipped() {
+    let mut history = History::new(100, Duration::from_secs(2));
+    for (seconds, used) in [(0.0, 10), (0.5, 20), (1.5, 30), (2.0, 40), (3.0, 50), (4.5, 60)] {
+        history.record(&at(seconds, used));
+    }
+    assert_eq!(values(&history.memory), vec![10.0, 40.0, 60.0]);
+    let times = history.memory.iter().map(|(time, _)| time.duration_since(UNIX_EPOCH).unwrap().as_secs_f64()).collect::<Vec<_>>();
+    assert_eq!(times, vec![0.0, 2.0, 4.5]);
+}
+
+#[test]
+fn repeated_refreshes_are_recorded_once() {
+    let mut history = History::new(10, Duration::ZERO);
+    let snapshot = at(1.0, 50);
+    history.record(&snapshot);
+    history.record(&snapshot);
+    history.record(&at(0.5, 20));
+    assert_eq!(values(&history.memory), vec![50.0]);
+    // Nothing else was refreshed.
+    assert!(history.cpu_total.is_empty());
+}
+
+#[test]
+fn capacity_is_capped_and_span_saturates() {
+    let history = History::new(usize::MAX, Duration::MAX);
+    assert_eq!(history.capacity(), MAX_CAPACITY);
+    assert_eq!(history.span(), Duration::MAX);
+    assert_eq!(History::new(0, Duration::from_secs(1)).capacity(), 1);
+    assert_eq!(History::new(300, Duration::from_secs(2)).span(), Duration::from_secs(600));
+}
+
+#[test]
+fn sensors_sharing_a_label_keep_their_own_history() {
+    let mut history = History::new(10, Duration::from_secs(1));
+    let time = UNIX_EPOCH + Duration::from_secs(1);
+    let snapshot = SystemSnapshot {
+        timestamp: time,
+        refreshed: BTreeMap::from([(Subsystem::Components, time)]),
+        components: ["Composite", "Composite", "acpitz"].iter().zip([40.0, 45.0, 50.0])
+            .map(|(label, temperature)| ComponentSample { label: label.to_string(), temperature, max: 80.0, critical: None })
+            .collect(),
+        ..SystemSnapshot::default()
+    };
+    history.record(&snapshot);
+    let latest: Vec<_> = history.temperatures.iter().map(|((label, i), series)| (label.as_str(), *i, series.latest())).collect();
+    assert_eq!(latest, vec![("Composite", 0, Some(40.0)), ("Composite", 1, Some(45.0)), ("acpitz", 2, Some(50.0))]);
+}
@@ -0,0 +1,44 @@
+use std::time::Duration;
+use systemmor::interval::{faster, parse_duration, parse_interval, slower};
+
+#[test]
+fn durations_in_every_unit() {
+    assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+    assert_eq!(parse_duration("250"), Ok(Duration::from_millis(250)));
+    assert_eq!(parse_duration("2s"), Ok(Duration::from_secs(2)));
+    assert_eq!(parse_duration(" 1.5s "), Ok(Duration::from_millis(1500)));
+    assert_eq!(parse_duration("1m"), Ok(Duration::from_secs(60)));
+    assert_eq!(parse_duration("1.5 m"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse_duration("0"), Ok(Duration::ZERO));
+    assert_eq!(parse_duration("0s"), Ok(Duration::ZERO));
+    assert_eq!(parse_duration("0.0ms"), Ok(Duration::ZERO));
+}
+
+#[test]
+fn intervals_must_be_positive() {
+    assert_eq!(parse_interval("2s"), Ok(Duration::from_secs(2)));
+    assert_eq!(parse_interval("0s").unwrap_err(), "duration '0s' must be greater than zero");
+    assert_eq!(parse_interval("0.0000001ms").unwrap_err(), "duration '0.0000001ms' must be greater than zero");
+    assert_eq!(parse_interval("1h").unwrap_err(), "unknown duration unit 'h' in '1h' (use ms, s or m)");
+}
+
+#[test]
+fn invalid_durations() {
+    let error = |input: &str| parse_duration(input).unwrap_err();
+    assert_eq!(error("-1s"), "invalid duration '-1s'");
+    assert_eq!(error("NaN"), "invalid duration 'NaN'");
+    assert_eq!(error("s"), "invalid duration 's'");
+    assert_eq!(error("1h"), "unknown duration unit 'h' in '1h' (use ms, s or m)");
+    assert!(error("100000000000000000000s").starts_with("invalid duration '100000000000000000000s': "));
+    // Too many digits for an f64.
+    let infinite = format!("{}ms", "9".repeat(400));
+    assert!(error(&infinite).contains("too big"), "{}", error(&infinite));
+}
+
+#[test]
+fn interval_steps() {
+    assert_eq!(faster(Duration::from_secs(1)), Duration::from_millis(500));
+    assert_eq!(faster(Duration::from_millis(100)), Duration::from_millis(100));
+    assert_eq!(slower(Duration::from_millis(300)), Duration::from_millis(500));
+    assert_eq!(slower(Duration::from_secs(600)), Duration::from_secs(60));
+}
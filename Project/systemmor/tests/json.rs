@@ -0,0 +1,111 @@
+use std::time::{Duration, UNIX_EPOCH};
+use systemmor::json::{to_json, to_json_line, Document, FORMAT_VERSION};
+use systemmor::snapshot::{ComponentSample, MemorySample};
+use systemmor::{Error, Subsystem, SystemSnapshot};
+
+fn sample() -> SystemSnapshot {
+    // Whole milliseconds survive the trip through seconds as f64.
+    let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_125);
+    let mut snapshot = SystemSnapshot {
+        timestamp: UNIX_EPOCH + Duration::from_millis(1_700_000_000_250),
+        refreshed: Subsystem::ALL.iter().map(|subsystem| (*subsystem, time)).collect(),
+        memory: MemorySample { total: 8192, used: 4096, total_swap: 0, used_swap: 0 },
+        components: vec![ComponentSample { label: "cpu".to_string(), temperature: 48.0, max: 61.0, critical: None }],
+        ..SystemSnapshot::default()
+    };
+    snapshot.errors.insert(Subsystem::Batteries, Error::NotFound(Subsystem::Batteries));
+    snapshot
+}
+
+fn pretty(snapshot: &SystemSnapshot, subsystems: &[Subsystem]) -> String {
+    to_json(&Document::new(snapshot, subsystems)).unwrap()
+}
+
+fn line(snapshot: &SystemSnapshot, subsystems: &[Subsystem]) -> String {
+    to_json_line(&Document::new(snapshot, subsystems)).unwrap()
+}
+
+fn parse(text: &str) -> serde_json::Value {
+    serde_json::from_str(text).unwrap()
+}
+
+#[test]
+fn documents_carry_the_format_version() {
+    let value = parse(&line(&sample(), &[Subsystem::Memory]));
+    assert_eq!(value["version"], FORMAT_VERSION);
+    assert_eq!(value["timestamp"], 1_700_000_000.25);
+    assert_eq!(value["memory"]["used"], 4096);
+    assert!(value.get("cpus").is_none() && value.get("components").is_none());
+    assert_eq!(value["errors"], serde_json::json!({}));
+}
+
+#[test]
+fn pretty_and_line_documents_hold_the_same_data() {
+    let indented = pretty(&sample(), &Subsystem::ALL);
+    let single = line(&sample(), &Subsystem::ALL);
+    assert!(indented.contains('\n'));
+    assert!(!single.contains('\n'));
+    assert_eq!(parse(&indented), parse(&single));
+}
+
+#[test]
+fn unreadable_subsystems_are_reported_under_errors() {
+    let value = parse(&line(&sample(), &Subsystem::ALL));
+    assert!(value.get("batteries").is_none());
+    assert_eq!(value["errors"]["batteries"], "no batteries found");
+    assert_eq!(value["components"][0]["label"], "cpu");
+    assert_eq!(value["components"][0]["critical"], serde_json::Value::Null);
+}
+
+#[test]
+fn documents_round_trip() {
+    let snapshot = sample();
+    for text in [pretty(&snapshot, &Subsystem::ALL), line(&snapshot, &Subsystem::ALL)] {
+        let document: Document = serde_json::from_str(&text).unwrap();
+        assert_eq!(document.version, FORMAT_VERSION);
+        assert_eq!(document.subsystems(), Subsystem::ALL.to_vec());
+
+        let mut restored = SystemSnapshot::default();
+        document.apply(&mut restored);
+        assert_eq!(restored.timestamp, snapshot.timestamp);
+        assert_eq!(restored.refreshed, snapshot.refreshed);
+        assert_eq!(restored.memory, snapshot.memory);
+        assert_eq!(restored.components, snapshot.components);
+        // Errors come back as their message.
+        assert!(restored.batteries.is_empty());
+        assert_eq!(restored.error(Subsystem::Batteries).map(ToString::to_string), Some("no batteries found".to_string()));
+
+        // Serializing the restored snapshot gives the same document.
+        assert_eq!(pretty(&restored, &Subsystem::ALL), pretty(&snapshot, &Subsystem::ALL));
+    }
+}
+
+#[test]
+fn partial_documents_leave_other_subsystems_alone() {
+    let snapshot = sample();
+    let mut restored = SystemSnapshot::default();
+    serde_json::from_str::<Document>(&line(&snapshot, &Subsystem::ALL)).unwrap().apply(&mut restored);
+
+    let mut later = sample();
+    later.timestamp = snapshot.timestamp + Duration::from_secs(1);
+    later.memory.used = 1024;
+    later.components.clear();
+    let document: Document = serde_json::from_str(&line(&later, &[Subsystem::Memory])).unwrap();
+    assert_eq!(document.subsystems(), vec![Subsystem::Memory]);
+    document.apply(&mut restored);
+    assert_eq!(restored.timestamp, later.timestamp);
+    assert_eq!(restored.memory, later.memory);
+    assert_eq!(restored.components, snapshot.components);
+    assert_eq!(restored.refreshed_at(Subsystem::Components), snapshot.refreshed_at(Subsystem::Components));
+}
+
+#[test]
+fn older_documents_without_optional_fields_load() {
+    let document: Document = serde_json::from_str(r#"{"version":1,"timestamp":10.5,"memory":{"total":100,"used":50,"total_swap":0,"used_swap":0}}"#).unwrap();
+    assert_eq!(document.subsystems(), vec![Subsystem::Memory]);
+    let mut snapshot = SystemSnapshot::default();
+    document.apply(&mut snapshot);
+    // Without `refreshed`, the document's timestamp stands in.
+    assert_eq!(snapshot.refreshed_at(Subsystem::Memory), Some(UNIX_EPOCH + Duration::from_millis(10_500)));
+    assert_eq!(snapshot.memory.used, 50);
+}
@@ -0,0 +1,178 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use systemmor::keymap::{format_sequence, parse_sequence, KeyChord, KeyResult, KeysConfig};
+use ratatui::backend::TestBackend;
+use systemmor::{Action, Config, Error, Keymap, PanelRegistry};
+
+fn chord(code: KeyCode, modifiers: KeyModifiers) -> KeyChord {
+    KeyChord::new(code, modifiers)
+}
+
+fn key(c: char) -> KeyEvent {
+    KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+}
+
+fn keys(toml: &str) -> KeysConfig {
+    toml.parse::<Config>().unwrap().keys
+}
+
+fn panels() -> Vec<(String, char)> {
+    vec![("cpu".to_string(), 'c'), ("process".to_string(), 'p')]
+}
+
+fn action(result: KeyResult) -> Option<Action> {
+    match result {
+        KeyResult::Action(action) => Some(action),
+        _ => None,
+    }
+}
+
+fn config_error(result: systemmor::Result<Keymap>) -> String {
+    match result {
+        Err(Error::Config(message)) => message,
+        other => panic!("expected a config error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn chords_parse_keys_and_modifiers() {
+    assert_eq!(KeyChord::parse("q").unwrap(), chord(KeyCode::Char('q'), KeyModifiers::NONE));
+    assert_eq!(KeyChord::parse("G").unwrap(), chord(KeyCode::Char('G'), KeyModifiers::NONE));
+    assert_eq!(KeyChord::parse("ctrl+c").unwrap(), chord(KeyCode::Char('c'), KeyModifiers::CONTROL));
+    assert_eq!(KeyChord::parse("Control+Alt+x").unwrap(), chord(KeyCode::Char('x'), KeyModifiers::CONTROL | KeyModifiers::ALT));
+    assert_eq!(KeyChord::parse("shift+tab").unwrap(), chord(KeyCode::Tab, KeyModifiers::SHIFT));
+    assert_eq!(KeyChord::parse("alt+enter").unwrap(), chord(KeyCode::Enter, KeyModifiers::ALT));
+    assert_eq!(KeyChord::parse("+").unwrap(), chord(KeyCode::Char('+'), KeyModifiers::NONE));
+    assert_eq!(KeyChord::parse("ctrl++").unwrap(), chord(KeyCode::Char('+'), KeyModifiers::CONTROL));
+    // Shift is part of a printable character already.
+    assert_eq!(KeyChord::parse("shift+g").unwrap(), chord(KeyCode::Char('G'), KeyModifiers::NONE));
+}
+
+#[test]
+fn chords_parse_named_keys() {
+    for (name, code) in [
+        ("space", KeyCode::Char(' ')),
+        ("Esc", KeyCode::Esc),
+        ("return", KeyCode::Enter),
+        ("backspace", KeyCode::Backspace),
+        ("del", KeyCode::Delete),
+        ("pageup", KeyCode::PageUp),
+        ("left", KeyCode::Left),
+        ("f5", KeyCode::F(5)),
+        ("F24", KeyCode::F(24)),
+    ] {
+        assert_eq!(KeyChord::parse(name).unwrap().code, code, "{}", name);
+    }
+}
+
+#[test]
+fn invalid_chords_are_rejected() {
+    for (input, reason) in [
+        ("hyper+x", "unknown modifier 'hyper'"),
+        ("ctrl+", "expected a key after '+'"),
+        ("ctrl+x+", "expected a key after '+'"),
+        ("escape2", "unknown key name"),
+        ("f0", "unknown key name"),
+        ("f25", "unknown key name"),
+        ("", "unknown key name"),
+    ] {
+        match KeyChord::parse(input) {
+            Err(Error::Config(message)) => assert!(message.contains(reason), "{}: {}", input, message),
+            other => panic!("{}: expected an error, got {:?}", input, other),
+        }
+    }
+    assert!(parse_sequence("   ").is_err());
+}
+
+#[test]
+fn chords_display_as_they_are_written() {
+    for input in ["q", "ctrl+c", "alt+x", "space", "F5", "g p"] {
+        assert_eq!(format_sequence(&parse_sequence(input).unwrap()), input);
+    }
+    assert_eq!(format_sequence(&parse_sequence("shift+tab").unwrap()), "shift+Tab");
+}
+
+#[test]
+fn defaults_and_panel_hotkeys_are_bound() {
+    let mut keymap = Keymap::new(&panels(), &KeysConfig::default()).unwrap();
+    assert_eq!(action(keymap.feed(key('q'))), Some(Action::Quit));
+    assert_eq!(action(keymap.feed(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL))), Some(Action::Quit));
+    assert_eq!(action(keymap.feed(key('p'))), Some(Action::ShowPanel("process".to_string())));
+    assert_eq!(keymap.panel_hotkey("cpu"), Some('c'));
+    assert!(matches!(keymap.feed(key('z')), KeyResult::Unbound));
+}
+
+#[test]
+fn sequences_wait_for_their_last_key() {
+    let config = keys("[keys]\nquit = \"g q\"\n\n[keys.panels]\nprocess = [\"g p\", \"P\"]\n");
+    let mut keymap = Keymap::new(&panels(), &config).unwrap();
+    assert!(matches!(keymap.feed(key('g')), KeyResult::Pending));
+    assert_eq!(format_sequence(keymap.pending()), "g");
+    assert_eq!(action(keymap.feed(key('p'))), Some(Action::ShowPanel("process".to_string())));
+    assert!(keymap.pending().is_empty());
+    assert_eq!(action(keymap.feed(key('P'))), Some(Action::ShowPanel("process".to_string())));
+    // Listing an action replaces its defaults.
+    assert!(matches!(keymap.feed(key('q')), KeyResult::Unbound));
+    assert!(matches!(keymap.feed(key('g')), KeyResult::Pending));
+    assert_eq!(action(keymap.feed(key('q'))), Some(Action::Quit));
+}
+
+#[test]
+fn a_broken_sequence_replays_the_key_that_broke_it() {
+    let config = keys("[keys.panels]\nprocess = \"g p\"\n");
+    let mut keymap = Keymap::new(&panels(), &config).unwrap();
+    assert!(matches!(keymap.feed(key('g')), KeyResult::Pending));
+    assert_eq!(action(keymap.feed(key('c'))), Some(Action::ShowPanel("cpu".to_string())));
+    assert!(keymap.pending().is_empty());
+}
+
+#[test]
+fn empty_lists_unbind_an_action() {
+    let keymap = Keymap::new(&panels(), &keys("[keys]\nslower = []\n")).unwrap();
+    assert!(keymap.keys_for(&Action::Slower).is_empty());
+    assert!(keymap.help().iter().all(|(_, action)| *action != Action::Slower));
+}
+
+#[test]
+fn conflicting_keys_are_reported() {
+    let message = config_error(Keymap::new(&panels(), &keys("[keys]\nslower = \"q\"\n")));
+    assert!(message.contains("key 'q' for sample slower") && message.contains("conflicts with 'q' for exit"), "{}", message);
+
+    // A sequence may not start with a key that is bound on its own.
+    let message = config_error(Keymap::new(&panels(), &keys("[keys]\nfaster = \"c c\"\n")));
+    assert!(message.contains("'c c'") && message.contains("'c'"), "{}", message);
+}
+
+#[test]
+fn unknown_names_are_reported() {
+    let message = config_error(Keymap::new(&panels(), &keys("[keys]\nexplode = \"x\"\n")));
+    assert!(message.contains("unknown action 'explode'"), "{}", message);
+    let message = config_error(Keymap::new(&panels(), &keys("[keys.panels]\nnetwork = \"n\"\n")));
+    assert!(message.contains("unknown panel 'network'"), "{}", message);
+    let message = config_error(Keymap::new(&panels(), &keys("[keys]\nquit = \"ctrl+banana\"\n")));
+    assert!(message.contains("invalid key 'ctrl+banana'"), "{}", message);
+}
+
+#[test]
+fn shift_letters_match_the_capital() {
+    let mut keymap = Keymap::new(&panels(), &keys("[keys]\nquit = \"shift+g\"\nslower = \"ctrl+shift+x\"\n")).unwrap();
+    assert_eq!(action(keymap.feed(key('G'))), Some(Action::Quit));
+    assert_eq!(action(keymap.feed(KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT))), Some(Action::Quit));
+    assert!(matches!(keymap.feed(key('g')), KeyResult::Unbound));
+    assert_eq!(action(keymap.feed(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::CONTROL | KeyModifiers::SHIFT))), Some(Action::Slower));
+    let message = config_error(Keymap::new(&panels(), &keys("[keys]\nquit = \"shift+g\"\nslower = \"G\"\n")));
+    assert!(message.contains("key 'G' for sample slower") && message.contains("conflicts with 'G' for exit"), "{}", message);
+}
+
+#[test]
+fn actions_of_missing_panels_are_not_bound() {
+    let config = "panels = [\"home\", \"cpu\", \"queue\"]\n\n[keys]\nfleet_reverse = \"R\"\n\n[[commands]]\nname = \"Queue\"\ncommand = \"true\"\nkey = \"s\"\n".parse::<Config>().unwrap();
+    let mut panels = PanelRegistry::<TestBackend>::default();
+    panels.register_commands(&config.commands).unwrap();
+    panels.configure(config.panels.as_ref().unwrap()).unwrap();
+    let mut keymap = Keymap::new(&panels.hotkeys(), &config.keys).unwrap();
+    assert_eq!(action(keymap.feed(key('s'))), Some(Action::ShowPanel("queue".to_string())));
+    assert!(matches!(keymap.feed(key('R')), KeyResult::Unbound));
+    assert!(keymap.keys_for(&Action::FleetOpen).is_empty());
+    assert!(!keymap.keys_for(&Action::CpuView).is_empty());
+    assert!(keymap.help().iter().all(|(_, action)| action.panel() != Some("fleet")));
+}
@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+use std::time::{Duration, UNIX_EPOCH};
+use systemmor::metrics::render;
+use systemmor::snapshot::{BatterySample, BatteryState, ComponentSample, DiskSample, MemorySample, NetworkSample, ProcessSample};
+use systemmor::{Error, Subsystem, SystemSnapshot};
+
+fn component(label: &str, temperature: f32) -> ComponentSample {
+    ComponentSample { label: label.to_string(), temperature, max: 80.0, critical: None }
+}
+
+// A snapshot with a little of everything, all refreshed at the same time.
+fn sample() -> SystemSnapshot {
+    let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    SystemSnapshot {
+        timestamp: time,
+        refreshed: Subsystem::ALL.iter().map(|subsystem| (*subsystem, time)).collect(),
+        memory: MemorySample { total: 8 << 30, used: 4 << 30, total_swap: 0, used_swap: 0 },
+        networks: vec![NetworkSample { interface: "eth0".to_string(), received: 1000, transmitted: 500, total_received: 1_000_000, total_transmitted: 2000 }],
+        processes: (1..=20).map(|pid| ProcessSample { pid, name: format!("process-{}", pid), memory: (pid as u64) << 20, cpu_usage: pid as f32, disk_read: 0, disk_written: 0 }).collect(),
+        disks: vec![DiskSample { name: "/dev/sda1".to_string(), kind: "SSD".to_string(), mount_point: "/".to_string(), total_space: 256 << 30, available_space: 64 << 30 }],
+        components: vec![component("sensor 0", 40.0), component("sensor 1", 41.0)],
+        batteries: vec![BatterySample { state: BatteryState::Discharging, charge: 0.5 }],
+        ..SystemSnapshot::default()
+    }
+}
+
+// Every line is family metadata, a sample of the family declared last or
+// the closing "# EOF", and no series appears twice.
+fn check_format(text: &str) {
+    assert!(text.ends_with("# EOF\n"), "{}", text);
+    let mut families = HashSet::new();
+    let mut family = String::new();
+    let mut series = HashSet::new();
+    for line in text.lines() {
+        if line == "# EOF" {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("# TYPE ") {
+            let (name, kind) = rest.split_once(' ').unwrap();
+            assert!(["gauge", "counter", "stateset"].contains(&kind), "{}", line);
+            assert!(families.insert(name.to_string()), "family declared twice: {}", line);
+            family = name.to_string();
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("# UNIT ").or_else(|| line.strip_prefix("# HELP ")) {
+            let (name, value) = rest.split_once(' ').unwrap();
+            assert_eq!(name, family, "{}", line);
+            if line.starts_with("# UNIT ") {
+                assert!(family.ends_with(&format!("_{}", value)), "{}", line);
+            }
+            continue;
+        }
+        let (key, value) = line.rsplit_once(' ').unwrap();
+        assert!(value.parse::<f64>().is_ok(), "{}", line);
+        let name = key.split('{').next().unwrap();
+        assert!(name == family || name == format!("{}_total", family), "sample outside its family: {}", line);
+        assert!(series.insert(key.to_string()), "duplicate series: {}", line);
+    }
+}
+
+#[test]
+fn rendered_metrics_are_well_formed() {
+    let text = render(&sample(), 10);
+    check_format(&text);
+    for line in [
+        "systemmor_subsystem_up{subsystem=\"memory\"} 1",
+        "systemmor_memory_used_bytes 4294967296",
+        "systemmor_network_received_bytes_total{interface=\"eth0\"} 1000000",
+        "systemmor_disk_total_bytes{device=\"/dev/sda1\",mount_point=\"/\",kind=\"SSD\"} 274877906944",
+        "systemmor_temperature_celsius{sensor=\"sensor 1\",index=\"1\"} 41",
+        "systemmor_battery_state{battery=\"1\",systemmor_battery_state=\"discharging\"} 1",
+        "systemmor_process_cpu_usage_ratio{pid=\"20\",name=\"process-20\"} 0.2",
+    ] {
+        assert!(text.lines().any(|rendered| rendered == line), "missing {}:\n{}", line, text);
+    }
+    assert!(text.contains("# TYPE systemmor_network_received_bytes counter\n# UNIT systemmor_network_received_bytes bytes\n"));
+}
+
+#[test]
+fn sensors_sharing_a_label_get_their_own_series() {
+    let snapshot = SystemSnapshot {
+        components: vec![component("Composite", 40.0), component("Composite", 45.0), component("say \"hi\"\n", 50.0)],
+        ..sample()
+    };
+    let text = render(&snapshot, 10);
+    check_format(&text);
+    assert!(text.contains("systemmor_temperature_celsius{sensor=\"Composite\",index=\"0\"} 40\n"));
+    assert!(text.contains("systemmor_temperature_celsius{sensor=\"Composite\",index=\"1\"} 45\n"));
+    assert!(text.contains("systemmor_temperature_max_celsius{sensor=\"say \\\"hi\\\"\\n\",index=\"2\"} 80\n"), "{}", text);
+    // None of them know their critical temperature.
+    assert!(!text.contains("systemmor_temperature_critical_celsius{"));
+}
+
+#[test]
+fn unreadable_subsystems_only_report_that_they_are_down() {
+    let mut snapshot = sample();
+    snapshot.components.clear();
+    snapshot.errors.insert(Subsystem::Components, Error::NotFound(Subsystem::Components));
+    let text = render(&snapshot, 10);
+    check_format(&text);
+    assert!(text.contains("systemmor_subsystem_up{subsystem=\"components\"} 0\n"));
+    assert!(!text.contains("systemmor_temperature"));
+}
+
+#[test]
+fn only_the_busiest_processes_are_exported() {
+    let text = render(&sample(), 3);
+    let pids: Vec<&str> = text.lines()
+        .filter(|line| line.starts_with("systemmor_process_memory_bytes{"))
+        .map(|line| line.split('"').nth(1).unwrap())
+        .collect();
+    assert_eq!(pids, vec!["20", "19", "18"]);
+}
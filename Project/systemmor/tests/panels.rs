@@ -0,0 +1,329 @@
+mod common;
+
+use common::{press, render, sample};
+use crossterm::event::KeyCode;
+use ratatui::backend::TestBackend;
+use systemmor::alerts::AlertConfig;
+use systemmor::fake::FakeData;
+use systemmor::panel::*;
+use systemmor::snapshot::{BatteryState, LoadSample};
+use systemmor::{Alerts, App, Config, Error, Keymap, Subsystem};
+
+#[test]
+fn every_panel_renders_the_default_machine() {
+    let snapshot = sample(FakeData::default());
+    let mut registry = PanelRegistry::<TestBackend>::default();
+    let titles: Vec<String> = registry.panels().map(|panel| panel.title().to_string()).collect();
+    for (i, title) in titles.iter().enumerate() {
+        registry.select(i);
+        let mut app = App::default();
+        let screen = render(registry.active_mut().unwrap(), &snapshot, &mut app, 120, 40);
+        assert!(screen.contains(title.as_str()), "{} panel is missing its title:\n{}", title, screen);
+        assert!(!screen.contains("not available"), "{} panel shows an error:\n{}", title, screen);
+    }
+}
+
+#[test]
+fn panels_survive_a_tiny_terminal() {
+    let snapshot = sample(FakeData::default().with_cpus(128, 50.0).with_processes(10_000));
+    let mut registry = PanelRegistry::<TestBackend>::default();
+    for i in 0..registry.len() {
+        registry.select(i);
+        for (width, height) in [(1, 1), (10, 3), (20, 6)] {
+            render(registry.active_mut().unwrap(), &snapshot, &mut App::default(), width, height);
+        }
+    }
+}
+
+#[test]
+fn home_lists_the_key_bindings() {
+    let mut app = App { keymap: Keymap::new(&[("cpu".to_string(), 'c')], &Default::default()).unwrap(), ..App::default() };
+    let screen = render(&mut HomePanel, &sample(FakeData::default()), &mut app, 100, 30);
+    assert!(screen.contains("Press q, ctrl+c to exit."), "{}", screen);
+    assert!(screen.contains("to choose what to display"), "{}", screen);
+    assert!(screen.contains("In the CPU panel: v: view, [ ]: core, H: heatmap, B: time."), "{}", screen);
+    // Without a fleet panel its keys are unbound.
+    assert!(!screen.contains("Fleet panel"), "{}", screen);
+    let screen = render(&mut HomePanel, &sample(FakeData::default()), &mut App::default(), 100, 30);
+    assert!(screen.contains("In the Fleet panel: Enter: open, s: sort, r: reverse."), "{}", screen);
+}
+
+#[test]
+fn cpu_shows_average_and_cores() {
+    let screen = render(&mut CpuPanel::default(), &sample(FakeData::default().with_cpus(4, 37.5)), &mut App::default(), 120, 30);
+    assert!(screen.contains("Average CPU Usage: 37.50%"), "{}", screen);
+    assert!(screen.contains("CPU 3 37.50%"), "{}", screen);
+}
+
+#[test]
+fn cpu_with_128_cores() {
+    let screen = render(&mut CpuPanel::default(), &sample(FakeData::default().with_cpus(128, 99.0)), &mut App::default(), 120, 40);
+    assert!(screen.contains("Average CPU Usage: 99.00%"), "{}", screen);
+}
+
+#[test]
+fn cpu_chart_uses_a_full_scale_and_history() {
+    let snapshot = sample(FakeData::default().with_cpus(4, 90.0));
+    let mut app = App::default();
+    app.history.record(&snapshot);
+    let screen = render(&mut CpuPanel::default(), &snapshot, &mut app, 120, 40);
+    assert!(screen.contains("CPU Chart (average"), "{}", screen);
+    assert!(screen.contains("100%"), "{}", screen);
+    assert!(screen.contains("Seconds ago"), "{}", screen);
+    assert!(screen.contains("300"), "{}", screen);
+    assert!(screen.contains("Average"), "{}", screen);
+}
+
+#[test]
+fn cpu_chart_views() {
+    let snapshot = sample(FakeData::default().with_cpus(4, 50.0));
+    let mut app = App::default();
+    app.history.record(&snapshot);
+    let mut panel = CpuPanel::default();
+    render(&mut panel, &snapshot, &mut app, 120, 40);
+
+    let view = |panel: &mut CpuPanel, app: &mut App, key: char| {
+        assert!(press(panel, app, KeyCode::Char(key)));
+        panel.view()
+    };
+    assert_eq!(view(&mut panel, &mut app, 'v'), CpuView::AllCores);
+    assert_eq!(view(&mut panel, &mut app, 'v'), CpuView::Core(0));
+    assert_eq!(view(&mut panel, &mut app, ']'), CpuView::Core(1));
+    assert_eq!(view(&mut panel, &mut app, '['), CpuView::Core(0));
+    assert_eq!(view(&mut panel, &mut app, '['), CpuView::Core(3));
+    assert_eq!(view(&mut panel, &mut app, ']'), CpuView::Core(0));
+    assert_eq!(view(&mut panel, &mut app, 'v'), CpuView::Average);
+    assert_eq!(view(&mut panel, &mut app, '['), CpuView::Core(3));
+
+    let screen = render(&mut panel, &snapshot, &mut app, 120, 40);
+    assert!(screen.contains("CPU Chart (CPU 3; v: view, [ ]: core)"), "{}", screen);
+    view(&mut panel, &mut app, 'v');
+    view(&mut panel, &mut app, 'v');
+    let screen = render(&mut panel, &snapshot, &mut app, 120, 40);
+    assert!(screen.contains("CPU Chart (all cores"), "{}", screen);
+    assert!(screen.contains("CPU 2"), "{}", screen);
+}
+
+#[test]
+fn cpu_keys_follow_the_key_map() {
+    let keys = "[keys]\ncpu_view = \"g v\"\ncpu_next_core = []\n".parse::<Config>().unwrap().keys;
+    let mut app = App { keymap: Keymap::new(&[("cpu".to_string(), 'c')], &keys).unwrap(), ..App::default() };
+    let mut panel = CpuPanel::default();
+    assert!(!press(&mut panel, &mut app, KeyCode::Char('v')));
+    assert!(!press(&mut panel, &mut app, KeyCode::Char(']')));
+    assert!(!press(&mut panel, &mut app, KeyCode::Char('g')));
+    assert!(press(&mut panel, &mut app, KeyCode::Char('v')));
+    assert_eq!(panel.view(), CpuView::AllCores);
+
+    let snapshot = sample(FakeData::default());
+    app.history.record(&snapshot);
+    let screen = render(&mut panel, &snapshot, &mut app, 120, 40);
+    // Only complete hints are shown.
+    assert!(screen.contains("CPU Chart (all cores; g v: view)"), "{}", screen);
+}
+
+#[test]
+fn cpu_graph_keys_follow_the_key_map() {
+    let keys = "[keys]\ncpu_heatmap = \"m\"\ncpu_breakdown = \"t\"\n".parse::<Config>().unwrap().keys;
+    let mut app = App { keymap: Keymap::new(&[("cpu".to_string(), 'c')], &keys).unwrap(), ..App::default() };
+    let snapshot = sample(FakeData::default());
+    app.history.record(&snapshot);
+    let mut panel = CpuPanel::default();
+    let screen = render(&mut panel, &snapshot, &mut app, 120, 40);
+    assert!(screen.contains("CPU Bar Graph (m: heatmap, t: time)"), "{}", screen);
+
+    assert!(!press(&mut panel, &mut app, KeyCode::Char('H')));
+    assert!(!press(&mut panel, &mut app, KeyCode::Char('B')));
+    assert!(press(&mut panel, &mut app, KeyCode::Char('m')));
+    let screen = render(&mut panel, &snapshot, &mut app, 120, 40);
+    assert!(screen.contains("CPU Heatmap (m: bars)"), "{}", screen);
+    assert!(press(&mut panel, &mut app, KeyCode::Char('t')));
+    let screen = render(&mut panel, &snapshot, &mut app, 120, 40);
+    assert!(screen.contains("CPU Time (t: bars)"), "{}", screen);
+
+    app.keymap = Keymap::new(&[], &"[keys]\ncpu_breakdown = []\n".parse::<Config>().unwrap().keys).unwrap();
+    let screen = render(&mut panel, &snapshot, &mut app, 120, 40);
+    assert!(screen.contains("CPU Time─"), "{}", screen);
+}
+
+#[test]
+fn cpu_heatmap_fits_every_core() {
+    let snapshot = sample(FakeData::default().with_cpus(128, 95.0));
+    let mut app = App::default();
+    app.history.record(&snapshot);
+    let mut panel = CpuPanel::default();
+    let screen = render(&mut panel, &snapshot, &mut app, 120, 40);
+    assert!(screen.contains("of 128 cores; H: heatmap"), "{}", screen);
+
+    assert!(press(&mut panel, &mut app, KeyCode::Char('H')));
+    assert_eq!(panel.graph(), CpuGraph::Heatmap);
+    let screen = render(&mut panel, &snapshot, &mut app, 120, 40);
+    assert!(screen.contains("CPU Heatmap (busiest of 15 cores per row"), "{}", screen);
+    assert!(screen.contains("C0-14 "), "{}", screen);
+    assert!(screen.contains("cores per row; H: bars)"), "{}", screen);
+    // A single sample, in the newest column.
+    assert!(screen.lines().any(|line| line.contains("C120-127") && line.ends_with("█│")), "{}", screen);
+    assert!(screen.contains("█ 80%+"), "{}", screen);
+}
+
+#[test]
+fn cpu_shows_model_and_clock() {
+    let snapshot = sample(FakeData::default().with_cpus(4, 50.0).with_frequency(3600));
+    let mut app = App::default();
+    app.history.record(&snapshot);
+    let screen = render(&mut CpuPanel::default(), &snapshot, &mut app, 120, 40);
+    assert!(screen.contains("CPU Usage (Fake CPU @ 2.40GHz)"), "{}", screen);
+    assert!(screen.contains("CPU 3 50.00% 3.60 GHz"), "{}", screen);
+    assert!(screen.contains("Clock range: 800 MHz-4.20 GHz"), "{}", screen);
+    assert!(screen.contains("CPU Clock"), "{}", screen);
+    assert!(screen.contains("Fastest core"), "{}", screen);
+    assert_eq!(app.history.cpu_frequency_peak.latest(), Some(3600.0));
+
+    // Without a clock there is no clock chart.
+    let snapshot = sample(FakeData::default().with_cpus(4, 50.0).with_frequency(0));
+    let screen = render(&mut CpuPanel::default(), &snapshot, &mut App::default(), 120, 40);
+    assert!(!screen.contains("Average clock"), "{}", screen);
+    assert!(!screen.contains("CPU Clock"), "{}", screen);
+}
+
+#[test]
+fn cpu_shows_load_per_core() {
+    let load = LoadSample { one: 6.0, five: 4.0, fifteen: 2.0, running: Some(5), blocked: Some(1), tasks: Some(250) };
+    let snapshot = sample(FakeData::default().with_cpus(4, 50.0).with_load(Some(load)));
+    let mut app = App::default();
+    app.history.record(&snapshot);
+    assert_eq!(app.history.load_one.latest(), Some(1.5));
+    assert_eq!(app.history.tasks_running.latest(), Some(1.25));
+    let screen = render(&mut CpuPanel::default(), &snapshot, &mut app, 120, 40);
+    assert!(screen.contains("Load: 6.00 4.00 2.00 (1.50/core)"), "{}", screen);
+    assert!(screen.contains("Tasks: 5 running, 1 blocked of 250"), "{}", screen);
+    assert!(screen.contains("Load per Core"), "{}", screen);
+    // Overloaded, so the scale grows past one per core.
+    assert!(screen.contains("2.0"), "{}", screen);
+
+    let snapshot = sample(FakeData::default().with_load(None).with_frequency(0));
+    let screen = render(&mut CpuPanel::default(), &snapshot, &mut App::default(), 120, 40);
+    assert!(!screen.contains("Load"), "{}", screen);
+}
+
+#[test]
+fn cpu_time_breakdown() {
+    let snapshot = sample(FakeData::default().with_cpus(4, 50.0));
+    let mut app = App::default();
+    app.history.record(&snapshot);
+    assert_eq!(app.history.cpu_times[0].latest().map(f64::round), Some(30.0));
+    let mut panel = CpuPanel::default();
+    let graph = |panel: &mut CpuPanel, key: char| {
+        assert!(press(panel, &mut App::default(), KeyCode::Char(key)));
+        panel.graph()
+    };
+    assert_eq!(graph(&mut panel, 'B'), CpuGraph::Breakdown);
+    assert_eq!(graph(&mut panel, 'H'), CpuGraph::Heatmap);
+    assert_eq!(graph(&mut panel, 'B'), CpuGraph::Breakdown);
+
+    let screen = render(&mut panel, &snapshot, &mut app, 120, 40);
+    assert!(screen.contains("CPU Time (B: bars)"), "{}", screen);
+    assert!(screen.contains("CPU Time History"), "{}", screen);
+    assert!(screen.contains("▒ iowait"), "{}", screen);
+    // 50% busy on a 36-cell bar: 30% user is 11 cells, and steal rounds
+    // away.
+    assert!(screen.contains("All ███████████▓▓▓▒▒░▞ "), "{}", screen);
+    assert!(screen.lines().any(|line| line.contains("0%") && line.ends_with("█│")), "{}", screen);
+
+    let snapshot = sample(FakeData::default().with_times(None));
+    let screen = render(&mut panel, &snapshot, &mut App::default(), 120, 40);
+    assert!(screen.contains("Not available"), "{}", screen);
+    assert_eq!(graph(&mut panel, 'B'), CpuGraph::Bars);
+}
+
+#[test]
+fn memory_shows_used_and_available() {
+    let screen = render(&mut MemoryPanel, &sample(FakeData::default()), &mut App::default(), 250, 30);
+    assert!(screen.contains("Memory: 4.00 GiB / 8.00 GiB"), "{}", screen);
+    assert!(screen.contains("Available memory: 4.00 GiB"), "{}", screen);
+}
+
+#[test]
+fn network_lists_interfaces() {
+    let screen = render(&mut NetworkPanel, &sample(FakeData::default().with_networks(3)), &mut App::default(), 120, 40);
+    for interface in ["eth0", "eth1", "eth2"] {
+        assert!(screen.contains(interface), "{} missing:\n{}", interface, screen);
+    }
+}
+
+#[test]
+fn process_scrolls_through_10k_processes() {
+    let snapshot = sample(FakeData::default().with_processes(10_000));
+    let mut app = App::default();
+    let screen = render(&mut ProcessPanel, &snapshot, &mut app, 120, 20);
+    assert!(screen.contains("process-1 "), "{}", screen);
+    assert!(!screen.contains("process-10000"), "{}", screen);
+
+    for _ in 0..9_990 {
+        app.scroll_down();
+    }
+    let screen = render(&mut ProcessPanel, &snapshot, &mut app, 120, 20);
+    assert!(screen.contains("process-10000"), "{}", screen);
+}
+
+#[test]
+fn disk_shows_the_first_disk() {
+    let screen = render(&mut DiskPanel, &sample(FakeData::default().with_disks(2)), &mut App::default(), 250, 40);
+    assert!(screen.contains("Name: \"/dev/sda1\""), "{}", screen);
+    assert!(screen.contains("Used space: 192.00 GiB"), "{}", screen);
+    assert!(screen.contains("75%"), "{}", screen);
+}
+
+#[test]
+fn zero_disks_is_reported_as_unavailable() {
+    let snapshot = sample(FakeData::default().with_disks(0));
+    assert_eq!(snapshot.error(Subsystem::Disks), Some(&Error::NotFound(Subsystem::Disks)));
+    let screen = render(&mut DiskPanel, &snapshot, &mut App::default(), 120, 20);
+    assert!(screen.contains("Disk information is not available on this system."), "{}", screen);
+}
+
+#[test]
+fn temperature_lists_sensors() {
+    let screen = render(&mut TemperaturePanel, &sample(FakeData::default().with_components(3)), &mut App::default(), 120, 30);
+    assert!(screen.contains("sensor 2"), "{}", screen);
+}
+
+#[test]
+fn battery_shows_state_and_charge() {
+    let snapshot = sample(FakeData::default().with_battery(BatteryState::Charging, 0.42));
+    let screen = render(&mut BatteryPanel, &snapshot, &mut App::default(), 250, 30);
+    assert!(screen.contains("Battery 1: Charging, Current Battery 42.00%"), "{}", screen);
+}
+
+#[test]
+fn no_battery_is_reported_as_unavailable() {
+    let screen = render(&mut BatteryPanel, &sample(FakeData::default().without_batteries()), &mut App::default(), 120, 20);
+    assert!(screen.contains("Battery information is not available on this system."), "{}", screen);
+    assert!(screen.contains("no batteries found"), "{}", screen);
+}
+
+#[test]
+fn backend_errors_are_shown_in_the_panel() {
+    let data = FakeData { batteries: Err(Error::Battery("permission denied".to_string())), ..FakeData::default() };
+    let screen = render(&mut BatteryPanel, &sample(data), &mut App::default(), 120, 20);
+    assert!(screen.contains("battery backend error: permission denied"), "{}", screen);
+}
+
+#[test]
+fn alerts_lists_rules_and_their_state() {
+    let config = AlertConfig { name: "busy".to_string(), when: "cpu > 90".to_string(), hold: Default::default() };
+    let mut app = App { alerts: Alerts::new(&[config]).unwrap(), ..App::default() };
+    let snapshot = sample(FakeData::default().with_cpus(8, 95.0));
+    app.alerts.evaluate(&snapshot);
+
+    let screen = render(&mut AlertsPanel, &snapshot, &mut app, 120, 20);
+    assert!(screen.contains("busy"), "{}", screen);
+    assert!(screen.contains("firing since"), "{}", screen);
+    assert!(screen.contains("still firing"), "{}", screen);
+}
+
+#[test]
+fn alerts_without_rules() {
+    let screen = render(&mut AlertsPanel, &sample(FakeData::default()), &mut App::default(), 120, 20);
+    assert!(screen.contains("No alerts are configured"), "{}", screen);
+}
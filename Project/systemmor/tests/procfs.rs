@@ -0,0 +1,48 @@
+use systemmor::procfs::{parse_loadavg, parse_stat_cpus, parse_stat_tasks, CpuTicks};
+use systemmor::snapshot::{CpuTimes, LoadSample};
+
+const STAT: &str = "cpu  10132153 290696 3084719 46828483 16683 0 25195 0 0 0
+cpu0 1393280 32966 572056 13343292 6130 0 17875 0 0 0
+intr 1462898 0 0 0
+ctxt 2136219547
+btime 1699990000
+processes 1268211
+procs_running 3
+procs_blocked 2
+softirq 130237548 4 4 0 0 0 0 0 0 0 0
+";
+
+#[test]
+fn load_averages_and_tasks() {
+    assert_eq!(parse_loadavg("0.52 0.58 0.59 2/1234 5678\n"), Some(LoadSample {
+        one: 0.52,
+        five: 0.58,
+        fifteen: 0.59,
+        running: Some(2),
+        blocked: None,
+        tasks: Some(1234),
+    }));
+    assert_eq!(parse_loadavg("4.00 2.00 1.00").map(|load| (load.fifteen, load.tasks)), Some((1.0, None)));
+    assert_eq!(parse_loadavg("0.52 0.58"), None);
+    assert_eq!(parse_loadavg(""), None);
+
+    assert_eq!(parse_stat_tasks(STAT), (Some(3), Some(2)));
+    assert_eq!(parse_stat_tasks("procs_running_total 9\n"), (None, None));
+}
+
+#[test]
+fn cpu_time_breakdown() {
+    let cores = parse_stat_cpus(STAT);
+    assert_eq!(cores.len(), 1);
+    assert_eq!(cores[0].system, 572056);
+    assert_eq!(cores[0].guest_nice, 0);
+    // Kernels before 2.6.33 have no guest_nice column.
+    assert_eq!(parse_stat_cpus("cpu 1 2 3 4\ncpu7 1 2 3 4 5 6 7 8 9\ncpufreq 1\n")[0].guest, 9);
+
+    let previous = CpuTicks::default();
+    // 200 ticks: 60 user of which 20 in a guest, 20 niced, 40 system, 10
+    // iowait, 5 irq, 5 softirq, 10 steal and 50 idle.
+    let current = CpuTicks { user: 60, nice: 20, system: 40, idle: 50, iowait: 10, irq: 5, softirq: 5, steal: 10, guest: 20, guest_nice: 0 };
+    assert_eq!(current.times_since(&previous), Some(CpuTimes { user: 30.0, system: 20.0, iowait: 5.0, irq: 5.0, steal: 5.0, guest: 10.0 }));
+    assert_eq!(current.times_since(&current), None);
+}
@@ -0,0 +1,165 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use systemmor::json::{to_json_line, Document};
+use systemmor::replay::{Recorder, Replay};
+use systemmor::snapshot::{BatterySample, BatteryState, MemorySample};
+use systemmor::{Error, History, Subsystem, SystemSnapshot};
+
+fn time(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(1_700_000_000 + seconds)
+}
+
+// A snapshot with the battery `charge` percent full and memory `memory`
+// full, with the given subsystems refreshed at `seconds`.
+fn at(seconds: u64, charge: f32, memory: f64, refreshed: &[Subsystem]) -> SystemSnapshot {
+    SystemSnapshot {
+        timestamp: time(seconds),
+        refreshed: refreshed.iter().map(|subsystem| (*subsystem, time(seconds))).collect(),
+        memory: MemorySample { total: 1000, used: (memory * 1000.0) as u64, total_swap: 0, used_swap: 0 },
+        batteries: vec![BatterySample { state: BatteryState::Discharging, charge: charge / 100.0 }],
+        ..SystemSnapshot::default()
+    }
+}
+
+// A recording with the battery sampled every second and memory every
+// third, so most lines only hold the battery.
+fn recording(seconds: u64) -> Replay {
+    let lines: Vec<String> = (0..seconds).map(|second| {
+        let subsystems: &[Subsystem] = if second % 3 == 0 { &[Subsystem::Batteries, Subsystem::Memory] } else { &[Subsystem::Batteries] };
+        to_json_line(&Document::new(&at(second, second as f32, second as f64 / 100.0, subsystems), subsystems)).unwrap()
+    }).collect();
+    Replay::new(lines.iter().map(|line| serde_json::from_str(line).unwrap()).collect()).unwrap()
+}
+
+fn charge(snapshot: &SystemSnapshot) -> f32 {
+    (snapshot.batteries[0].charge * 100.0).round()
+}
+
+fn memory(snapshot: &SystemSnapshot) -> f64 {
+    (snapshot.memory.used_ratio() * 100.0).round()
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("systemmor-test-{}-{}.ndjson", name, std::process::id()))
+}
+
+#[test]
+fn partial_lines_are_merged_into_the_snapshot() {
+    let mut replay = recording(10);
+    let mut history = History::default();
+    assert_eq!(replay.position(), time(0));
+    assert_eq!((charge(replay.snapshot()), memory(replay.snapshot())), (0.0, 0.0));
+
+    replay.seek(time(4), &mut history);
+    // Battery from the line at 4s, memory from the last line that had it.
+    assert_eq!((charge(replay.snapshot()), memory(replay.snapshot())), (4.0, 3.0));
+    assert_eq!(replay.snapshot().refreshed_at(Subsystem::Memory), Some(time(3)));
+    assert_eq!(replay.snapshot().refreshed_at(Subsystem::Batteries), Some(time(4)));
+    // Never recorded, so reported rather than shown as empty.
+    assert!(replay.snapshot().error(Subsystem::Processes).is_some());
+}
+
+#[test]
+fn seeking_lands_on_the_sample_at_or_before_the_target() {
+    let mut replay = recording(100);
+    let mut history = History::new(20, Duration::from_secs(1));
+
+    replay.seek(time(50) + Duration::from_millis(500), &mut history);
+    assert_eq!(charge(replay.snapshot()), 50.0);
+    assert_eq!(memory(replay.snapshot()), 48.0);
+    // The history is rebuilt from the samples that fit in it.
+    assert_eq!(history.battery_charge[0].len(), 20);
+    assert_eq!(history.battery_charge[0].latest(), Some(50.0));
+
+    replay.seek(time(10), &mut history);
+    assert_eq!((charge(replay.snapshot()), memory(replay.snapshot())), (10.0, 9.0));
+    assert_eq!(history.battery_charge[0].iter().next().map(|(_, value)| *value), Some(0.0));
+    assert_eq!(history.battery_charge[0].len(), 11);
+
+    // Short jumps forward play through and keep the history.
+    replay.seek(time(15), &mut history);
+    assert_eq!(charge(replay.snapshot()), 15.0);
+    assert_eq!(history.battery_charge[0].len(), 16);
+
+    // Targets outside the recording are clamped to it.
+    replay.seek(time(500), &mut history);
+    assert_eq!(replay.position(), time(99));
+    assert!(replay.is_finished());
+    replay.seek(UNIX_EPOCH, &mut history);
+    assert_eq!(replay.position(), time(0));
+    assert_eq!(charge(replay.snapshot()), 0.0);
+}
+
+#[test]
+fn seek_steps_scale_with_the_speed() {
+    let mut replay = recording(100);
+    let mut history = History::default();
+    replay.seek_by(1, &mut history);
+    assert_eq!(replay.position(), time(10));
+    replay.faster();
+    replay.seek_by(2, &mut history);
+    assert_eq!(replay.position(), time(50));
+    replay.seek_by(-1, &mut history);
+    assert_eq!(replay.position(), time(30));
+    assert_eq!(charge(replay.snapshot()), 30.0);
+}
+
+#[test]
+fn advancing_applies_the_samples_that_are_due() {
+    let mut replay = recording(10);
+    let mut history = History::default();
+    assert!(!replay.advance(Duration::from_millis(500), &mut history));
+    assert!(replay.advance(Duration::from_millis(600), &mut history));
+    assert_eq!(charge(replay.snapshot()), 1.0);
+    replay.toggle_pause();
+    assert!(!replay.advance(Duration::from_secs(5), &mut history));
+    replay.toggle_pause();
+    assert!(replay.advance(Duration::from_secs(60), &mut history));
+    assert_eq!(replay.position(), time(9));
+    assert!(replay.is_finished());
+}
+
+#[test]
+fn recordings_only_hold_what_changed() {
+    let path = temp_path("record");
+    let _ = std::fs::remove_file(&path);
+    let mut recorder = Recorder::create(&path).unwrap();
+    let mut first = at(0, 10.0, 0.5, &[Subsystem::Batteries, Subsystem::Memory]);
+    recorder.record(&first).unwrap();
+    // Nothing refreshed since, so no line.
+    recorder.record(&first).unwrap();
+    first.refreshed.insert(Subsystem::Batteries, time(1));
+    first.timestamp = time(1);
+    first.batteries[0].charge = 0.2;
+    recorder.record(&first).unwrap();
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("\"memory\"") && lines[0].contains("\"batteries\""));
+    assert!(!lines[1].contains("\"memory\"") && lines[1].contains("\"batteries\""));
+
+    let mut replay = Replay::load(&path).unwrap();
+    replay.seek(time(1), &mut History::default());
+    assert_eq!((charge(replay.snapshot()), memory(replay.snapshot())), (20.0, 50.0));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn bad_recordings_are_reported_with_their_line() {
+    let path = temp_path("bad");
+    let good = to_json_line(&Document::new(&at(0, 10.0, 0.5, &[Subsystem::Batteries]), &[Subsystem::Batteries])).unwrap();
+    let newer = good.replacen("\"version\":1", "\"version\":99", 1);
+    for (contents, expected) in [
+        ("".to_string(), "no samples".to_string()),
+        (format!("{}\n\nnot json\n", good), ":3: ".to_string()),
+        (format!("{}\n{}\n", good, newer), ":2: format version 99 is newer".to_string()),
+    ] {
+        std::fs::write(&path, contents).unwrap();
+        match Replay::load(&path) {
+            Err(Error::Recording(message)) => assert!(message.contains(&expected), "{}", message),
+            other => panic!("expected a recording error, got {:?}", other.map(|_| ())),
+        }
+    }
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use systemmor::fake::FakeData;
+use systemmor::report::report;
+use systemmor::{Subsystem, Units};
+use common::sample;
+
+#[test]
+fn reports_only_the_requested_subsystems() {
+    let snapshot = sample(FakeData::default().with_cpus(2, 50.0));
+    let text = report(&snapshot, &[Subsystem::Memory, Subsystem::Cpu], &Units::default());
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "cpu: 50.0% average over 2 cores");
+    assert!(lines.contains(&"  load 1.50 1.00 0.50, 2 running, 1 blocked"), "{}", text);
+    assert!(lines.contains(&"  cpu 1    50.0%  2.40 GHz"), "{}", text);
+    assert!(lines.contains(&"memory: 4.00 GiB / 8.00 GiB used, 4.00 GiB available"), "{}", text);
+    assert!(!text.contains("processes:") && !text.contains("batteries:"), "{}", text);
+}
+
+#[test]
+fn lists_the_busiest_processes_first() {
+    let text = report(&sample(FakeData::default()), &[Subsystem::Processes], &Units::default());
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "processes: 20 total");
+    assert_eq!(lines.len(), 11);
+    assert!(lines[1].contains("process-20") && lines[10].contains("process-11"), "{}", text);
+}
+
+#[test]
+fn unreadable_subsystems_give_the_reason() {
+    let snapshot = sample(FakeData::default().without_batteries());
+    let text = report(&snapshot, &Subsystem::ALL, &Units::default());
+    assert!(text.ends_with("batteries: not available (no batteries found)\n"), "{}", text);
+    assert!(text.contains("components: 2 sensors\n  sensor 0                 40.0°C\n"), "{}", text);
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+use systemmor::fake::{FakeData, FakeSource};
+use systemmor::snapshot::MemorySample;
+use systemmor::{Error, Sampler, Subsystem};
+
+fn sampler(steps: Vec<FakeData>) -> Sampler {
+    let mut sampler = Sampler::with_source(Box::new(FakeSource::scenario(steps)));
+    sampler.set_background_interval(None);
+    for subsystem in Subsystem::ALL {
+        sampler.set_cadence(subsystem, Duration::ZERO);
+    }
+    sampler
+}
+
+#[test]
+fn only_needed_subsystems_are_refreshed() {
+    let mut sampler = sampler(vec![FakeData::default()]);
+    let snapshot = sampler.sample(&[Subsystem::Cpu, Subsystem::Memory]);
+    assert_eq!(snapshot.refreshed.keys().copied().collect::<Vec<_>>(), vec![Subsystem::Cpu, Subsystem::Memory]);
+    assert_eq!(snapshot.cpus.len(), 4);
+    assert!(snapshot.processes.is_empty());
+}
+
+#[test]
+fn scenario_steps_are_played_in_order() {
+    let mut sampler = sampler(vec![
+        FakeData::default().with_cpus(2, 10.0),
+        FakeData::default().with_cpus(2, 50.0),
+        FakeData::default().with_cpus(2, 90.0),
+    ]);
+    let averages: Vec<f32> = (0..4).map(|_| sampler.sample(&[Subsystem::Cpu]).cpu_average()).collect();
+    assert_eq!(averages, vec![10.0, 50.0, 90.0, 90.0]);
+}
+
+#[test]
+fn cadence_holds_back_refreshes() {
+    let mut sampler = sampler(vec![FakeData::default().with_processes(1), FakeData::default().with_processes(2)]);
+    sampler.set_cadence(Subsystem::Processes, Duration::from_secs(60));
+    assert_eq!(sampler.sample(&[Subsystem::Processes]).processes.len(), 1);
+    assert_eq!(sampler.sample(&[Subsystem::Processes]).processes.len(), 1);
+}
+
+#[test]
+fn background_refreshes_unneeded_subsystems() {
+    let mut sampler = sampler(vec![FakeData::default()]);
+    sampler.set_background_interval(Some(Duration::from_secs(60)));
+    let snapshot = sampler.sample(&[Subsystem::Cpu]);
+    assert_eq!(snapshot.refreshed.len(), Subsystem::ALL.len());
+}
+
+#[test]
+fn errors_clear_when_the_subsystem_recovers() {
+    let broken = FakeData { disks: Err(Error::NotFound(Subsystem::Disks)), ..FakeData::default() };
+    let mut sampler = sampler(vec![FakeData::default(), broken, FakeData::default()]);
+
+    let snapshot = sampler.sample(&[Subsystem::Disks]);
+    assert!(snapshot.error(Subsystem::Disks).is_none());
+    let snapshot = sampler.sample(&[Subsystem::Disks]);
+    assert_eq!(snapshot.error(Subsystem::Disks), Some(&Error::NotFound(Subsystem::Disks)));
+    assert!(snapshot.disks.is_empty(), "stale disks kept after an error");
+    let snapshot = sampler.sample(&[Subsystem::Disks]);
+    assert!(snapshot.error(Subsystem::Disks).is_none());
+    assert_eq!(snapshot.disks.len(), 1);
+}
+
+#[test]
+fn empty_lists_and_zero_memory_are_not_found() {
+    let data = FakeData {
+        memory: Ok(MemorySample::default()),
+        ..FakeData::default().with_cpus(0, 0.0).with_networks(0).with_processes(0).with_components(0).without_batteries()
+    };
+    let snapshot = sampler(vec![data]).sample_all();
+    for subsystem in [Subsystem::Cpu, Subsystem::Memory, Subsystem::Networks, Subsystem::Processes, Subsystem::Components, Subsystem::Batteries] {
+        assert_eq!(snapshot.error(subsystem), Some(&Error::NotFound(subsystem)));
+    }
+    assert!(snapshot.error(Subsystem::Disks).is_none());
+}
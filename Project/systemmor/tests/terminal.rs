@@ -0,0 +1,24 @@
+use systemmor::terminal::{is_active, restore, TerminalGuard};
+
+#[test]
+fn restoring_twice_is_a_no_op() {
+    assert!(!is_active());
+    restore();
+    restore();
+    assert!(!is_active());
+}
+
+// Only runs without a controlling terminal, as in CI, where entering
+// fails.
+#[test]
+fn a_failed_enter_leaves_nothing_to_restore() {
+    if std::fs::File::open("/dev/tty").is_ok() {
+        return;
+    }
+    if let Ok(guard) = TerminalGuard::enter() {
+        drop(guard);
+    }
+    assert!(!is_active());
+    restore();
+    assert!(!is_active());
+}
@@ -0,0 +1,66 @@
+use ratatui::style::{Color, Modifier};
+use systemmor::theme::no_color;
+use systemmor::{Config, Error, Theme, Themes};
+
+fn themes(toml: &str, no_color: bool) -> systemmor::Result<Themes> {
+    let config: Config = toml.parse()?;
+    Themes::new(config.theme.as_deref(), &config.themes, no_color)
+}
+
+fn config_error(result: systemmor::Result<Themes>) -> String {
+    match result {
+        Err(Error::Config(message)) => message,
+        other => panic!("expected a config error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn overrides_accept_every_colour_format() {
+    let themes = themes("theme = \"mine\"\n\n[themes.mine]\nbase = \"light\"\ntext = \"#839496\"\nbar = \"208\"\naxis = \"lightblue\"\nbackground = \"#002b36\"\n", false).unwrap();
+    let theme = themes.active();
+    assert_eq!(theme.name, "mine");
+    assert_eq!(theme.text.fg, Some(Color::Rgb(0x83, 0x94, 0x96)));
+    assert_eq!(theme.text.bg, Some(Color::Rgb(0x00, 0x2b, 0x36)));
+    assert_eq!(theme.bar.fg, Some(Color::Indexed(208)));
+    assert_eq!(theme.axis.fg, Some(Color::LightBlue));
+    // Everything else comes from the base.
+    assert_eq!(theme.title, Theme::light().title);
+    assert_eq!(themes.names().collect::<Vec<_>>(), vec!["dark", "light", "high-contrast", "mine"]);
+}
+
+#[test]
+fn user_themes_replace_builtins_of_the_same_name() {
+    let themes = themes("[themes.dark]\ngauge = \"red\"\n", false).unwrap();
+    assert_eq!(themes.names().collect::<Vec<_>>(), vec!["dark", "light", "high-contrast"]);
+    assert_eq!(themes.active().gauge.fg, Some(Color::Red));
+}
+
+#[test]
+fn invalid_themes_are_reported() {
+    let message = config_error(themes("[themes.mine]\ntext = \"#12345\"\n", false));
+    assert!(message.contains("invalid colour '#12345'"), "{}", message);
+    let message = config_error(themes("[themes.mine]\nbase = \"sepia\"\n", false));
+    assert!(message.contains("unknown base theme 'sepia' for theme 'mine' (available: dark, light, high-contrast)"), "{}", message);
+    let message = config_error(themes("theme = \"sepia\"\n\n[themes.mine]\n", false));
+    assert_eq!(message, "unknown theme 'sepia' (available: dark, light, high-contrast, mine)");
+}
+
+#[test]
+fn no_color_strips_every_theme() {
+    std::env::set_var("NO_COLOR", "1");
+    assert!(no_color());
+    std::env::set_var("NO_COLOR", "");
+    assert!(!no_color());
+    std::env::remove_var("NO_COLOR");
+
+    let mut themes = themes("[themes.mine]\ntext = \"#839496\"\nbackground = \"#002b36\"\n", true).unwrap();
+    for _ in 0..4 {
+        let theme = themes.cycle().clone();
+        for style in [theme.text, theme.title, theme.highlight, theme.key, theme.gauge, theme.battery, theme.bar, theme.chart, theme.axis, theme.error] {
+            assert_eq!((style.fg, style.bg), (None, None), "{}", theme.name);
+        }
+        // What colour told apart is kept with modifiers.
+        assert!(theme.highlight.add_modifier.contains(Modifier::REVERSED), "{}", theme.name);
+        assert!(theme.key.add_modifier.contains(Modifier::BOLD), "{}", theme.name);
+    }
+}